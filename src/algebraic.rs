@@ -1,25 +1,29 @@
 use crate::algebraic::ComplexFactor::{
-    AddSub, Divide, Factorial, Fibonacci, Lucas, Multiply, Power, Primorial,
+    AddSub, Divide, Factorial, Fibonacci, Lucas, Modulo, Multiply, Pell, Power, Primorial,
+    Subfactorial, Tribonacci,
 };
 use crate::algebraic::Factor::{Complex, ElidedNumber, Numeric, UnknownExpression};
-use crate::{create_cache, get_from_cache, BasicCache};
 use crate::net::BigNumber;
-use crate::{NumberLength, hash, write_bignum};
+use crate::{BasicCache, create_cache, get_from_cache};
+use crate::{MAX_BIGNUM_FACTORIAL_TERM, MAX_BIGNUM_POWER_BITS, NumberLength, hash, write_bignum};
 use ahash::{HashMap, HashMapExt};
 use derivative::Derivative;
 use hipstr::HipStr;
 use itertools::Either::{Left, Right};
 use itertools::Itertools;
 use log::{debug, error, info, warn};
+use num_bigint::BigUint;
 use num_integer::Integer;
 use num_modular::{
-    FixedMersenneInt, ModularInteger, MontgomeryInt, ReducedInt, Reducer, VanillaInt,
+    FixedMersenneInt, ModularCoreOps, ModularInteger, MontgomeryInt, ReducedInt, Reducer,
+    VanillaInt,
 };
 use num_prime::ExactRoots;
 use num_prime::Primality::No;
 use num_prime::buffer::{NaiveBuffer, PrimeBufferExt};
 use num_prime::detail::SMALL_PRIMES;
 use num_prime::nt_funcs::factorize128;
+use primitive_types::U512;
 use std::borrow::Cow;
 use std::cell::RefCell;
 use std::cmp::{Ordering, PartialEq, Reverse};
@@ -30,6 +34,9 @@ use std::fmt::{Display, Formatter};
 use std::hash::{Hash, Hasher};
 use std::hint::unreachable_unchecked;
 use std::mem::swap;
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::rc::Rc;
+use std::sync::atomic::Ordering::Acquire;
 use std::sync::{Arc, LazyLock, OnceLock};
 use tokio::task;
 use tokio::time::Instant;
@@ -592,6 +599,13 @@ pub enum FactorBeingParsed {
     Lucas(Box<FactorBeingParsed>),
     Factorial(Box<FactorBeingParsed>),
     Primorial(Box<FactorBeingParsed>),
+    Subfactorial(Box<FactorBeingParsed>),
+    Tribonacci(Box<FactorBeingParsed>),
+    Pell(Box<FactorBeingParsed>),
+    Modulo {
+        left: Box<FactorBeingParsed>,
+        right: Box<FactorBeingParsed>,
+    },
 }
 
 impl Default for FactorBeingParsed {
@@ -623,10 +637,18 @@ pub enum ComplexFactor {
     Lucas(Factor),
     Factorial(Factor),
     Primorial(Factor),
+    Subfactorial(Factor),
+    Tribonacci(Factor),
+    Pell(Factor),
     Power {
         base: Factor,
         exponent: Factor,
     },
+    /// `left % right`, the residue of `left` modulo `right`.
+    Modulo {
+        left: Factor,
+        right: Factor,
+    },
 }
 
 impl PartialOrd for ComplexFactor {
@@ -646,6 +668,10 @@ impl ComplexFactor {
             Lucas(_) => 5,
             Primorial(_) => 6,
             Factorial(_) => 7,
+            Subfactorial(_) => 8,
+            Tribonacci(_) => 9,
+            Pell(_) => 10,
+            Modulo { .. } => 11,
         }
     }
 }
@@ -720,11 +746,24 @@ impl Ord for ComplexFactor {
                                 .then_with(|| base.cmp(other_base));
                         }
                     }
-                    Fibonacci(input) | Lucas(input) | Factorial(input) | Primorial(input) => {
+                    Modulo { left, right } => {
+                        if let Modulo {
+                            left: other_left,
+                            right: other_right,
+                        } = other
+                        {
+                            return right.cmp(other_right).then_with(|| left.cmp(other_left));
+                        }
+                    }
+                    Fibonacci(input) | Lucas(input) | Factorial(input) | Primorial(input)
+                    | Subfactorial(input) | Tribonacci(input) | Pell(input) => {
                         if let Fibonacci(other_input)
                         | Lucas(other_input)
                         | Factorial(other_input)
-                        | Primorial(other_input) = other
+                        | Primorial(other_input)
+                        | Subfactorial(other_input)
+                        | Tribonacci(other_input)
+                        | Pell(other_input) = other
                         {
                             return input.cmp(other_input);
                         }
@@ -885,6 +924,19 @@ impl PartialEq for ComplexFactor {
             (Lucas(a), Lucas(b)) => a == b,
             (Factorial(a), Factorial(b)) => a == b,
             (Primorial(a), Primorial(b)) => a == b,
+            (Subfactorial(a), Subfactorial(b)) => a == b,
+            (Tribonacci(a), Tribonacci(b)) => a == b,
+            (Pell(a), Pell(b)) => a == b,
+            (
+                Modulo {
+                    left: l1,
+                    right: r1,
+                },
+                Modulo {
+                    left: l2,
+                    right: r2,
+                },
+            ) => r1 == r2 && l1 == l2,
             _ => false,
         }
     }
@@ -958,6 +1010,25 @@ impl From<FactorBeingParsed> for Factor {
                 inner: Arc::new(Primorial(Factor::from(*term))),
                 hash: OnceLock::new(),
             },
+            FactorBeingParsed::Subfactorial(term) => Complex {
+                inner: Arc::new(Subfactorial(Factor::from(*term))),
+                hash: OnceLock::new(),
+            },
+            FactorBeingParsed::Tribonacci(term) => Complex {
+                inner: Arc::new(Tribonacci(Factor::from(*term))),
+                hash: OnceLock::new(),
+            },
+            FactorBeingParsed::Pell(term) => Complex {
+                inner: Arc::new(Pell(Factor::from(*term))),
+                hash: OnceLock::new(),
+            },
+            FactorBeingParsed::Modulo { left, right } => Complex {
+                inner: Arc::new(Modulo {
+                    left: Factor::from(*left),
+                    right: Factor::from(*right),
+                }),
+                hash: OnceLock::new(),
+            },
         }
     }
 }
@@ -1010,25 +1081,35 @@ impl From<BigNumber> for Factor {
     }
 }
 
+/// Expressions longer than this are parsed on the blocking thread pool by [Factor::parse], so that
+/// a huge expression from `frame_prime.php` or the API can't stall one of the runtime's workers.
+pub const MAX_INLINE_PARSE_LEN: usize = 1 << 12;
+
+/// Longest number that a repeated-digit pattern such as `9{1000}` may expand to, so that a huge
+/// repeat count in a scraped page can't make the parser allocate without bound.
+const MAX_REPEATED_DIGITS: usize = MAX_INLINE_PARSE_LEN << 6;
+
 impl From<&str> for Factor {
     #[inline(always)]
     fn from(value: &str) -> Self {
         if let Ok(numeric) = value.parse() {
             return Numeric(numeric);
         }
-        task::block_in_place(|| {
-            expression_parser::arithmetic(value)
-                .map(Factor::from)
-                .unwrap_or_else(|e| {
-                    error!("Error parsing expression {value}: {e}");
-                    UnknownExpression {
-                        inner: value.into(),
-                        hash: OnceLock::new(),
-                    }
-                })
-        })
+        task::block_in_place(|| parse_expression(value))
     }
 }
+
+fn parse_expression(value: &str) -> Factor {
+    expression_parser::arithmetic(value)
+        .map(Factor::from)
+        .unwrap_or_else(|e| {
+            error!("Error parsing expression {value}: {e}");
+            UnknownExpression {
+                inner: value.into(),
+                hash: OnceLock::new(),
+            }
+        })
+}
 type FactorCacheLock<T> = OnceLock<BasicCache<Factor, T>>;
 
 // Object pools are used to avoid discarding a thread-local cache's contents when the thread exits,
@@ -1039,16 +1120,105 @@ static LOG10_ESTIMATE_CACHE_LOCK: FactorCacheLock<(NumberLength, NumberLength)>
     FactorCacheLock::new();
 static FACTOR_CACHE_LOCK: FactorCacheLock<BTreeMap<Factor, NumberLength>> = FactorCacheLock::new();
 static UNIQUE_FACTOR_CACHE_LOCK: FactorCacheLock<Box<[Factor]>> = FactorCacheLock::new();
+static SMALL_RESIDUES_CACHE_LOCK: FactorCacheLock<Arc<[OnceLock<Option<NumericFactor>>]>> =
+    FactorCacheLock::new();
 
 const NUMERIC_VALUE_CACHE_SIZE: usize = 1 << 20;
 const LOG10_ESTIMATE_CACHE_SIZE: usize = 1 << 20;
 const FACTOR_CACHE_SIZE: usize = 1 << 12;
 const UNIQUE_FACTOR_CACHE_SIZE: usize = 1 << 16;
+const SMALL_RESIDUES_CACHE_SIZE: usize = 1 << 16;
+
+/// Composite moduli that [Factor::may_be_proper_divisor_of] compares residues for, besides
+/// [SMALL_PRIMES], so that it notices repeated factors of 2, 3 and 5.
+const DIVISIBILITY_TEST_COMPOSITE_MODULI: [NumericFactor; 23] = [
+    900, 450, 300, 225, 180, 150, 100, 90, 75, 60, 50, 45, 36, 30, 25, 20, 18, 15, 12, 10, 9, 6, 4,
+];
+
+fn divisibility_test_moduli() -> impl Iterator<Item = NumericFactor> {
+    DIVISIBILITY_TEST_COMPOSITE_MODULI
+        .iter()
+        .copied()
+        .chain(SMALL_PRIMES.iter().map(|&prime| NumericFactor::from(prime)))
+}
+
+/// Residues of `expr` modulo each of [divisibility_test_moduli], in the same order, each filled in
+/// the first time it's needed. Cached because the submission loop probes the same target with many
+/// candidate divisors, though each candidate only needs the residues for moduli that divide it.
+fn small_residues(expr: &Factor) -> Arc<[OnceLock<Option<NumericFactor>>]> {
+    let cache = SMALL_RESIDUES_CACHE_LOCK.get_or_init(|| create_cache(SMALL_RESIDUES_CACHE_SIZE));
+    if let Some(residues) = get_from_cache(cache, expr) {
+        return residues;
+    }
+    let residues: Arc<[_]> = divisibility_test_moduli()
+        .map(|_| OnceLock::new())
+        .collect();
+    cache.insert(expr.clone(), residues.clone());
+    residues
+}
 
 pub fn get_numeric_value_cache() -> &'static BasicCache<Factor, Option<NumericFactor>> {
     NUMERIC_VALUE_CACHE_LOCK.get_or_init(|| create_cache(NUMERIC_VALUE_CACHE_SIZE))
 }
 
+/// Size and hit counts of one of the factor-finder caches.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CacheStats {
+    pub name: &'static str,
+    pub hits: u64,
+    pub misses: u64,
+    pub len: usize,
+}
+
+impl Display for CacheStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} cache: {} entries, {} hits, {} misses",
+            self.name, self.len, self.hits, self.misses
+        )
+    }
+}
+
+fn cache_stats<T: Clone>(name: &'static str, lock: &FactorCacheLock<T>) -> CacheStats {
+    match lock.get() {
+        Some(cache) => CacheStats {
+            name,
+            hits: cache.hits(),
+            misses: cache.misses(),
+            len: cache.len(),
+        },
+        None => CacheStats {
+            name,
+            ..CacheStats::default()
+        },
+    }
+}
+
+pub fn factor_cache_stats() -> [CacheStats; 5] {
+    [
+        cache_stats("Numeric value", &NUMERIC_VALUE_CACHE_LOCK),
+        cache_stats("Log10 estimate", &LOG10_ESTIMATE_CACHE_LOCK),
+        cache_stats("Factor", &FACTOR_CACHE_LOCK),
+        cache_stats("Unique factor", &UNIQUE_FACTOR_CACHE_LOCK),
+        cache_stats("Small residues", &SMALL_RESIDUES_CACHE_LOCK),
+    ]
+}
+
+/// Empties all the factor-finder caches, to free memory. Everything in them can be recomputed.
+pub fn clear_all_caches() {
+    fn clear<T: Clone>(lock: &FactorCacheLock<T>) {
+        if let Some(cache) = lock.get() {
+            cache.clear();
+        }
+    }
+    clear(&NUMERIC_VALUE_CACHE_LOCK);
+    clear(&LOG10_ESTIMATE_CACHE_LOCK);
+    clear(&FACTOR_CACHE_LOCK);
+    clear(&UNIQUE_FACTOR_CACHE_LOCK);
+    clear(&SMALL_RESIDUES_CACHE_LOCK);
+}
+
 impl Default for Factor {
     fn default() -> Self {
         Numeric(1)
@@ -1058,7 +1228,14 @@ impl Default for Factor {
 peg::parser! {
   pub grammar expression_parser() for str {
     pub rule number() -> FactorBeingParsed
-      = n:$(['0'..='9']+) { n.parse::<NumericFactor>().map(FactorBeingParsed::Numeric).unwrap_or_else(|_| FactorBeingParsed::BigNumber(n.into())) }
+      = n:$(['0'..='9']+) "{" k:$(['0'..='9']+) "}" {?
+          // Digit pattern repeated k times, e.g. 1{50} for the 50-digit repunit
+          k.parse::<usize>().ok()
+              .filter(|&k| k > 0 && n.len().checked_mul(k).is_some_and(|len| len <= MAX_REPEATED_DIGITS))
+              .map(|k| parse_digits(&n.repeat(k)))
+              .ok_or("positive repeat count that isn't too long")
+      }
+      / n:$(['0'..='9']+) { parse_digits(n) }
 
     #[cache_left_rec]
     pub rule arithmetic() -> FactorBeingParsed = precedence!{
@@ -1125,6 +1302,7 @@ peg::parser! {
             FactorBeingParsed::Divide { left: x.into(), right: [(y, 1)].into() }
         }
       }
+      x:(@) "%" y:@ { FactorBeingParsed::Modulo { left: x.into(), right: y.into() } }
       --
       x:(@) "*" y:@ {
         let mut x = x;
@@ -1142,7 +1320,7 @@ peg::parser! {
         }
       }
       --
-      x:@ "^" y:(@) {
+      x:@ ("^" / "**") y:(@) {
                 if let FactorBeingParsed::Numeric(y) = y && let Ok(y_numeric) = NumberLength::try_from(y) {
                   FactorBeingParsed::Multiply { terms: [(x, y_numeric)].into() }
                 } else {
@@ -1168,10 +1346,16 @@ peg::parser! {
           (FactorBeingParsed::Numeric(1), -1)
       ].into() } }
       --
+      "!" x:@ { FactorBeingParsed::Subfactorial(x.into()) }
+      --
       "I" x:@ { FactorBeingParsed::Fibonacci(x.into()) }
       --
       "lucas(" x:arithmetic() ")" { FactorBeingParsed::Lucas(x.into()) }
       --
+      "trib(" x:arithmetic() ")" { FactorBeingParsed::Tribonacci(x.into()) }
+      --
+      "pell(" x:arithmetic() ")" { FactorBeingParsed::Pell(x.into()) }
+      --
       n:$(['0'..='9']+ "..." ['0'..='9']+) { FactorBeingParsed::ElidedNumber(n.into()) }
       --
       n:number() { n }
@@ -1181,6 +1365,12 @@ peg::parser! {
   }
 }
 
+fn parse_digits(n: &str) -> FactorBeingParsed {
+    n.parse::<NumericFactor>()
+        .map(FactorBeingParsed::Numeric)
+        .unwrap_or_else(|_| FactorBeingParsed::BigNumber(n.into()))
+}
+
 fn largest_prime_le(mut given: NumericFactor) -> NumericFactor {
     while given >= 2 {
         if is_prime(given) {
@@ -1227,6 +1417,85 @@ impl Factor {
         }
     }
 
+    /// Parses an expression without blocking a runtime worker for long. Inputs longer than
+    /// [MAX_INLINE_PARSE_LEN] are parsed on the blocking thread pool.
+    pub async fn parse(value: &str) -> Factor {
+        if value.len() <= MAX_INLINE_PARSE_LEN {
+            return Factor::from(value);
+        }
+        if let Ok(numeric) = value.parse() {
+            return Numeric(numeric);
+        }
+        let owned = value.to_owned();
+        match task::spawn_blocking(move || parse_expression(&owned)).await {
+            Ok(factor) => factor,
+            Err(e) => {
+                error!(
+                    "Parsing task for an expression of {} chars failed: {e}",
+                    value.len()
+                );
+                UnknownExpression {
+                    inner: value.to_owned().into(),
+                    hash: OnceLock::new(),
+                }
+            }
+        }
+    }
+
+    /// Parses each line or `;`-separated expression in `batch`, skipping blank ones. Expressions
+    /// that don't parse, or don't round-trip (see [Factor::check_round_trip]), are logged and left
+    /// out, rather than failing the whole batch.
+    pub fn from_batch(batch: &str) -> Vec<Factor> {
+        batch
+            .split(['\n', ';'])
+            .map(str::trim)
+            .filter(|expr| !expr.is_empty())
+            .filter_map(|expr| {
+                if let Ok(numeric) = expr.parse() {
+                    return Some(Numeric(numeric));
+                }
+                match expression_parser::arithmetic(expr) {
+                    Ok(factor) => {
+                        let factor = Factor::from(factor);
+                        if factor.check_round_trip().is_err() {
+                            error!(
+                                "Skipping expression {expr} in batch, since it doesn't round-trip"
+                            );
+                            return None;
+                        }
+                        Some(factor)
+                    }
+                    Err(e) => {
+                        error!("Skipping unparseable expression {expr} in batch: {e}");
+                        None
+                    }
+                }
+            })
+            .collect()
+    }
+
+    /// Like [Factor::from], but also checks that the result prints as an expression that parses
+    /// back to the same [Factor], since a mismatch means a parser or printer bug that could get a
+    /// factor submitted to the wrong number. On a mismatch, logs both forms and returns the
+    /// reparsed one as the error.
+    pub fn from_checked(value: &str) -> Result<Factor, Factor> {
+        let factor = Factor::from(value);
+        factor.check_round_trip()?;
+        Ok(factor)
+    }
+
+    /// Prints this factor and reparses it, returning the reparsed form as the error if it differs.
+    pub fn check_round_trip(&self) -> Result<(), Factor> {
+        let printed = self.to_unelided_string();
+        let reparsed = Factor::from(printed.as_str());
+        if reparsed == *self {
+            Ok(())
+        } else {
+            warn!("{self:?} printed as {printed}, which parsed back as {reparsed:?}");
+            Err(reparsed)
+        }
+    }
+
     #[inline(always)]
     pub fn as_numeric(&self) -> Option<NumericFactor> {
         match self {
@@ -1300,13 +1569,34 @@ impl Factor {
                 ),
                 Factorial(ref input) => format!("({}!)", input.to_unelided_string()),
                 Primorial(ref input) => format!("({}#)", input.to_unelided_string()),
+                Subfactorial(ref input) => format!("(!{})", input.to_unelided_string()),
                 Fibonacci(ref input) => format!("I({})", input.to_unelided_string()),
                 Lucas(ref input) => format!("lucas({})", input.to_unelided_string()),
+                Tribonacci(ref input) => format!("trib({})", input.to_unelided_string()),
+                Pell(ref input) => format!("pell({})", input.to_unelided_string()),
+                Modulo {
+                    ref left,
+                    ref right,
+                } => format!(
+                    "({}%{})",
+                    left.to_unelided_string(),
+                    right.to_unelided_string()
+                ),
             }
             .into(),
         }
     }
 
+    /// The full decimal expansion of this number, if it's known or can be evaluated with
+    /// [evaluate_as_bignum]; otherwise `None`.
+    pub fn digits(&self) -> Option<HipStr<'static>> {
+        match self {
+            Numeric(n) => Some(n.to_string().into()),
+            Factor::BigNumber { inner: s, .. } => Some(s.0.clone()),
+            _ => evaluate_as_bignum(self).map(|n| n.to_string().into()),
+        }
+    }
+
     #[inline(always)]
     fn last_two_digits(&self) -> Option<u8> {
         match self {
@@ -1361,25 +1651,14 @@ impl Factor {
                         return Some(a_mod_b == 0);
                     }
                 } else {
-                    for prime in [
-                        900, 450, 300, 225, 180, 150, 100, 90, 75, 60, 50, 45, 36, 30, 25, 20, 18,
-                        15, 12, 10, 9, 6, 4,
-                    ]
-                    .iter()
-                    .chain(SMALL_PRIMES.iter())
-                    .copied()
-                    {
-                        if let Some(0) = modulo_as_numeric_no_evaluate(a, prime.into()) {
-                            let b_mod_p = if let Some(b_numeric) = b_numeric {
-                                Some(b_numeric % NumericFactor::from(prime))
-                            } else {
-                                modulo_as_numeric_no_evaluate(b, NumericFactor::from(prime))
-                            };
-                            if let Some(b_mod_p) = b_mod_p
-                                && (b_mod_p != 0)
-                            {
-                                return Some(false);
-                            }
+                    let b_residues = small_residues(b);
+                    for (modulus, b_mod_p) in divisibility_test_moduli().zip(b_residues.iter()) {
+                        if let Some(0) = modulo_as_numeric_no_evaluate(a, modulus)
+                            && let Some(b_mod_p) =
+                                *b_mod_p.get_or_init(|| modulo_as_numeric_no_evaluate(b, modulus))
+                            && b_mod_p != 0
+                        {
+                            return Some(false);
                         }
                     }
                 }
@@ -1415,9 +1694,117 @@ impl Factor {
                 true
             })
         }
+        fn square_root(term: &Factor) -> Option<&Factor> {
+            let Complex { inner: ref c, .. } = *term else {
+                return None;
+            };
+            match **c {
+                Multiply { ref terms, .. } if terms.len() == 1 => {
+                    let (base, exponent) = terms.iter().next()?;
+                    (*exponent == 2).then_some(base)
+                }
+                Power {
+                    ref base,
+                    exponent: Numeric(2),
+                } => Some(base),
+                _ => None,
+            }
+        }
+        /// If `expr` is `x^2+1` or `x^2+-x+1`, returns `(q, m)` such that every prime factor of
+        /// `expr` is either `q` (with multiplicity 1) or congruent to 1 mod `m`, whatever `x` is.
+        /// The primes `x^2+1` can have are those for which -1 is a quadratic residue, and those
+        /// for `x^2+-x+1` are those for which -3 is.
+        fn quadratic_form_prime_classes(expr: &Factor) -> Option<(NumericFactor, NumericFactor)> {
+            let Complex { inner: ref c, .. } = *expr else {
+                return None;
+            };
+            let AddSub { ref terms, .. } = **c else {
+                return None;
+            };
+            if terms.get(&Factor::one()) != Some(&1) {
+                return None;
+            }
+            let other_terms: Vec<_> = terms
+                .iter()
+                .filter(|(term, _)| **term != Factor::one())
+                .collect();
+            match *other_terms {
+                [(square, 1)] => square_root(square).map(|_| (2, 4)),
+                [(a, a_coeff), (b, b_coeff)] => {
+                    let (square, square_coeff, linear, linear_coeff) = if square_root(a) == Some(b)
+                    {
+                        (a, a_coeff, b, b_coeff)
+                    } else {
+                        (b, b_coeff, a, a_coeff)
+                    };
+                    (*square_coeff == 1
+                        && linear_coeff.abs() == 1
+                        && square_root(square) == Some(linear))
+                    .then_some((3, 3))
+                }
+                _ => None,
+            }
+        }
+        /// If `expr` is `a*x^2+c` for nonzero constants `a` and `c`, returns `-a*c`. An odd prime
+        /// that divides neither `a` nor `c` can only divide `expr` if `-a*c` is a quadratic residue
+        /// mod that prime, since `x^2` must then be congruent to `-c/a`.
+        fn square_plus_constant_discriminant(expr: &Factor) -> Option<i128> {
+            let Complex { inner: ref c, .. } = *expr else {
+                return None;
+            };
+            let AddSub { ref terms, .. } = **c else {
+                return None;
+            };
+            let mut square_coeff = None;
+            let mut constant: i128 = 0;
+            for (term, coeff) in terms {
+                if let Some(n) = term.as_numeric() {
+                    constant =
+                        constant.checked_add(i128::try_from(n).ok()?.checked_mul(*coeff)?)?;
+                } else if square_coeff.is_none() && square_root(term).is_some() {
+                    square_coeff = Some(*coeff);
+                } else {
+                    return None;
+                }
+            }
+            if constant == 0 {
+                return None;
+            }
+            square_coeff?.checked_mul(constant)?.checked_neg()
+        }
         if let Some(exact) = divides_exactly(self, other) {
             return exact;
         }
+        if let Some(self_numeric) = evaluate_as_numeric(self)
+            && self_numeric > 1
+            && let Some((exceptional_prime, modulus)) = quadratic_form_prime_classes(other)
+            && find_raw_factors_of_numeric(self_numeric)
+                .into_iter()
+                .any(|(prime, power)| {
+                    if prime == exceptional_prime {
+                        power > 1
+                    } else {
+                        prime % modulus != 1
+                    }
+                })
+        {
+            return false;
+        }
+        if let Some(self_numeric) = evaluate_as_numeric(self)
+            && self_numeric > 1
+            && let Some(discriminant) = square_plus_constant_discriminant(other)
+            && find_raw_factors_of_numeric(self_numeric)
+                .into_iter()
+                .any(|(prime, _)| {
+                    let Ok(signed_prime) = i128::try_from(prime) else {
+                        return false;
+                    };
+                    let residue = discriminant.rem_euclid(signed_prime) as NumericFactor;
+                    prime != 2 && residue != 0 && !is_quadratic_residue(residue, prime)
+                })
+        {
+            return false;
+        }
         if let Complex { inner: ref c, .. } = *self
             && let Divide {
                 ref left,
@@ -1613,6 +2000,13 @@ impl Factor {
                 Lucas(ref term) => term.is_elided(),
                 Factorial(ref term) => term.is_elided(),
                 Primorial(ref term) => term.is_elided(),
+                Subfactorial(ref term) => term.is_elided(),
+                Tribonacci(ref term) => term.is_elided(),
+                Pell(ref term) => term.is_elided(),
+                Modulo {
+                    ref left,
+                    ref right,
+                } => left.is_elided() || right.is_elided(),
             },
         }
     }
@@ -1677,8 +2071,15 @@ impl Display for Factor {
                 } => f.write_fmt(format_args!("({base})^({exponent})")),
                 Factorial(ref input) => f.write_fmt(format_args!("({input}!)")),
                 Primorial(ref input) => f.write_fmt(format_args!("({input}#)")),
+                Subfactorial(ref input) => f.write_fmt(format_args!("(!{input})")),
                 Fibonacci(ref input) => f.write_fmt(format_args!("I({input})")),
                 Lucas(ref input) => f.write_fmt(format_args!("lucas({input})")),
+                Tribonacci(ref input) => f.write_fmt(format_args!("trib({input})")),
+                Pell(ref input) => f.write_fmt(format_args!("pell({input})")),
+                Modulo {
+                    ref left,
+                    ref right,
+                } => f.write_fmt(format_args!("({left}%{right})")),
             },
         }
     }
@@ -1686,6 +2087,45 @@ impl Display for Factor {
 
 thread_local! {
     pub static SIEVE: RefCell<NaiveBuffer> = RefCell::new(NaiveBuffer::new());
+
+    /// Primes in each residue class that's been searched, and the bound they go up to.
+    static PRIMES_IN_CLASS: RefCell<HashMap<(u64, u64), (u64, Rc<[u64]>)>> =
+        RefCell::new(HashMap::new());
+}
+
+/// Residue classes whose primes are remembered per thread; past this, the cache starts over.
+const MAX_CACHED_PRIME_CLASSES: usize = 64;
+
+/// Calls `f` with the primes up to `limit` that are congruent to `residue` mod `modulus`, e.g. the
+/// candidate factors `2kp+1` of a Mersenne number 2^p-1. The list is computed from [SIEVE] once
+/// per class and thread, so searches that test many candidates don't repeat primality tests.
+pub(crate) fn with_primes_in_class<T>(
+    modulus: u64,
+    residue: u64,
+    limit: u64,
+    f: impl FnOnce(&[u64]) -> T,
+) -> T {
+    let primes = PRIMES_IN_CLASS.with_borrow_mut(|cache| {
+        if let Some((cached_limit, primes)) = cache.get(&(modulus, residue))
+            && *cached_limit >= limit
+        {
+            return primes.clone();
+        }
+        if cache.len() >= MAX_CACHED_PRIME_CLASSES {
+            cache.clear();
+        }
+        let primes: Rc<[u64]> = SIEVE.with_borrow_mut(|sieve| {
+            sieve
+                .primes(limit)
+                .copied()
+                .filter(|prime| prime % modulus == residue)
+                .collect()
+        });
+        cache.insert((modulus, residue), (limit, primes.clone()));
+        primes
+    });
+    let end = primes.partition_point(|&prime| prime <= limit);
+    f(&primes[..end])
 }
 
 #[inline(always)]
@@ -1829,7 +2269,7 @@ fn lucas_factors(term: NumericFactor, subset_recursion: bool) -> BTreeMap<Factor
 }
 
 #[inline]
-fn power_multiset<T: PartialEq + Ord + Copy>(
+pub(crate) fn power_multiset<T: PartialEq + Ord + Copy>(
     multiset: BTreeMap<T, NumberLength>,
 ) -> Vec<BTreeMap<T, NumberLength>> {
     let total_size = multiset
@@ -1976,6 +2416,13 @@ pub fn to_like_powers(terms: &BTreeMap<Factor, i128>) -> BTreeMap<Factor, Number
             let a_minus_b =
                 Factor::add_sub(pos_term_roots.into_iter().chain(neg_term_roots).collect());
             (a_plus_b, a_minus_b)
+        } else if prime == 3
+            && let [(a, a_coeff), (b, b_coeff)] = &*pos_term_roots
+                .iter()
+                .chain(&neg_term_roots)
+                .collect::<Vec<_>>()
+        {
+            sum_of_cubes_factors(a, *a_coeff, b, *b_coeff)
         } else {
             let a_minus_b =
                 Factor::add_sub(pos_term_roots.into_iter().chain(neg_term_roots).collect());
@@ -1989,6 +2436,27 @@ pub fn to_like_powers(terms: &BTreeMap<Factor, i128>) -> BTreeMap<Factor, Number
     results
 }
 
+/// With `u = a_coeff * a` and `v = b_coeff * b`, factors `u^3 + v^3` as `(u + v)(u^2 - uv + v^2)`,
+/// which covers both sums and differences of cubes. Returns the quadratic factor, then the linear
+/// one.
+fn sum_of_cubes_factors(a: &Factor, a_coeff: i128, b: &Factor, b_coeff: i128) -> (Factor, Factor) {
+    let linear = simplify_add_sub_internal(&[(a.clone(), a_coeff), (b.clone(), b_coeff)].into())
+        .unwrap_or_else(|| Factor::add_sub([(a.clone(), a_coeff), (b.clone(), b_coeff)].into()));
+    let mut quadratic_terms = BTreeMap::new();
+    *quadratic_terms
+        .entry(simplify_multiply([(a.clone(), 2)].into()))
+        .or_insert(0) += a_coeff * a_coeff;
+    *quadratic_terms
+        .entry(simplify_multiply([(a.clone(), 1), (b.clone(), 1)].into()))
+        .or_insert(0) -= a_coeff * b_coeff;
+    *quadratic_terms
+        .entry(simplify_multiply([(b.clone(), 2)].into()))
+        .or_insert(0) += b_coeff * b_coeff;
+    let quadratic = simplify_add_sub_internal(&quadratic_terms)
+        .unwrap_or_else(|| Factor::add_sub(quadratic_terms));
+    (quadratic, linear)
+}
+
 pub fn div_exact(product: &Factor, divisor: &Factor) -> Option<Factor> {
     if product == divisor {
         return Some(Factor::one());
@@ -2270,6 +2738,43 @@ pub(crate) fn find_factors_of_numeric(input: NumericFactor) -> BTreeMap<Factor,
         .collect()
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+enum FactoringBackend {
+    /// yamaquasi's SIQS, which is the fastest for numbers above 2^84
+    Siqs,
+    /// num-prime's factorize128, which is slower but doesn't need any SIMD instructions
+    Scalar,
+}
+
+static FACTORING_BACKEND: LazyLock<FactoringBackend> = LazyLock::new(|| {
+    let backend = select_factoring_backend(cpu_supports_siqs());
+    if backend == FactoringBackend::Scalar {
+        warn!(
+            "Using scalar factoring instead of SIQS, because this CPU lacks AVX2 or the scalar-factoring feature is enabled"
+        );
+    }
+    backend
+});
+
+fn cpu_supports_siqs() -> bool {
+    #[cfg(target_arch = "x86_64")]
+    {
+        is_x86_feature_detected!("avx2")
+    }
+    #[cfg(not(target_arch = "x86_64"))]
+    {
+        true
+    }
+}
+
+fn select_factoring_backend(cpu_supports_siqs: bool) -> FactoringBackend {
+    if cfg!(feature = "scalar-factoring") || !cpu_supports_siqs {
+        FactoringBackend::Scalar
+    } else {
+        FactoringBackend::Siqs
+    }
+}
+
 #[inline(always)]
 pub(crate) fn find_raw_factors_of_numeric(
     input: NumericFactor,
@@ -2278,24 +2783,158 @@ pub(crate) fn find_raw_factors_of_numeric(
     task::block_in_place(|| match input {
         1 => BTreeMap::new(),
         0 | 2 | 3 => [(input, 1)].into(),
-        4..=MAX_FACTORIZE128 => factorize128(input)
-            .into_iter()
-            .map(|(factor, exponent)| (factor, exponent as NumberLength))
-            .collect(),
+        _ if input <= MAX_FACTORIZE128 || *FACTORING_BACKEND == FactoringBackend::Scalar => {
+            factorize128(input)
+                .into_iter()
+                .map(|(factor, exponent)| (factor, exponent as NumberLength))
+                .collect()
+        }
         _ => {
             let mut prefs = Preferences::default();
             prefs.verbosity = Silent;
-            let mut factors = BTreeMap::new();
-            for factor in factor(input.into(), Siqs, &prefs).unwrap() {
-                *factors
-                    .entry(NumericFactor::try_from(factor).unwrap())
-                    .or_insert(0 as NumberLength) += 1;
+            let factors = collect_backend_factors(input, || factor(input.into(), Siqs, &prefs));
+            if factors.get(&input) == Some(&1)
+                && let Some(rho_factor) = pollard_rho_brent(input)
+            {
+                info!("Pollard rho split {input}, which the factoring backend couldn't");
+                let mut factors = BTreeMap::new();
+                for part in [rho_factor, input / rho_factor] {
+                    for (factor, exponent) in factorize128(part) {
+                        *factors.entry(factor).or_insert(0) += exponent as NumberLength;
+                    }
+                }
+                return factors;
             }
             factors
         }
     })
 }
 
+/// How many iterations [pollard_rho_brent] may spend on one number, across all the polynomials it
+/// tries.
+const POLLARD_RHO_MAX_ITERATIONS: u64 = 1 << 22;
+
+/// How many differences Brent's variant multiplies together before taking a GCD.
+const POLLARD_RHO_BATCH_SIZE: u64 = 128;
+
+/// Finds a nontrivial factor of `n` with Pollard's rho method, using Brent's cycle detection and
+/// batched GCDs. Returns `None` if `n` is prime, less than 4, or resists
+/// [POLLARD_RHO_MAX_ITERATIONS] iterations. The factor found isn't necessarily prime.
+pub fn pollard_rho_brent(n: NumericFactor) -> Option<NumericFactor> {
+    pollard_rho_brent_with_budget(n, POLLARD_RHO_MAX_ITERATIONS)
+}
+
+fn pollard_rho_brent_with_budget(n: NumericFactor, max_iterations: u64) -> Option<NumericFactor> {
+    if n < 4 {
+        return None;
+    }
+    if n.is_multiple_of(2) {
+        return Some(2);
+    }
+    // The sequence can't tell the two factors of a square apart
+    if let Some(root) = n.sqrt_exact() {
+        return Some(root);
+    }
+    if is_prime(n) {
+        return None;
+    }
+    let mut iterations = 0;
+    for c in 1..n {
+        let f = |x: NumericFactor| x.mulm(x, &n).addm(c, &n);
+        let mut y: NumericFactor = 2;
+        let mut saved_y = y;
+        let mut product: NumericFactor = 1;
+        let mut cycle_length = 1;
+        let (x, mut gcd) = loop {
+            let x = y;
+            for _ in 0..cycle_length {
+                y = f(y);
+            }
+            let mut gcd = 1;
+            let mut steps = 0;
+            while steps < cycle_length && gcd == 1 {
+                saved_y = y;
+                let batch = POLLARD_RHO_BATCH_SIZE.min(cycle_length - steps);
+                for _ in 0..batch {
+                    y = f(y);
+                    product = product.mulm(x.abs_diff(y), &n);
+                }
+                gcd = product.gcd(&n);
+                steps += batch;
+            }
+            iterations += cycle_length + steps;
+            if gcd != 1 {
+                break (x, gcd);
+            }
+            if iterations > max_iterations {
+                return None;
+            }
+            cycle_length *= 2;
+        };
+        if gcd == n {
+            // The batch overshot, so retrace it one step at a time
+            loop {
+                saved_y = f(saved_y);
+                gcd = x.abs_diff(saved_y).gcd(&n);
+                if gcd != 1 {
+                    break;
+                }
+            }
+        }
+        if gcd != n {
+            return Some(gcd);
+        }
+        // This polynomial's cycle closed without separating the factors, so try the next one
+        if iterations > max_iterations {
+            return None;
+        }
+    }
+    None
+}
+
+/// Collects the factors found by a factoring backend. If the backend panics, returns an error, or
+/// returns factors whose product isn't `input`, logs the input and returns it as a single
+/// unfactored entry, so that the caller can carry on without its factors.
+fn collect_backend_factors<T, E: Display>(
+    input: NumericFactor,
+    backend: impl FnOnce() -> Result<Vec<T>, E>,
+) -> BTreeMap<NumericFactor, NumberLength>
+where
+    NumericFactor: TryFrom<T>,
+{
+    let unfactored = || [(input, 1)].into();
+    let raw_factors = match catch_unwind(AssertUnwindSafe(backend)) {
+        Ok(Ok(raw_factors)) => raw_factors,
+        Ok(Err(e)) => {
+            error!("Factoring backend failed on {input}: {e}");
+            return unfactored();
+        }
+        Err(_) => {
+            error!("Factoring backend panicked on {input}");
+            return unfactored();
+        }
+    };
+    let mut factors = BTreeMap::new();
+    let mut product: NumericFactor = 1;
+    for raw_factor in raw_factors {
+        let Ok(factor) = NumericFactor::try_from(raw_factor) else {
+            error!("Factoring backend returned an out-of-range factor of {input}");
+            return unfactored();
+        };
+        let Some(new_product) = product.checked_mul(factor) else {
+            error!("Factoring backend returned factors of {input} whose product overflows");
+            return unfactored();
+        };
+        product = new_product;
+        *factors.entry(factor).or_insert(0 as NumberLength) += 1;
+    }
+    if product != input {
+        error!("Factoring backend returned factors of {input} whose product is {product}");
+        return unfactored();
+    }
+    factors
+}
+
 #[inline(always)]
 fn estimate_log10_internal(expr: &Factor) -> (NumberLength, NumberLength) {
     debug!("estimate_log10_internal: {expr}");
@@ -2323,6 +2962,29 @@ fn estimate_log10_internal(expr: &Factor) -> (NumberLength, NumberLength) {
                     est_log.ceil() as NumberLength + 1,
                 )
             }
+            Tribonacci(ref x) | Pell(ref x) => {
+                if let Some(value) = evaluate_as_numeric(expr) {
+                    return log10_bounds(value);
+                }
+                let Some(term_number) = evaluate_as_numeric(x) else {
+                    warn!("Could not parse term number of {}", expr);
+                    return (0, NumberLength::MAX);
+                };
+                // log10 of the dominant root of the characteristic polynomial, and of the
+                // constant factor in front of its nth power
+                let (log_root, log_coefficient) = if matches!(**c, Pell(_)) {
+                    // (1 + sqrt(2))^n / (2 * sqrt(2))
+                    (0.382_775_685_3, -0.451_544_993_5)
+                } else {
+                    // ~0.1828 * 1.8393^n
+                    (0.264_649_443_5, -0.738_015_415_1)
+                };
+                let est_log = term_number as f64 * log_root + log_coefficient;
+                (
+                    est_log.floor().max(0.0) as NumberLength,
+                    est_log.ceil().max(0.0) as NumberLength + 1,
+                )
+            }
             Factorial(ref input) => {
                 // factorial
                 let Some(input) = evaluate_as_numeric(input) else {
@@ -2340,6 +3002,23 @@ fn estimate_log10_internal(expr: &Factor) -> (NumberLength, NumberLength) {
                     log_factorial_upper_bound.ceil() as NumberLength,
                 )
             }
+            Subfactorial(ref input) => {
+                // subfactorial: !n is the nearest integer to n!/e
+                if let Some(value) = evaluate_as_numeric(expr) {
+                    log10_bounds(value)
+                } else {
+                    let Some(input) = evaluate_as_numeric(input) else {
+                        warn!("Could not parse input to a subfactorial: {}", input);
+                        return (0, NumberLength::MAX);
+                    };
+                    let (ln_factorial, _) = ((input + 1) as f64).ln_gamma();
+                    let ln_subfactorial = (ln_factorial - 1.0).max(0.0);
+                    (
+                        (ln_subfactorial.next_down() / LN_10).floor() as NumberLength,
+                        (ln_subfactorial.next_up() / LN_10.next_down()).ceil() as NumberLength,
+                    )
+                }
+            }
             Primorial(ref input) => {
                 // primorial
                 let Some(input) = evaluate_as_numeric(input) else {
@@ -2369,6 +3048,13 @@ fn estimate_log10_internal(expr: &Factor) -> (NumberLength, NumberLength) {
                 ref base,
                 ref exponent,
             } => estimate_log10_power(base, exponent),
+            Modulo { ref right, .. } => {
+                if let Some(value) = evaluate_as_numeric(expr) {
+                    return log10_bounds(value);
+                }
+                // The residue is less than the modulus
+                (0, estimate_log10_internal(right).1)
+            }
             Divide {
                 ref left,
                 ref right,
@@ -2425,6 +3111,10 @@ fn estimate_log10_internal(expr: &Factor) -> (NumberLength, NumberLength) {
                     .unwrap();
                 let combined_lower = if no_negative {
                     positive_lower
+                } else if let Some(subtrahend) = numeric_subtrahend(terms)
+                    && subtraction_keeps_lower_bound(expr, positive_lower, subtrahend)
+                {
+                    positive_lower
                 } else if negative_upper < positive_lower.saturating_sub(1) {
                     positive_lower.saturating_sub(1)
                 } else {
@@ -2443,6 +3133,38 @@ fn estimate_log10_internal(expr: &Factor) -> (NumberLength, NumberLength) {
     bounds
 }
 
+/// If all the negative terms of an AddSub are numeric, returns the total being subtracted.
+fn numeric_subtrahend(terms: &BTreeMap<Factor, i128>) -> Option<NumericFactor> {
+    terms.iter().filter(|(_, coeff)| **coeff < 0).try_fold(
+        0 as NumericFactor,
+        |total, (term, coeff)| {
+            total.checked_add(evaluate_as_numeric(term)?.checked_mul(coeff.unsigned_abs())?)
+        },
+    )
+}
+
+/// Whether `expr`, which is `x - subtrahend` for some `x >= 10^x_lower_log10`, is still at least
+/// `10^x_lower_log10`. Since `10^x_lower_log10` is a multiple of any smaller power of 10, `x` must be
+/// at least `10^x_lower_log10` plus its own residue modulo that power, so the subtraction can't
+/// lose a digit if that residue is at least `subtrahend`.
+fn subtraction_keeps_lower_bound(
+    expr: &Factor,
+    x_lower_log10: NumberLength,
+    subtrahend: NumericFactor,
+) -> bool {
+    let modulus_log10 = subtrahend.checked_ilog10().unwrap_or(0) + 1;
+    if x_lower_log10 < modulus_log10 {
+        return false;
+    }
+    let Some(modulus) = (10 as NumericFactor).checked_pow(modulus_log10) else {
+        return false;
+    };
+    let Some(residue) = modulo_as_numeric_no_evaluate(expr, modulus) else {
+        return false;
+    };
+    (residue + subtrahend) % modulus >= subtrahend
+}
+
 fn get_cached_log10_bounds(expr: &Factor) -> Option<(NumberLength, NumberLength)> {
     if let Numeric(numeric_value) = *expr {
         return Some(log10_bounds(numeric_value));
@@ -2465,7 +3187,17 @@ fn estimate_log10_power(base: &Factor, exponent: &Factor) -> (NumberLength, Numb
     let Some(exponent) =
         evaluate_as_numeric(exponent).and_then(|exponent| NumberLength::try_from(exponent).ok())
     else {
-        return (0, NumberLength::MAX);
+        // A tower whose exponent has at least 11 digits has more than NumberLength::MAX digits
+        // itself, as long as the base is at least 2
+        let base_is_at_least_2 = match evaluate_as_numeric(base) {
+            Some(base) => base >= 2,
+            None => estimate_log10_internal(base).0 >= 1,
+        };
+        return if base_is_at_least_2 && estimate_log10_internal(exponent).0 >= 11 {
+            (NumberLength::MAX, NumberLength::MAX)
+        } else {
+            (0, NumberLength::MAX)
+        };
     };
     if let Some(base) = evaluate_as_numeric(base) {
         let lower = (base as f64).log10().next_down() * exponent as f64;
@@ -2526,6 +3258,20 @@ pub(crate) fn estimate_log10(expr: &Factor) -> (NumberLength, NumberLength) {
     }
 }
 
+/// Approximate number of decimal digits in `expr`, for logging and prioritization where the exact
+/// bounds from [estimate_log10] aren't needed.
+pub fn estimate_digit_count(expr: &Factor) -> NumberLength {
+    let (lower, upper) = estimate_log10(expr);
+    digit_count_from_log10_bounds(lower, upper)
+}
+
+/// Midpoint of the digit counts allowed by the given bounds on log10. When there's no upper bound,
+/// this is huge, so numbers of unknown size sort after all numbers of known size.
+pub fn digit_count_from_log10_bounds(lower: NumberLength, upper: NumberLength) -> NumberLength {
+    let upper = upper.max(lower);
+    (lower + (upper - lower) / 2).saturating_add(1)
+}
+
 fn modulo_as_reduced<T: Reducer<NumericFactor> + std::clone::Clone>(
     expr: &Factor,
     reducer: &ReducedInt<NumericFactor, T>,
@@ -2549,6 +3295,14 @@ static SMALL_PRIME_MONTGOMERIES: LazyLock<HashMap<NumericFactor, MontgomeryInt<N
         map
     });
 
+fn modulo_as_numeric(expr: &Factor, modulus: NumericFactor) -> Option<NumericFactor> {
+    if let Some(eval) = evaluate_as_numeric(expr) {
+        eval.checked_rem(modulus)
+    } else {
+        modulo_as_numeric_no_evaluate(expr, modulus)
+    }
+}
+
 fn modulo_as_numeric_no_evaluate(expr: &Factor, modulus: NumericFactor) -> Option<NumericFactor> {
     macro_rules! with_reducer {
         ($reducer:expr) => {
@@ -2594,6 +3348,38 @@ fn modulo_as_numeric_no_evaluate(expr: &Factor, modulus: NumericFactor) -> Optio
     )
 }
 
+/// Checks whether `factor` divides `number`. Only works when `factor` is small enough to evaluate;
+/// returns None when it can't tell.
+pub(crate) fn divides(factor: &Factor, number: &Factor) -> Option<bool> {
+    let factor = evaluate_as_numeric(factor)?;
+    if factor == 0 {
+        return Some(false);
+    }
+    let residue = match evaluate_as_numeric(number) {
+        Some(number) => number % factor,
+        None => modulo_as_numeric_no_evaluate(number, factor)?,
+    };
+    Some(residue == 0)
+}
+
+/// How many times `factor` divides `number`, if that can be told without factoring `number`.
+pub(crate) fn multiplicity(factor: &Factor, number: &Factor) -> Option<NumberLength> {
+    let factor = evaluate_as_numeric(factor)?;
+    if factor < 2 {
+        return None;
+    }
+    let mut power = factor;
+    let mut exponent = 0;
+    while divides(&Numeric(power), number)? {
+        exponent += 1;
+        let Some(next_power) = power.checked_mul(factor) else {
+            break;
+        };
+        power = next_power;
+    }
+    Some(exponent)
+}
+
 fn modulo_as_reduced_no_evaluate<T: Reducer<NumericFactor> + std::clone::Clone>(
     expr: &Factor,
     reducer: &ReducedInt<NumericFactor, T>,
@@ -2671,11 +3457,11 @@ fn modulo_as_reduced_no_evaluate<T: Reducer<NumericFactor> + std::clone::Clone>(
             }
             Fibonacci(ref term) => {
                 let term = evaluate_as_numeric(term)?;
-                Some(reducer.convert(pisano(term, vec![0, 1, 1], reducer.modulus())))
+                Some(fibonacci_and_lucas_modulo(term, reducer).0)
             }
             Lucas(ref term) => {
                 let term = evaluate_as_numeric(term)?;
-                Some(reducer.convert(pisano(term, vec![2, 1], reducer.modulus())))
+                Some(fibonacci_and_lucas_modulo(term, reducer).1)
             }
             Factorial(ref term) => {
                 let term = evaluate_as_numeric(term)?;
@@ -2693,7 +3479,10 @@ fn modulo_as_reduced_no_evaluate<T: Reducer<NumericFactor> + std::clone::Clone>(
                         0
                     }));
                 }
-                let mut result = ReducedInt::new(1, &modulus);
+                if let Some(value) = evaluate_as_bignum(expr) {
+                    return Some(reducer.convert((value % U512::from(modulus)).low_u128()));
+                }
+                let mut result = reducer.convert(1);
                 for i in 2..=term {
                     result = result * i;
                     if result.is_zero() {
@@ -2708,7 +3497,7 @@ fn modulo_as_reduced_no_evaluate<T: Reducer<NumericFactor> + std::clone::Clone>(
                 if term >= modulus && (is_prime(term) || is_prime(modulus)) {
                     return Some(reducer.convert(0));
                 }
-                let mut result = ReducedInt::new(1, &modulus);
+                let mut result = reducer.convert(1);
                 for i in 2..=term {
                     if is_prime(i) {
                         result = result * i;
@@ -2719,33 +3508,138 @@ fn modulo_as_reduced_no_evaluate<T: Reducer<NumericFactor> + std::clone::Clone>(
                 }
                 Some(result)
             }
+            Subfactorial(ref term) => {
+                let term = evaluate_as_numeric(term)?;
+                let modulus = reducer.modulus();
+                // Once k is a multiple of 2*modulus, !k = 1 (mod modulus) and the recurrence
+                // restarts, so the sequence is periodic with period dividing 2*modulus.
+                let term = term % modulus.checked_mul(2).unwrap_or(NumericFactor::MAX);
+                if term > MAX_ITERATIVE_MODULO_TERM {
+                    return None;
+                }
+                let mut result = reducer.convert(1);
+                for k in 1..=term {
+                    result = result * k;
+                    result = if k.is_multiple_of(2) {
+                        result + reducer.convert(1)
+                    } else {
+                        result - reducer.convert(1)
+                    };
+                }
+                Some(result)
+            }
+            Tribonacci(ref term) => {
+                let term = evaluate_as_numeric(term)?;
+                if term > MAX_ITERATIVE_MODULO_TERM {
+                    return None;
+                }
+                // (trib(k), trib(k+1), trib(k+2))
+                let mut window = (reducer.convert(0), reducer.convert(0), reducer.convert(1));
+                for _ in 0..term {
+                    let next = window.0.clone() + window.1.clone() + window.2.clone();
+                    window = (window.1, window.2, next);
+                }
+                Some(window.0)
+            }
+            Pell(ref term) => {
+                let term = evaluate_as_numeric(term)?;
+                if term > MAX_ITERATIVE_MODULO_TERM {
+                    return None;
+                }
+                // (pell(k), pell(k+1))
+                let mut window = (reducer.convert(0), reducer.convert(1));
+                for _ in 0..term {
+                    let next = window.1.clone().double() + window.0;
+                    window = (window.1, next);
+                }
+                Some(window.0)
+            }
+            Modulo { .. } => Some(reducer.convert(evaluate_as_numeric(expr)?)),
         },
     }
 }
 
-fn is_prime(val: NumericFactor) -> bool {
-    SIEVE.with_borrow(|sieve| sieve.is_prime(&val, None)) != No
+const MAX_ITERATIVE_MODULO_TERM: NumericFactor = 1 << 16;
+
+/// Euler's criterion: for an odd prime `p` that doesn't divide `a`, `a` is a quadratic residue mod
+/// `p` if and only if `a^((p-1)/2)` is 1 mod `p`.
+fn is_quadratic_residue(a: NumericFactor, p: NumericFactor) -> bool {
+    MontgomeryInt::new(a % p, &p).pow(&((p - 1) / 2)).residue() == 1
 }
 
-fn pisano(
-    term: NumericFactor,
-    mut sequence: Vec<NumericFactor>,
-    modulus: NumericFactor,
-) -> NumericFactor {
-    let mut zeros = 0; // don't count the initial 0th term for Fibonacci sequence
-    loop {
-        if sequence.len() as NumericFactor == term + 1 {
-            return *sequence.last().unwrap();
+/// Numbers below this have their primality remembered per thread, at a cost of 2 bits each.
+const PRIMALITY_CACHE_BOUND: NumericFactor = 1 << 20;
+
+/// Primality of small numbers that have already been tested, since loops over primorials and
+/// residue classes test the same numbers many times.
+struct PrimalityCache {
+    /// Bit `n` is set once `n` has been tested.
+    known: Box<[u64]>,
+    /// Bit `n` is set if `n` is prime; meaningless unless it's known.
+    prime: Box<[u64]>,
+}
+
+impl PrimalityCache {
+    fn new() -> Self {
+        let words = (PRIMALITY_CACHE_BOUND as usize).div_ceil(u64::BITS as usize);
+        PrimalityCache {
+            known: vec![0; words].into_boxed_slice(),
+            prime: vec![0; words].into_boxed_slice(),
         }
-        let next_term = (sequence[sequence.len() - 2] + sequence[sequence.len() - 1]) % modulus;
-        if next_term == 0 {
-            zeros += 1;
+    }
+
+    fn get_or_test(&mut self, val: usize, test: impl FnOnce() -> bool) -> bool {
+        let (word, mask) = (val / u64::BITS as usize, 1 << (val % u64::BITS as usize));
+        if self.known[word] & mask == 0 {
+            self.known[word] |= mask;
+            if test() {
+                self.prime[word] |= mask;
+            }
         }
-        if zeros == 4 && next_term == sequence[0] {
-            return sequence[(term % (sequence.len() as NumericFactor)) as usize];
+        self.prime[word] & mask != 0
+    }
+}
+
+thread_local! {
+    static PRIMALITY_CACHE: RefCell<PrimalityCache> = RefCell::new(PrimalityCache::new());
+}
+
+pub(crate) fn is_prime(val: NumericFactor) -> bool {
+    if val >= PRIMALITY_CACHE_BOUND {
+        return is_prime_uncached(val);
+    }
+    PRIMALITY_CACHE
+        .with_borrow_mut(|cache| cache.get_or_test(val as usize, || is_prime_uncached(val)))
+}
+
+fn is_prime_uncached(val: NumericFactor) -> bool {
+    SIEVE.with_borrow(|sieve| sieve.is_prime(&val, None)) != No
+}
+
+/// Returns `(I(term), lucas(term))` reduced by `reducer`'s modulus, using fast doubling so that
+/// it's correct for any term and modulus in O(log(term)) steps.
+fn fibonacci_and_lucas_modulo<T: Reducer<NumericFactor> + Clone>(
+    term: NumericFactor,
+    reducer: &ReducedInt<NumericFactor, T>,
+) -> (ReducedInt<NumericFactor, T>, ReducedInt<NumericFactor, T>) {
+    // (f, g) = (I(n), I(n+1)), where n is the bits of term seen so far
+    let mut f = reducer.convert(0);
+    let mut g = reducer.convert(1);
+    for bit in (0..NumericFactor::BITS - term.leading_zeros()).rev() {
+        // I(2n) = I(n) * (2*I(n+1) - I(n)); I(2n+1) = I(n)^2 + I(n+1)^2
+        let f_double = f.clone() * (g.clone().double() - f.clone());
+        let g_double = f.square() + g.square();
+        if (term >> bit) & 1 == 1 {
+            f = g_double.clone();
+            g = f_double + g_double;
+        } else {
+            f = f_double;
+            g = g_double;
         }
-        sequence.push(next_term);
     }
+    // lucas(n) = 2*I(n+1) - I(n)
+    let lucas = g.double() - f.clone();
+    (f, lucas)
 }
 
 pub(crate) fn simplify(expr: &Factor) -> Factor {
@@ -2994,6 +3888,22 @@ fn simplify_divide_internal(
         }
     }
 
+    // General cancellation: a divisor term that exactly divides the numerator cancels even if it's
+    // not one of the numerator's terms, e.g. (x^3*y)/z/x -> (x^2*y)/z
+    if final_left != Factor::one() {
+        let keys: Vec<_> = current_right.keys().cloned().collect();
+        for term in keys {
+            while let Some(exponent) = current_right.get_mut(&term)
+                && *exponent > 0
+                && let Some(quotient) = div_exact(&final_left, &term)
+            {
+                changed = true;
+                *exponent -= 1;
+                final_left = simplify(&quotient);
+            }
+        }
+    }
+
     if changed {
         current_right.retain(|term, exponent| *exponent != 0 && *term != Factor::one());
         if current_right.is_empty() {
@@ -3084,6 +3994,54 @@ fn simplify_multiply_internal(terms: &BTreeMap<Factor, NumberLength>) -> Option<
     }
 }
 
+/// Evaluates `expr` exactly when it's too large for [evaluate_as_numeric] but fits in 512 bits.
+/// Only numbers, factorials up to [MAX_BIGNUM_FACTORIAL_TERM], powers (including towers such as
+/// `2^3^5`) of up to [MAX_BIGNUM_POWER_BITS] bits, and products of these are supported.
+pub(crate) fn evaluate_as_bignum(expr: &Factor) -> Option<U512> {
+    if let Some(n) = evaluate_as_numeric(expr) {
+        return Some(U512::from(n));
+    }
+    match expr {
+        Factor::BigNumber { .. } => U512::from_dec_str(&expr.to_unelided_string()).ok(),
+        Complex { inner: c, .. } => match **c {
+            Factorial(ref term) => {
+                let term = evaluate_as_numeric(term)?;
+                if term > MAX_BIGNUM_FACTORIAL_TERM.load(Acquire).into() {
+                    return None;
+                }
+                (2..=term).try_fold(U512::one(), |product, i| product.checked_mul(U512::from(i)))
+            }
+            Power {
+                ref base,
+                ref exponent,
+            } => {
+                let exponent = u32::try_from(evaluate_as_numeric(exponent)?).ok()?;
+                let base = evaluate_as_bignum(base)?;
+                if base <= U512::one() {
+                    return Some(base);
+                }
+                // Upper bound on the result's size, so that oversized towers stay symbolic
+                let max_bits = u64::from(exponent).checked_mul(base.bits() as u64)?;
+                if max_bits > u64::from(MAX_BIGNUM_POWER_BITS.load(Acquire)) {
+                    return None;
+                }
+                base.checked_pow(U512::from(exponent))
+            }
+            Multiply { ref terms, .. } => {
+                terms
+                    .iter()
+                    .try_fold(U512::one(), |product, (term, &exponent)| {
+                        product.checked_mul(
+                            evaluate_as_bignum(term)?.checked_pow(U512::from(exponent))?,
+                        )
+                    })
+            }
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
 pub(crate) fn evaluate_as_numeric(expr: &Factor) -> Option<NumericFactor> {
     if let Numeric(n) = expr {
         return Some(*n);
@@ -3129,6 +4087,10 @@ pub(crate) fn evaluate_as_numeric(expr: &Factor) -> Option<NumericFactor> {
                             }
                         }
                     }
+                    Modulo {
+                        ref left,
+                        ref right,
+                    } => modulo_as_numeric(left, evaluate_as_numeric(right)?),
                     Primorial(ref term) => {
                         let term = evaluate_as_numeric(term)?;
                         match term {
@@ -3145,6 +4107,53 @@ pub(crate) fn evaluate_as_numeric(expr: &Factor) -> Option<NumericFactor> {
                             ),
                         }
                     }
+                    Tribonacci(ref term) => {
+                        let term = evaluate_as_numeric(term)?;
+                        match term {
+                            149.. => None,
+                            0 | 1 => Some(0),
+                            n => {
+                                let (mut a, mut b, mut c): (NumericFactor, _, _) = (0, 0, 1);
+                                for _ in 2..n {
+                                    (a, b, c) = (b, c, a.checked_add(b)?.checked_add(c)?);
+                                }
+                                Some(c)
+                            }
+                        }
+                    }
+                    Pell(ref term) => {
+                        let term = evaluate_as_numeric(term)?;
+                        match term {
+                            102.. => None,
+                            0 => Some(0),
+                            n => {
+                                let (mut a, mut b): (NumericFactor, NumericFactor) = (0, 1);
+                                for _ in 1..n {
+                                    (a, b) = (b, b.checked_mul(2)?.checked_add(a)?);
+                                }
+                                Some(b)
+                            }
+                        }
+                    }
+                    Subfactorial(ref term) => {
+                        let term = evaluate_as_numeric(term)?;
+                        match term {
+                            35.. => None,
+                            x => {
+                                // !n = n*!(n-1) + (-1)^n, with !0 = 1
+                                let mut result: NumericFactor = 1;
+                                for i in 1..=x {
+                                    result = result.checked_mul(i)?;
+                                    result = if i.is_multiple_of(2) {
+                                        result.checked_add(1)?
+                                    } else {
+                                        result.checked_sub(1)?
+                                    };
+                                }
+                                Some(result)
+                            }
+                        }
+                    }
                     Power {
                         ref base,
                         ref exponent,
@@ -3267,12 +4276,25 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
                         }
                         Factorial(ref term) => {
                             // factorial
-                            if let Some(input) = evaluate_as_numeric(term) {
-                                let mut factors = BTreeMap::new();
-                                for i in 2..=input {
-                                    sum_factor_btreemaps(&mut factors, find_factors_of_numeric(i));
-                                }
-                                factors
+                            if let Some(input) = evaluate_as_numeric(term)
+                                && let Ok(input) = u64::try_from(input)
+                            {
+                                // Legendre's formula: the exponent of p in n! is the sum of
+                                // floor(n / p^i) for all i
+                                SIEVE.with_borrow_mut(|sieve| {
+                                    sieve
+                                        .primes(input)
+                                        .map(|&prime| {
+                                            let mut exponent = 0;
+                                            let mut quotient = input;
+                                            while quotient >= prime {
+                                                quotient /= prime;
+                                                exponent += quotient;
+                                            }
+                                            (Numeric(prime.into()), exponent as NumberLength)
+                                        })
+                                        .collect()
+                                })
                             } else {
                                 warn!("Could not parse input to factorial function: {}", term);
                                 BTreeMap::new()
@@ -3280,19 +4302,31 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
                         }
                         Primorial(ref term) => {
                             // primorial
-                            if let Some(input) = evaluate_as_numeric(term) {
-                                let mut factors = BTreeMap::new();
-                                for i in 2..=input {
-                                    if is_prime(i) {
-                                        factors.insert(Numeric(i), 1);
-                                    }
-                                }
-                                factors
+                            // n# is the product of the primes <= n, whether or not n is prime
+                            if let Some(input) = evaluate_as_numeric(term)
+                                && let Ok(input) = u64::try_from(input)
+                            {
+                                SIEVE.with_borrow_mut(|sieve| {
+                                    sieve
+                                        .primes(input)
+                                        .map(|&prime| (Numeric(prime.into()), 1))
+                                        .collect()
+                                })
                             } else {
                                 warn!("Could not parse input to primorial function: {}", term);
                                 BTreeMap::new()
                             }
                         }
+                        Subfactorial(ref term) => {
+                            // subfactorial; evaluable inputs are factored numerically above
+                            warn!("Can't evaluate subfactorial of {} numerically", term);
+                            BTreeMap::new()
+                        }
+                        Tribonacci(_) | Pell(_) | Modulo { .. } => {
+                            // evaluable terms are factored numerically above
+                            warn!("Can't evaluate {} numerically", expr);
+                            BTreeMap::new()
+                        }
                         Power {
                             ref base,
                             ref exponent,
@@ -3313,7 +4347,9 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
                             ref right,
                             ..
                         } => {
-                            if let Some(exact_div) =
+                            if let Some(factors) = primorial_quotient_factors(left, right) {
+                                factors
+                            } else if let Some(exact_div) =
                                 div_exact(left, &simplify_multiply(right.clone()))
                             {
                                 find_factors(&exact_div)
@@ -3369,6 +4405,11 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
                                     }
                                 }
                                 if right_remaining_factors.is_empty() {
+                                    merge_small_prime_factors_of_quotient(
+                                        &mut left_remaining_factors,
+                                        left,
+                                        right,
+                                    );
                                     return left_remaining_factors;
                                 }
                                 let mut left_recursive_factors = BTreeMap::new();
@@ -3481,29 +4522,30 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
                                     &mut left_recursive_factors,
                                     left_remaining_factors,
                                 );
+                                merge_small_prime_factors_of_quotient(
+                                    &mut left_recursive_factors,
+                                    left,
+                                    right,
+                                );
                                 left_recursive_factors
                             }
                         }
                         Multiply { ref terms, .. } => {
-                            if terms.is_empty() {
-                                return BTreeMap::new();
-                            }
-                            if terms.len() == 1 {
-                                let (factor, power) = terms.first_key_value().unwrap();
-                                return match power {
-                                    0 => BTreeMap::new(),
-                                    1 => find_factors(&simplify(factor)),
-                                    _ => [(simplify(factor), *power)].into(),
-                                };
-                            }
-                            // multiplication
+                            // multiplication; each term's factors are raised to its exponent,
+                            // so that (a*b)^k yields a^k and b^k
                             let mut factors = BTreeMap::new();
-                            for (term, exponent) in terms {
+                            for (term, &exponent) in terms {
+                                if exponent == 0 {
+                                    continue;
+                                }
                                 let term = simplify(term);
-                                let term_factors = find_factors(&term);
+                                let mut term_factors = find_factors(&term);
                                 if term_factors.is_empty() {
                                     *factors.entry(term).or_insert(0) += exponent;
                                 } else {
+                                    term_factors
+                                        .values_mut()
+                                        .for_each(|term_exponent| *term_exponent *= exponent);
                                     sum_factor_btreemaps(&mut factors, term_factors);
                                 }
                             }
@@ -3543,6 +4585,13 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
                                             *algebraic.entry(term).or_insert(0) += exponent;
                                         }
                                     }
+                                    for term in mersenne_divisor_factors(terms)
+                                        .into_iter()
+                                        .chain(generalized_fermat_factors(terms))
+                                        .chain(leyland_factors(terms))
+                                    {
+                                        algebraic.entry(simplify(&term)).or_insert(1);
+                                    }
                                     let factors = multiset_union(vec![common_factors, algebraic]);
                                     let cofactors = factors
                                         .iter()
@@ -3608,7 +4657,338 @@ fn find_factors(expr: &Factor) -> BTreeMap<Factor, NumberLength> {
     }
 }
 
+/// If `terms` is `2^x-1` with composite numeric `x`, returns `2^d-1` for every proper divisor
+/// `d > 1` of `x`, all of which divide it. [to_like_powers] only finds these for prime `x/d`.
+fn mersenne_divisor_factors(terms: &BTreeMap<Factor, i128>) -> Vec<Factor> {
+    if terms.len() != 2 || terms.get(&Factor::one()) != Some(&-1) {
+        return vec![];
+    }
+    let Some((Complex { inner: c, .. }, 1)) =
+        terms.iter().find(|(term, _)| **term != Factor::one())
+    else {
+        return vec![];
+    };
+    let exponent = match **c {
+        Multiply { ref terms, .. } if terms.len() == 1 => match terms.iter().next() {
+            Some((Numeric(2), exponent)) => *exponent,
+            _ => return vec![],
+        },
+        Power {
+            ref base,
+            ref exponent,
+        } if base.as_numeric() == Some(2) => {
+            match evaluate_as_numeric(exponent).and_then(|e| NumberLength::try_from(e).ok()) {
+                Some(exponent) => exponent,
+                None => return vec![],
+            }
+        }
+        _ => return vec![],
+    };
+    let mut divisors = BTreeSet::new();
+    let mut d: NumberLength = 2;
+    while d.saturating_mul(d) <= exponent {
+        if exponent.is_multiple_of(d) {
+            divisors.insert(d);
+            divisors.insert(exponent / d);
+        }
+        d += 1;
+    }
+    divisors.remove(&exponent);
+    if divisors.is_empty() && exponent > 2 {
+        return mersenne_prime_exponent_factors(exponent);
+    }
+    divisors
+        .into_iter()
+        .map(|d| {
+            Factor::add_sub(
+                [
+                    (Factor::multiply([(Factor::two(), d)].into()), 1),
+                    (Factor::one(), -1),
+                ]
+                .into(),
+            )
+        })
+        .collect()
+}
+
+/// Bound on the prime factors [mersenne_prime_exponent_factors] looks for.
+const MERSENNE_FACTOR_SEARCH_LIMIT: u64 = 1 << 22;
+
+/// For prime `p`, every prime factor of 2^p-1 is 1 mod 2p and +-1 mod 8, so only primes in that
+/// residue class need to be tried.
+fn mersenne_prime_exponent_factors(p: NumberLength) -> Vec<Factor> {
+    let modulus = 2 * u64::from(p);
+    with_primes_in_class(modulus, 1, MERSENNE_FACTOR_SEARCH_LIMIT, |candidates| {
+        candidates
+            .iter()
+            .copied()
+            .filter(|q| matches!(q % 8, 1 | 7))
+            // A small enough 2^p-1 can be its own only candidate
+            .filter(|&q| p >= u64::BITS || q < (1 << p) - 1)
+            .filter(|&q| {
+                let two = MontgomeryInt::new(2, &NumericFactor::from(q));
+                two.pow(&p.into()).residue() == 1
+            })
+            .map(|q| Numeric(q.into()))
+            .collect()
+    })
+}
+
+/// Bound on the prime factors [generalized_fermat_factors] looks for.
+const GENERALIZED_FERMAT_FACTOR_SEARCH_LIMIT: u64 = 1 << 22;
+
+/// Returns `(b, e)` if `term` is `b^e` with numeric `b` and `e`.
+fn as_numeric_power(term: &Factor) -> Option<(NumericFactor, NumberLength)> {
+    let Complex { inner: c, .. } = term else {
+        return None;
+    };
+    match **c {
+        Multiply { ref terms, .. } if terms.len() == 1 => {
+            let (base, exponent) = terms.iter().next()?;
+            Some((base.as_numeric()?, *exponent))
+        }
+        Power {
+            ref base,
+            ref exponent,
+        } => Some((
+            base.as_numeric()?,
+            NumberLength::try_from(evaluate_as_numeric(exponent)?).ok()?,
+        )),
+        _ => None,
+    }
+}
+
+/// If `terms` is a generalized Fermat number `b^(2^n)+1` with numeric `b`, returns the prime
+/// factors it has below [GENERALIZED_FERMAT_FACTOR_SEARCH_LIMIT]. These are all 1 mod 2^(n+1), or
+/// 1 mod 2^(n+2) when `b` is 2 and `n` is at least 2, so only primes in that class are tried.
+/// When `n` is 1 and `b = 2y^2`, also returns the Aurifeuillian factors `2y^2-2y+1` and
+/// `2y^2+2y+1`.
+fn generalized_fermat_factors(terms: &BTreeMap<Factor, i128>) -> Vec<Factor> {
+    if terms.len() != 2 || terms.get(&Factor::one()) != Some(&1) {
+        return vec![];
+    }
+    let Some((power, 1)) = terms.iter().find(|(term, _)| **term != Factor::one()) else {
+        return vec![];
+    };
+    let Some((base, exponent)) = as_numeric_power(&simplify(power)) else {
+        return vec![];
+    };
+    if base < 2 || exponent < 2 || !exponent.is_power_of_two() {
+        return vec![];
+    }
+    let n = exponent.trailing_zeros();
+    let mut factors = Vec::new();
+    if n == 1
+        && base.is_multiple_of(2)
+        && let Some(y) = (base / 2).nth_root_exact(2)
+        // When y is 1, the factors are 1 and 5 = 2^2+1 itself
+        && y > 1
+        && let Some(plus) = (base + 1).checked_add(2 * y)
+    {
+        // 4y^4+1 = (2y^2-2y+1)(2y^2+2y+1)
+        factors.push(Numeric(base + 1 - 2 * y));
+        factors.push(Numeric(plus));
+    }
+    let modulus_log2 = if base == 2 && n >= 2 { n + 2 } else { n + 1 };
+    if GENERALIZED_FERMAT_FACTOR_SEARCH_LIMIT >> modulus_log2 == 0 {
+        return factors;
+    }
+    with_primes_in_class(
+        1 << modulus_log2,
+        1,
+        GENERALIZED_FERMAT_FACTOR_SEARCH_LIMIT,
+        |candidates| {
+            factors.extend(
+                candidates
+                    .iter()
+                    .map(|&q| NumericFactor::from(q))
+                    .filter(|&q| {
+                        let base = MontgomeryInt::new(base % q, &q);
+                        // b^(2^n) = -1 (mod q)
+                        base.pow(&exponent.into()).residue() == q - 1
+                    })
+                    .map(Numeric),
+            )
+        },
+    );
+    factors
+}
+
+/// Bound on the prime factors [leyland_factors] looks for.
+const LEYLAND_FACTOR_SEARCH_LIMIT: u64 = 1 << 16;
+
+/// True if `term` is `base^exponent`, either as a power or already evaluated.
+fn is_numeric_power(term: &Factor, base: NumericFactor, exponent: NumericFactor) -> bool {
+    match as_numeric_power(term) {
+        Some((term_base, term_exponent)) => {
+            term_base == base && NumericFactor::from(term_exponent) == exponent
+        }
+        None => term.as_numeric().is_some_and(|n| {
+            u32::try_from(exponent)
+                .ok()
+                .and_then(|exponent| base.checked_pow(exponent))
+                == Some(n)
+        }),
+    }
+}
+
+/// The input `n` of a primorial `n#`, if it's numeric.
+fn as_numeric_primorial_input(expr: &Factor) -> Option<u64> {
+    let Complex { inner: ref c, .. } = *expr else {
+        return None;
+    };
+    let Primorial(ref term) = **c else {
+        return None;
+    };
+    u64::try_from(evaluate_as_numeric(term)?).ok()
+}
+
+/// If `left / right` is a primorial quotient `p# / q#` with `q < p`, returns its factors: the primes
+/// in `(q, p]`, since those up to `q` cancel out.
+fn primorial_quotient_factors(
+    left: &Factor,
+    right: &BTreeMap<Factor, NumberLength>,
+) -> Option<BTreeMap<Factor, NumberLength>> {
+    let [(denominator, &1)] = *right.iter().collect::<Vec<_>>() else {
+        return None;
+    };
+    let upper = as_numeric_primorial_input(left)?;
+    let lower = as_numeric_primorial_input(denominator)?;
+    if lower >= upper {
+        return None;
+    }
+    Some(SIEVE.with_borrow_mut(|sieve| {
+        sieve
+            .primes(upper)
+            .filter(|&&prime| prime > lower)
+            .map(|&prime| (Numeric(prime.into()), 1))
+            .collect()
+    }))
+}
+
+/// Returns how many times `prime` divides `expr`, or None if its residues can't be calculated. Stops
+/// counting at the highest power of `prime` that fits in a [NumericFactor].
+fn small_prime_multiplicity(expr: &Factor, prime: NumericFactor) -> Option<NumberLength> {
+    let mut prime_to_power = prime;
+    let mut multiplicity = 0;
+    while modulo_as_numeric(expr, prime_to_power)? == 0 {
+        multiplicity += 1;
+        let Some(new_power) = prime_to_power.checked_mul(prime) else {
+            break;
+        };
+        prime_to_power = new_power;
+    }
+    Some(multiplicity)
+}
+
+/// Finds the [SMALL_PRIMES] that divide `left / right` by comparing how many times each one divides
+/// the numerator and the denominator, which works even when [div_exact] can't simplify the quotient.
+/// Since [small_prime_multiplicity] stops counting, the multiplicities found may be too low, but
+/// never too high.
+fn small_prime_factors_of_quotient(
+    left: &Factor,
+    right: &BTreeMap<Factor, NumberLength>,
+) -> BTreeMap<Factor, NumberLength> {
+    SMALL_PRIMES
+        .iter()
+        .filter_map(|&prime| {
+            let prime = prime as NumericFactor;
+            let left_multiplicity = small_prime_multiplicity(left, prime)?;
+            if left_multiplicity == 0 {
+                return None;
+            }
+            let mut right_multiplicity: NumberLength = 0;
+            for (term, exponent) in right {
+                right_multiplicity = right_multiplicity.saturating_add(
+                    small_prime_multiplicity(term, prime)?.saturating_mul(*exponent),
+                );
+            }
+            let multiplicity = left_multiplicity.checked_sub(right_multiplicity)?;
+            (multiplicity > 0).then_some((Numeric(prime), multiplicity))
+        })
+        .collect()
+}
+
+/// Adds the factors found by [small_prime_factors_of_quotient] to `factors`, keeping whichever
+/// multiplicity is higher for primes that were already found symbolically.
+fn merge_small_prime_factors_of_quotient(
+    factors: &mut BTreeMap<Factor, NumberLength>,
+    left: &Factor,
+    right: &BTreeMap<Factor, NumberLength>,
+) {
+    for (prime, multiplicity) in small_prime_factors_of_quotient(left, right) {
+        let known_multiplicity = factors.entry(prime).or_insert(0);
+        *known_multiplicity = (*known_multiplicity).max(multiplicity);
+    }
+}
+
+/// If `terms` is a Leyland number `x^y+y^x` with numeric `x` and `y`, returns the prime factors it
+/// has below [LEYLAND_FACTOR_SEARCH_LIMIT]. These numbers have no algebraic factorization, but
+/// each small prime can be ruled out by its residue without evaluating them.
+fn leyland_factors(terms: &BTreeMap<Factor, i128>) -> Vec<Factor> {
+    let [(a, &1), (b, &1)] = *terms.iter().collect::<Vec<_>>() else {
+        return vec![];
+    };
+    let (a, b) = (simplify(a), simplify(b));
+    let Some((x, y)) = [(&a, &b), (&b, &a)].into_iter().find_map(|(power, other)| {
+        let (x, y) = as_numeric_power(power)?;
+        let y = NumericFactor::from(y);
+        (x >= 2 && y >= 2 && x != y && is_numeric_power(other, y, x)).then_some((x, y))
+    }) else {
+        return vec![];
+    };
+    info!("{x}^{y}+{y}^{x} is a Leyland number; checking small primes by residue");
+    with_primes_in_class(1, 0, LEYLAND_FACTOR_SEARCH_LIMIT, |candidates| {
+        candidates
+            .iter()
+            .map(|&q| NumericFactor::from(q))
+            .filter(|&q| {
+                if q == 2 {
+                    // x^y and y^x have the parities of x and y
+                    return x % 2 == y % 2;
+                }
+                let x_mod_q = MontgomeryInt::new(x % q, &q);
+                let y_mod_q = MontgomeryInt::new(y % q, &q);
+                (x_mod_q.pow(&y) + y_mod_q.pow(&x)).residue() == 0
+            })
+            .map(Numeric)
+            .collect()
+    })
+}
+
+/// Longest decimal literal that [big_number_as_power] checks, since it takes a root for every prime
+/// exponent up to the number's bit length.
+const MAX_PERFECT_POWER_CHECK_DIGITS: usize = 1000;
+
+/// If the decimal literal `digits` is a perfect power `root^exponent`, returns `root` and the
+/// smallest prime `exponent` that works. `root` may itself be a perfect power.
+fn big_number_as_power(digits: &str) -> Option<(Factor, NumberLength)> {
+    if digits.len() > MAX_PERFECT_POWER_CHECK_DIGITS {
+        return None;
+    }
+    let n: BigUint = digits.parse().ok()?;
+    let max_exponent = n.bits();
+    let exponents: Vec<u64> =
+        SIEVE.with_borrow_mut(|sieve| sieve.primes(max_exponent).copied().collect());
+    exponents.into_iter().find_map(|exponent| {
+        let exponent = u32::try_from(exponent).ok()?;
+        let root = n.nth_root(exponent);
+        (root.pow(exponent) == n).then(|| (Factor::from(root.to_string().as_str()), exponent))
+    })
+}
+
 fn factor_big_num(expr: &str) -> BTreeMap<Factor, NumberLength> {
+    if let Some((root, exponent)) = big_number_as_power(expr) {
+        // A listed x^k given as digits factors as x does
+        let mut factors = find_factors(&root);
+        if factors.is_empty() {
+            factors.insert(root, 1);
+        }
+        factors
+            .values_mut()
+            .for_each(|root_exponent| *root_exponent *= exponent);
+        return factors;
+    }
     let mut factors = BTreeMap::new();
     let mut expr_short = expr;
     let orig_length = expr_short.len();
@@ -3988,11 +5368,94 @@ mod tests {
         assert_eq!(evaluate_as_numeric("2^3^4"), Some(1 << 81));
     }
 
+    #[test]
+    fn test_perfect_power_big_number() {
+        // (2^3 * 3^2 * 7 * 1000000007 * 1000000009 * 998244353)^2
+        let square = "253124866195891004666094667851584407207994772556940698959936";
+        assert_eq!(
+            super::big_number_as_power(square),
+            Some((Numeric(503115161961842494288254696456), 2))
+        );
+        assert_eq!(
+            super::find_factors(&Factor::from(square)),
+            [
+                (Numeric(2), 6),
+                (Numeric(3), 4),
+                (Numeric(7), 2),
+                (Numeric(998244353), 2),
+                (Numeric(1000000007), 2),
+                (Numeric(1000000009), 2),
+            ]
+            .into()
+        );
+        assert_eq!(super::big_number_as_power(&format!("{square}1")), None);
+    }
+
+    #[test]
+    fn test_power_tower_as_bignum() {
+        use crate::algebraic::{estimate_log10, evaluate_as_bignum};
+        use primitive_types::U512;
+
+        // 2^243 is too large for u128 but fits in 512 bits
+        assert_eq!(evaluate_as_numeric("2^3^5"), None);
+        assert_eq!(
+            evaluate_as_bignum(&"2^3^5".into()),
+            Some(U512::one() << 243)
+        );
+        assert_eq!(
+            evaluate_as_bignum(&"(2^3^5)*3".into()),
+            Some((U512::one() << 243) * U512::from(3))
+        );
+        // 2^729 doesn't, so it stays symbolic
+        assert_eq!(evaluate_as_bignum(&"2^3^6".into()), None);
+        assert_eq!(evaluate_as_bignum(&"1^3^100".into()), Some(U512::one()));
+
+        // 243 * log10(2) = 73.15
+        assert_eq!(estimate_log10(&"2^3^5".into()), (73, 74));
+        // 3^50 > 10^23, so 2^3^50 has far more digits than fit in a NumberLength
+        assert_eq!(
+            estimate_log10(&"2^3^50".into()),
+            (NumberLength::MAX, NumberLength::MAX)
+        );
+    }
+
+    #[test]
+    fn test_digits() {
+        use primitive_types::U512;
+
+        assert_eq!(Factor::from("2^10").digits().as_deref(), Some("1024"));
+        assert_eq!(
+            Factor::from("2^3^5").digits().as_deref(),
+            Some((U512::one() << 243).to_string().as_str())
+        );
+        assert_eq!(Factor::from("2^3^6").digits(), None);
+        assert_eq!(Factor::from("lucas(1000)").digits(), None);
+    }
+
     #[test]
     fn test_division_associativity() {
         assert_eq!(evaluate_as_numeric("20/5/2"), Some(2));
     }
 
+    #[test]
+    fn test_division_chain_cancels_non_adjacent_divisor() {
+        use crate::algebraic::simplify;
+        // The last divisor is a term of the numerator, but the one between them isn't
+        assert_eq!(
+            simplify(&Factor::from("(2^127-1)*(3^50+1)/7/(3^50+1)")),
+            simplify(&Factor::from("(2^127-1)/7"))
+        );
+        // The last divisor divides the numerator without being one of its terms
+        assert_eq!(
+            simplify(&Factor::from("(2^127-1)^3/5/(2^127-1)")),
+            simplify(&Factor::from("(2^127-1)^2/5"))
+        );
+        assert_eq!(
+            simplify(&Factor::from("(2^127-1)^3/(2^127-1)/5/(2^127-1)")),
+            simplify(&Factor::from("(2^127-1)/5"))
+        );
+    }
+
     #[test]
     fn test_stack_depth() {
         // unsafe { backtrace_on_stack_overflow::enable() };
@@ -4219,6 +5682,20 @@ mod tests {
         );
         assert_eq!(evaluate_as_numeric("(5^6+1)^2-1"), Some(244171875));
         assert_eq!(evaluate_as_numeric("3^3+4^4+5^5"), Some(3408));
+        assert_eq!(evaluate_as_numeric("!0"), Some(1));
+        assert_eq!(evaluate_as_numeric("!1"), Some(0));
+        assert_eq!(evaluate_as_numeric("!5"), Some(44));
+        assert_eq!(evaluate_as_numeric("!(2+2)"), Some(9));
+        assert_eq!(evaluate_as_numeric("trib(0)"), Some(0));
+        assert_eq!(evaluate_as_numeric("trib(2)"), Some(1));
+        assert_eq!(evaluate_as_numeric("trib(11)"), Some(149));
+        assert_eq!(evaluate_as_numeric("pell(0)"), Some(0));
+        assert_eq!(evaluate_as_numeric("pell(1)"), Some(1));
+        assert_eq!(evaluate_as_numeric("pell(11)"), Some(5741));
+        assert!(evaluate_as_numeric("trib(148)").is_some());
+        assert_eq!(evaluate_as_numeric("trib(149)"), None);
+        assert!(evaluate_as_numeric("pell(101)").is_some());
+        assert_eq!(evaluate_as_numeric("pell(102)"), None);
     }
 
     #[test]
@@ -4227,6 +5704,28 @@ mod tests {
             Some(1),
             modulo_as_numeric_no_evaluate(&"1234512345123451234512345123451234512345".into(), 2)
         );
+        assert_eq!(Some(5), modulo_as_numeric_no_evaluate(&"!10".into(), 7));
+        assert_eq!(Some(5), modulo_as_numeric_no_evaluate(&"!38".into(), 7));
+        assert_eq!(
+            Some(149 % 13),
+            modulo_as_numeric_no_evaluate(&"trib(11)".into(), 13)
+        );
+        assert_eq!(
+            Some(5741 % 13),
+            modulo_as_numeric_no_evaluate(&"pell(11)".into(), 13)
+        );
+    }
+
+    #[test]
+    fn test_modulo_operator() {
+        assert_eq!(evaluate_as_numeric("2^10%7"), Some(2));
+        // 2^200 is too large to evaluate, but its residue isn't
+        assert_eq!(evaluate_as_numeric("2^200%1000"), Some(376));
+        assert_eq!(
+            super::find_factors(&Factor::from("2^200%1000")),
+            [(Numeric(2), 3), (Numeric(47), 1)].into()
+        );
+        assert_eq!(evaluate_as_numeric("lucas(1000)%0"), None);
     }
 
     #[test]
@@ -4291,6 +5790,58 @@ mod tests {
         assert!(!may_be_proper_divisor_of("12345", "0"));
     }
 
+    #[test]
+    fn test_may_be_proper_divisor_of_quadratic_forms() {
+        fn may_be_proper_divisor_of(left: &str, right: &str) -> bool {
+            Factor::from(left).may_be_proper_divisor_of(&Factor::from(right))
+        }
+        assert!(!may_be_proper_divisor_of("3", "x^2+1"));
+        assert!(!may_be_proper_divisor_of("4", "x^2+1"));
+        assert!(!may_be_proper_divisor_of("35", "x^2+1"));
+        assert!(may_be_proper_divisor_of("2", "x^2+1"));
+        assert!(may_be_proper_divisor_of("65", "x^2+1"));
+        assert!(!may_be_proper_divisor_of("2", "x^2+x+1"));
+        assert!(!may_be_proper_divisor_of("5", "x^2-x+1"));
+        assert!(!may_be_proper_divisor_of("9", "x^2+x+1"));
+        assert!(may_be_proper_divisor_of("3", "x^2+x+1"));
+        assert!(may_be_proper_divisor_of("7", "x^2-x+1"));
+        assert!(may_be_proper_divisor_of("5", "x^2+y+1"));
+        // -2 is a quadratic residue mod 3 and 11, but not 5, 7 or 13
+        assert!(may_be_proper_divisor_of("3", "x^2+2"));
+        assert!(may_be_proper_divisor_of("11", "x^2+2"));
+        assert!(!may_be_proper_divisor_of("5", "x^2+2"));
+        assert!(!may_be_proper_divisor_of("7", "x^2+2"));
+        // 14^2+2 = 198 = 33*6
+        assert!(may_be_proper_divisor_of("33", "x^2+2"));
+        assert!(!may_be_proper_divisor_of("15", "x^2+2"));
+        assert!(!may_be_proper_divisor_of("13", "x^2+2"));
+        // 3 is a quadratic residue mod 11, but not mod 7
+        assert!(may_be_proper_divisor_of("11", "x^2-3"));
+        assert!(!may_be_proper_divisor_of("7", "x^2-3"));
+    }
+
+    #[test]
+    fn test_is_quadratic_residue() {
+        use crate::algebraic::is_quadratic_residue;
+        for (p, residues) in [
+            (7, vec![1, 2, 4]),
+            (11, vec![1, 3, 4, 5, 9]),
+            (13, vec![1, 3, 4, 9, 10, 12]),
+        ] {
+            for a in 1..p {
+                assert_eq!(
+                    is_quadratic_residue(a, p),
+                    residues.contains(&a),
+                    "{a} mod {p}"
+                );
+            }
+        }
+        assert!(is_quadratic_residue(
+            (1 << 100) % 1_000_000_007,
+            1_000_000_007
+        ));
+    }
+
     #[test]
     fn test_find_factors_performance_1() {
         let factors = find_factors_recursive("I(969969)");
@@ -4315,8 +5866,7 @@ mod tests {
     fn test_factor_big_num_symbolic() {
         // "1212...12" (50 times)
         // Sum = (1+2)*50 = 150 (div by 3). Ends in 2 (div by 2).
-        let repeated_12 = "12".repeat(50);
-        let expr = super::expression_parser::arithmetic(&repeated_12).unwrap();
+        let expr = super::expression_parser::arithmetic("12{50}").unwrap();
         let factors = super::find_factors(&Factor::from(expr).into());
         println!(
             "{}",
@@ -4337,6 +5887,421 @@ mod tests {
         });
     }
 
+    #[test]
+    fn test_select_factoring_backend() {
+        use super::FactoringBackend::{Scalar, Siqs};
+        use super::select_factoring_backend;
+
+        assert_eq!(select_factoring_backend(false), Scalar);
+        assert_eq!(
+            select_factoring_backend(true),
+            if cfg!(feature = "scalar-factoring") {
+                Scalar
+            } else {
+                Siqs
+            }
+        );
+    }
+
+    #[test]
+    fn test_collect_backend_factors() {
+        use super::collect_backend_factors;
+
+        const INPUT: NumericFactor = 1 << 100;
+        let unfactored: BTreeMap<NumericFactor, NumberLength> = [(INPUT, 1)].into();
+        assert_eq!(
+            collect_backend_factors(INPUT, || Ok::<_, String>(vec![1 << 50, 1 << 50])),
+            [(1 << 50, 2)].into()
+        );
+        assert_eq!(
+            collect_backend_factors(INPUT, || Err::<Vec<NumericFactor>, _>("no factors found")),
+            unfactored
+        );
+        assert_eq!(
+            collect_backend_factors(INPUT, || -> Result<Vec<NumericFactor>, String> {
+                panic!("illegal instruction")
+            }),
+            unfactored
+        );
+        // Wrong product
+        assert_eq!(
+            collect_backend_factors(INPUT, || Ok::<_, String>(vec![3, 5])),
+            unfactored
+        );
+        // Product overflows
+        assert_eq!(
+            collect_backend_factors(INPUT, || Ok::<_, String>(vec![1 << 100, 1 << 100])),
+            unfactored
+        );
+    }
+
+    #[test]
+    fn test_pollard_rho_brent() {
+        use super::{pollard_rho_brent, pollard_rho_brent_with_budget};
+
+        for (p, q) in [
+            (83, 97),
+            (101, 103),
+            (1_000_000_007, 1_000_000_009),
+            ((1 << 61) - 1, (1 << 31) - 1),
+        ] {
+            let factor = pollard_rho_brent(p * q);
+            assert!(
+                factor == Some(p) || factor == Some(q),
+                "{factor:?} for {p} * {q}"
+            );
+        }
+        assert_eq!(pollard_rho_brent(2 * 1_000_000_007), Some(2));
+        assert_eq!(
+            pollard_rho_brent(1_000_000_007 * 1_000_000_007),
+            Some(1_000_000_007)
+        );
+        for prime in [2, 3, 1_000_000_007, (1 << 61) - 1, (1 << 127) - 1] {
+            assert_eq!(pollard_rho_brent(prime), None);
+        }
+        assert_eq!(pollard_rho_brent(1), None);
+        // Far more iterations than this are needed to find a 31-bit factor
+        assert_eq!(
+            pollard_rho_brent_with_budget(((1 << 61) - 1) * ((1 << 31) - 1), 16),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_factors_of_power_of_product() {
+        let factors = super::find_factors(&Factor::from("(6*35)^3"));
+        assert_eq!(
+            factors,
+            [
+                (Numeric(2), 3),
+                (Numeric(3), 3),
+                (Numeric(5), 3),
+                (Numeric(7), 3)
+            ]
+            .into()
+        );
+
+        // Too large to evaluate
+        let factors = super::find_factors(&Factor::from("(6*2^127)^3"));
+        assert_eq!(factors, [(Numeric(2), 384), (Numeric(3), 3)].into());
+        let factors = super::find_factors(&Factor::from("(6*2^127)^2*35"));
+        assert_eq!(
+            factors,
+            [
+                (Numeric(2), 256),
+                (Numeric(3), 2),
+                (Numeric(5), 1),
+                (Numeric(7), 1)
+            ]
+            .into()
+        );
+    }
+
+    #[test]
+    fn test_factorial_prime_exponents() {
+        let factors = super::find_factors(&Factor::from("20!"));
+        assert_eq!(factors.get(&Numeric(2)), Some(&18));
+        assert_eq!(factors.get(&Numeric(3)), Some(&8));
+        assert_eq!(factors.get(&Numeric(5)), Some(&4));
+        assert_eq!(factors.get(&Numeric(7)), Some(&2));
+        assert_eq!(factors.get(&Numeric(19)), Some(&1));
+        assert_eq!(factors.len(), 8);
+
+        // Too large to evaluate, so this uses Legendre's formula
+        let factors = super::find_factors(&Factor::from("40!"));
+        assert_eq!(factors.get(&Numeric(2)), Some(&38));
+        assert_eq!(factors.get(&Numeric(3)), Some(&18));
+        assert_eq!(factors.get(&Numeric(5)), Some(&9));
+        assert_eq!(factors.get(&Numeric(7)), Some(&5));
+        assert_eq!(factors.get(&Numeric(37)), Some(&1));
+        assert_eq!(factors.len(), 12);
+    }
+
+    #[test]
+    fn test_primorial_factors() {
+        let primes_up_to = |n: NumericFactor| -> BTreeMap<Factor, NumberLength> {
+            (2..=n)
+                .filter(|&i| super::is_prime(i))
+                .map(|p| (Numeric(p), 1))
+                .collect()
+        };
+        let factors = super::find_factors(&Factor::from("100#"));
+        assert_eq!(factors, primes_up_to(100));
+        assert_eq!(factors.len(), 25);
+
+        // Too large to evaluate, so this uses the sieve
+        let factors = super::find_factors(&Factor::from("200#"));
+        assert_eq!(factors, primes_up_to(200));
+        assert_eq!(factors.len(), 46);
+    }
+
+    #[test]
+    fn test_primorial_quotient_factors() {
+        assert_eq!(
+            *crate::algebraic::find_unique_factors(&Factor::from("13#/7#")),
+            [Numeric(11), Numeric(13)]
+        );
+        // Too large to evaluate, so the primes up to 990 have to cancel out
+        assert_eq!(
+            super::find_factors(&Factor::from("1000#/990#")),
+            [(Numeric(991), 1), (Numeric(997), 1)].into()
+        );
+    }
+
+    #[test]
+    fn test_small_prime_factors_of_quotient() {
+        // (10^20+11)*(10^20+8), where 10^20+8 = 2^3*3^3*233*1986965506278811 and 10^20+11 is
+        // also divisible by 3, so 3 is only found by comparing multiplicities
+        let left = Factor::from("10^40+19*10^20+88");
+        let right = [(Factor::from("10^20+11"), 1)].into();
+        assert_eq!(
+            super::small_prime_factors_of_quotient(&left, &right),
+            [(Numeric(2), 3), (Numeric(3), 3), (Numeric(233), 1)].into()
+        );
+        assert!(find_factors("(10^40+19*10^20+88)/(10^20+11)").contains(&Numeric(233)));
+    }
+
+    #[test]
+    fn test_estimate_digit_count() {
+        use crate::algebraic::estimate_digit_count;
+
+        let estimate = |expr: &str| estimate_digit_count(&Factor::from(expr));
+        assert_eq!(estimate("7"), 1);
+        assert_eq!(estimate("99"), 2);
+        assert_eq!(estimate("100"), 3);
+        assert_eq!(estimate("12345"), 5);
+        assert_eq!(
+            estimate(&("1".to_string() + &repeat_n('0', 50).collect::<String>())),
+            51
+        );
+        for (expr, digits) in [
+            ("2^607-1", 183),
+            ("10^200+1", 201),
+            ("100!", 158),
+            ("lucas(1234)", 258),
+            ("3^5000-4^2001", 2386),
+        ] {
+            assert!(
+                estimate(expr).abs_diff(digits) <= 1,
+                "{expr}: estimated {} digits, but has {digits}",
+                estimate(expr)
+            );
+        }
+        // Numbers of unknown size sort after all numbers of known size
+        assert!(estimate("1234567890...1234567890") > estimate("3^5000-4^2001"));
+        // Inconsistent bounds don't underflow
+        assert_eq!(super::digit_count_from_log10_bounds(10, 5), 11);
+    }
+
+    #[test]
+    fn test_estimate_log10_minus_small_constant() {
+        let estimate = |expr: &str| super::estimate_log10_internal(&Factor::from(expr));
+        let power_lower = estimate("2^1000").0;
+        // 2^1000 ends in 6, so it's at least 10^power_lower + 6, and subtracting 3 can't lose a
+        // digit
+        assert_eq!(estimate("2^1000-3").0, power_lower);
+        // ...but subtracting 7 might, as far as the last digit can tell
+        assert_eq!(estimate("2^1000-7").0, power_lower - 1);
+        // 10^300 is exactly a power of 10
+        assert!(estimate("10^300-7").0 < 300);
+    }
+
+    #[test]
+    fn test_custom_bignum_elision() {
+        use crate::{BignumElision, with_bignum_elision};
+        let digits = "1234567890".repeat(40);
+        let number = Factor::from(digits.as_str());
+        assert_eq!(number.to_string(), "12345678901234567890...67890<400>");
+        let elision = BignumElision {
+            threshold: 300,
+            head: 30,
+            tail: 10,
+        };
+        assert_eq!(
+            with_bignum_elision(elision, || number.to_string()),
+            "123456789012345678901234567890...1234567890<400>"
+        );
+        let elision = BignumElision {
+            threshold: 500,
+            ..elision
+        };
+        assert_eq!(with_bignum_elision(elision, || number.to_string()), digits);
+        // The default is restored afterward
+        assert_eq!(number.to_string(), "12345678901234567890...67890<400>");
+        // Even if the closure panics
+        assert!(
+            std::panic::catch_unwind(|| {
+                with_bignum_elision(BignumElision::UNELIDED, || panic!("factor mismatch"))
+            })
+            .is_err()
+        );
+        assert_eq!(number.to_string(), "12345678901234567890...67890<400>");
+    }
+
+    #[test]
+    fn test_primes_in_class() {
+        use crate::algebraic::with_primes_in_class;
+        // Candidate factors of 2^61-1 are 1 mod 122
+        let expected: Vec<u64> = (2..=100_000)
+            .filter(|&i| i % 122 == 1 && super::is_prime(i.into()))
+            .collect();
+        with_primes_in_class(122, 1, 100_000, |primes| assert_eq!(primes, expected));
+        // Served from the cache, cut off at the smaller limit
+        with_primes_in_class(122, 1, 50_000, |primes| {
+            assert_eq!(
+                primes,
+                &expected[..expected.partition_point(|&p| p <= 50_000)]
+            )
+        });
+        with_primes_in_class(122, 1, 200_000, |primes| {
+            assert_eq!(&primes[..expected.len()], expected);
+            assert!(primes.len() > expected.len());
+        });
+    }
+
+    #[test]
+    fn test_parse_repeated_digits() {
+        let repunit = Factor::from("1{50}");
+        assert_eq!(repunit, Factor::from("1".repeat(50).as_str()));
+        assert_eq!(repunit.to_unelided_string(), "1".repeat(50));
+        // R50 has a digit sum of 50, so it isn't divisible by 3, but R51 is
+        let factors = super::find_factors(&repunit);
+        assert!(!factors.contains_key(&Factor::three()));
+        let factors = super::find_factors(&Factor::from("1{51}"));
+        assert!(factors.contains_key(&Factor::three()));
+
+        assert_eq!(Factor::from("12{3}"), Numeric(121_212));
+        assert_eq!(Factor::from("2{3}+1"), Factor::from("222+1"));
+        assert!(super::expression_parser::arithmetic("1{0}").is_err());
+        assert!(super::expression_parser::arithmetic("9{1000000000}").is_err());
+        assert!(super::expression_parser::arithmetic("12{18446744073709551615}").is_err());
+    }
+
+    #[test]
+    fn test_primality_cache() {
+        use super::{PRIMALITY_CACHE_BOUND, is_prime, is_prime_uncached};
+        let values = (0..10_000)
+            .chain(PRIMALITY_CACHE_BOUND - 1_000..PRIMALITY_CACHE_BOUND + 1_000)
+            .chain([(1 << 19) - 1, 1_048_573, 1_048_583]);
+        for value in values {
+            let fresh = is_prime_uncached(value);
+            // Once to fill the cache, once to read from it
+            assert_eq!(is_prime(value), fresh, "{value}");
+            assert_eq!(is_prime(value), fresh, "{value}");
+        }
+        assert!(is_prime((1 << 19) - 1));
+        assert!(is_prime(1_048_573));
+        assert!(!is_prime(PRIMALITY_CACHE_BOUND - 1));
+    }
+
+    /// Run with `cargo test --release bench_primality_cache -- --ignored --nocapture`.
+    #[ignore]
+    #[test]
+    fn bench_primality_cache() {
+        use super::{is_prime, is_prime_uncached};
+        use std::hint::black_box;
+        use std::time::Instant;
+
+        // The same small numbers over and over, as when factoring primorials
+        const ROUNDS: usize = 50;
+        let time = |test: fn(NumericFactor) -> bool| {
+            let start = Instant::now();
+            for _ in 0..ROUNDS {
+                for value in 0..100_000 {
+                    black_box(test(black_box(value)));
+                }
+            }
+            start.elapsed()
+        };
+        let uncached = time(is_prime_uncached);
+        let cached = time(is_prime);
+        println!(
+            "Primality of 0..100000 x {ROUNDS}: {uncached:?} uncached, {cached:?} cached ({:.1}x)",
+            uncached.as_secs_f64() / cached.as_secs_f64()
+        );
+
+        let start = Instant::now();
+        black_box(find_factors_recursive("I(969969)"));
+        println!("I(969969) with the cache warm: {:?}", start.elapsed());
+    }
+
+    #[test]
+    fn test_from_batch() {
+        let batch = Factor::from_batch("2^127-1\n\n  10^50+1 ;I(1000)\r\n");
+        assert_eq!(
+            batch,
+            [
+                Factor::from("2^127-1"),
+                Factor::from("10^50+1"),
+                Factor::from("I(1000)")
+            ]
+        );
+        // A bad expression doesn't stop the rest from being parsed
+        assert_eq!(
+            Factor::from_batch("12345;2^^3;;7#"),
+            [Numeric(12345), Factor::from("7#")]
+        );
+    }
+
+    #[test]
+    fn test_evaluate_factorial_as_bignum() {
+        use crate::algebraic::evaluate_as_bignum;
+        use primitive_types::U512;
+
+        let factorial_35 = Factor::from("35!");
+        // Too large for the u128 fast path
+        assert_eq!(evaluate_as_numeric("35!"), None);
+        let expected = U512::from_dec_str("10333147966386144929666651337523200000000").unwrap();
+        assert_eq!(evaluate_as_bignum(&factorial_35), Some(expected));
+        assert_eq!(
+            evaluate_as_bignum(&"34!".into()),
+            evaluate_as_numeric("34!").map(U512::from)
+        );
+        assert_eq!(
+            evaluate_as_bignum(&"35!*3".into()),
+            Some(expected * U512::from(3))
+        );
+        // 99! doesn't fit in 512 bits
+        assert!(evaluate_as_bignum(&"98!".into()).is_some());
+        assert_eq!(evaluate_as_bignum(&"99!".into()), None);
+
+        assert_eq!(
+            modulo_as_numeric_no_evaluate(&factorial_35, (1 << 127) - 1),
+            Some(124676958757991025765413114570153656380)
+        );
+    }
+
+    #[test]
+    fn test_parse_double_star_exponent() {
+        assert_eq!(Factor::from("2**10"), Factor::from("2^10"));
+        assert_eq!(Factor::from("3**(2**5)+1"), Factor::from("3^(2^5)+1"));
+        let mersenne = Factor::from("2**127-1").to_string();
+        assert!(mersenne.contains('^') && !mersenne.contains("**"));
+        assert_eq!(Factor::from("2**3*5"), Factor::from("2^3*5"));
+    }
+
+    #[test]
+    fn test_fibonacci_lucas_modulo() {
+        for modulus in 2..60 {
+            // (X(k), X(k+1)) mod modulus
+            let (mut fibonacci, mut lucas) = ((0, 1), (2 % modulus, 1 % modulus));
+            for k in 0..300 {
+                assert_eq!(
+                    modulo_as_numeric_no_evaluate(&format!("I({k})").into(), modulus),
+                    Some(fibonacci.0),
+                    "I({k}) mod {modulus}"
+                );
+                assert_eq!(
+                    modulo_as_numeric_no_evaluate(&format!("lucas({k})").into(), modulus),
+                    Some(lucas.0),
+                    "lucas({k}) mod {modulus}"
+                );
+                fibonacci = (fibonacci.1, (fibonacci.0 + fibonacci.1) % modulus);
+                lucas = (lucas.1, (lucas.0 + lucas.1) % modulus);
+            }
+        }
+    }
+
     #[test]
     fn test_pisano() {
         assert_eq!(modulo_as_numeric_no_evaluate(&"I(2000)".into(), 5), Some(0));
@@ -4346,6 +6311,54 @@ mod tests {
         assert_eq!(modulo_as_numeric_no_evaluate(&"I(2004)".into(), 5), Some(3));
     }
 
+    #[test]
+    fn test_modulo_near_u128_max() {
+        use num_modular::ModularCoreOps;
+        const LARGEST_U128_PRIME: NumericFactor = NumericFactor::MAX - 158;
+        for modulus in [
+            NumericFactor::MAX,
+            NumericFactor::MAX - 1,
+            LARGEST_U128_PRIME,
+            (1 << 127) - 1,
+        ] {
+            let mut factorial: NumericFactor = 1;
+            let mut primorial: NumericFactor = 1;
+            for i in 2..=60 {
+                factorial = factorial.mulm(i, &modulus);
+                if super::is_prime(i) {
+                    primorial = primorial.mulm(i, &modulus);
+                }
+            }
+            assert_eq!(
+                modulo_as_numeric_no_evaluate(&"60!".into(), modulus),
+                Some(factorial),
+                "60! mod {modulus}"
+            );
+            assert_eq!(
+                modulo_as_numeric_no_evaluate(&"60#".into(), modulus),
+                Some(primorial),
+                "60# mod {modulus}"
+            );
+            let mut fibonacci: (NumericFactor, NumericFactor) = (0, 1);
+            for _ in 0..500 {
+                fibonacci = (fibonacci.1, fibonacci.0.addm(fibonacci.1, &modulus));
+            }
+            assert_eq!(
+                modulo_as_numeric_no_evaluate(&"I(500)".into(), modulus),
+                Some(fibonacci.0),
+                "I(500) mod {modulus}"
+            );
+        }
+        // Wilson's theorem
+        assert_eq!(
+            modulo_as_numeric_no_evaluate(
+                &format!("{}!", LARGEST_U128_PRIME - 1).into(),
+                LARGEST_U128_PRIME
+            ),
+            Some(LARGEST_U128_PRIME - 1)
+        );
+    }
+
     #[test]
     fn test_large_fibonacci_lucas_factors() {
         use primitive_types::U256;
@@ -4868,6 +6881,9 @@ mod tests {
             "lucas(100)",
             "5!",
             "7#",
+            "!40",
+            "trib(200)",
+            "pell(200)",
             "((a+b)*c)^d",
         ];
 
@@ -4879,6 +6895,22 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_from_checked() {
+        assert_eq!(
+            Factor::from_checked("(2^64-1)/3"),
+            Ok(Factor::from("(2^64-1)/3"))
+        );
+        assert_eq!(Factor::from_checked("x^2+1"), Ok(Factor::from("x^2+1")));
+
+        // As if the parser had failed on an expression that it parses when it's printed
+        let mismatched = Factor::UnknownExpression {
+            inner: "2^64-1".into(),
+            hash: OnceLock::new(),
+        };
+        assert_eq!(mismatched.check_round_trip(), Err(Factor::from("2^64-1")));
+    }
+
     #[test]
     fn test_to_like_powers() {
         use crate::algebraic::to_like_powers;
@@ -4909,6 +6941,37 @@ mod tests {
         assert!(!result.is_empty());
     }
 
+    #[test]
+    fn test_sum_and_difference_of_cubes() {
+        use crate::algebraic::{simplify_multiply, to_like_powers};
+        let factors = find_factors("5^3+2^3");
+        assert!(factors.contains(&Numeric(7)));
+        assert!(factors.contains(&Numeric(19)));
+
+        let x = Factor::from("2^100");
+        let y = Factor::from("3^100");
+        for y_coeff in [1, -1] {
+            let cubes = [
+                (simplify_multiply([(x.clone(), 3)].into()), 1),
+                (simplify_multiply([(y.clone(), 3)].into()), y_coeff),
+            ];
+            let result = to_like_powers(&cubes.into());
+            // x^2 -+ xy + y^2 is given explicitly, not as a quotient
+            let quadratic = result
+                .keys()
+                .find(|factor| {
+                    [1_000_003, 1_000_033].into_iter().all(|p| {
+                        let x = modulo_as_numeric_no_evaluate(&x, p).unwrap() as i128;
+                        let y = modulo_as_numeric_no_evaluate(&y, p).unwrap() as i128;
+                        let expected = (x * x - y_coeff * x * y + y * y).rem_euclid(p as i128);
+                        modulo_as_numeric_no_evaluate(factor, p) == Some(expected as u128)
+                    })
+                })
+                .unwrap_or_else(|| panic!("No quadratic factor in {result:?}"));
+            assert!(!quadratic.to_string().contains('/'));
+        }
+    }
+
     #[test]
     fn test_difference_of_squares() {
         // a^2 - b^2 -> (a-b)(a+b)
@@ -4934,6 +6997,115 @@ mod tests {
         assert_eq!(result, Some(5.into()));
     }
 
+    #[test]
+    fn test_mersenne_composite_exponent() {
+        use crate::algebraic::ComplexFactor::AddSub;
+        use crate::algebraic::mersenne_divisor_factors;
+        use std::collections::BTreeSet;
+
+        let m12 = Factor::from("M(12)");
+        let Complex { inner: ref c, .. } = m12 else {
+            panic!("M(12) didn't parse as a complex expression");
+        };
+        let AddSub { ref terms, .. } = **c else {
+            panic!("M(12) didn't parse as 2^12-1");
+        };
+        let values: BTreeSet<_> = mersenne_divisor_factors(terms)
+            .iter()
+            .map(|factor| crate::algebraic::evaluate_as_numeric(factor).unwrap())
+            .collect();
+        assert_eq!(values, [3, 7, 15, 63].into());
+
+        let factors = find_factors("2^1200-1");
+        assert!(factors.contains(&"2^300-1".into()));
+    }
+
+    #[test]
+    fn test_generalized_fermat_factors() {
+        use crate::algebraic::ComplexFactor::AddSub;
+        use crate::algebraic::generalized_fermat_factors;
+
+        let gfn_factors = |expr: &str| {
+            let Complex { inner: c, .. } = Factor::from(expr) else {
+                panic!("{expr} didn't parse as a complex expression");
+            };
+            let AddSub { ref terms, .. } = *c else {
+                panic!("{expr} didn't parse as a sum");
+            };
+            generalized_fermat_factors(terms)
+        };
+        // F6 = 2^64+1 = 274177 * 67280421310721, and 274177 is 1 mod 2^8
+        assert_eq!(gfn_factors("2^64+1"), [Numeric(274177)]);
+        assert_eq!(gfn_factors("12^64+1"), [Numeric(36097), Numeric(81281)]);
+        // Not of the form b^(2^n)+1
+        assert!(gfn_factors("12^48+1").is_empty());
+        assert!(gfn_factors("12^64-1").is_empty());
+
+        // Too large to evaluate, so these have to come from the residue-class search
+        let factors = find_factors("12^64+1");
+        assert!(factors.contains(&Numeric(36097)));
+        assert!(factors.contains(&Numeric(81281)));
+        let factors = find_factors("3^128+1");
+        assert!(factors.contains(&Numeric(257)));
+        assert!(factors.contains(&Numeric(275201)));
+
+        // Aurifeuillian: (2*10^38)^2+1 = 4*(10^19)^4+1
+        let factors = gfn_factors("200000000000000000000000000000000000000^2+1");
+        assert!(factors.contains(&Numeric(199999999999999999980000000000000000001)));
+        assert!(factors.contains(&Numeric(200000000000000000020000000000000000001)));
+        assert!(!gfn_factors("2^2+1").contains(&Factor::one()));
+    }
+
+    #[test]
+    fn test_leyland_factors() {
+        use crate::algebraic::ComplexFactor::AddSub;
+        use crate::algebraic::leyland_factors;
+
+        let leyland_factors = |expr: &str| {
+            let Complex { inner: c, .. } = Factor::from(expr) else {
+                panic!("{expr} didn't parse as a complex expression");
+            };
+            let AddSub { ref terms, .. } = *c else {
+                panic!("{expr} didn't parse as a sum");
+            };
+            leyland_factors(terms)
+        };
+        let expected = [13, 127, 223, 2053].map(Numeric);
+        assert_eq!(leyland_factors("5^222+222^5"), expected);
+        assert_eq!(leyland_factors("222^5+5^222"), expected);
+        // 3^100+100^3 is odd, and 101 is its only prime factor below the search limit
+        assert_eq!(leyland_factors("3^100+100^3"), [Numeric(101)]);
+        // Not of the form x^y+y^x
+        assert!(leyland_factors("5^222+223^5").is_empty());
+        assert!(leyland_factors("5^222-222^5").is_empty());
+
+        let factors = find_factors("5^222+222^5");
+        for factor in expected {
+            assert!(factors.contains(&factor));
+        }
+        // Small enough to evaluate, so it's factored numerically: 3^4+4^3 = 145 = 5*29
+        let factors = find_factors("3^4+4^3");
+        assert!(factors.contains(&Numeric(5)));
+        assert!(factors.contains(&Numeric(29)));
+    }
+
+    #[test]
+    fn test_mersenne_prime_exponent_factors() {
+        use crate::algebraic::mersenne_prime_exponent_factors;
+        // 2^11-1 = 23*89, 2^29-1 = 233*1103*2089
+        assert_eq!(
+            mersenne_prime_exponent_factors(11),
+            vec![Numeric(23), Numeric(89)]
+        );
+        assert_eq!(
+            mersenne_prime_exponent_factors(29),
+            vec![Numeric(233), Numeric(1103), Numeric(2089)]
+        );
+        // 2^13-1 is prime, so the only candidate it's divisible by is itself
+        assert!(mersenne_prime_exponent_factors(13).is_empty());
+        assert!(find_factors("2^83-1").contains(&Numeric(167)));
+    }
+
     #[test]
     fn test_mod_3() {
         let s = "2^1234-1";
@@ -4942,4 +7114,71 @@ mod tests {
         println!("2^1234-1 mod 3 = {:?}", m);
         assert_eq!(m, Some(0));
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_parse_long_expression() {
+        use crate::algebraic::MAX_INLINE_PARSE_LEN;
+        let digits = "7".repeat(MAX_INLINE_PARSE_LEN * 4);
+        let parsed = Factor::parse(&digits).await;
+        assert!(matches!(parsed, Factor::BigNumber { .. }));
+        assert_eq!(parsed.to_unelided_string(), digits);
+
+        let sum = repeat_n("3^5", MAX_INLINE_PARSE_LEN).join("+");
+        assert!(sum.len() > MAX_INLINE_PARSE_LEN);
+        let parsed = Factor::parse(&sum).await;
+        assert_eq!(parsed, Factor::from(sum.as_str()));
+        assert_eq!(
+            crate::algebraic::evaluate_as_numeric(&parsed),
+            Some(243 * MAX_INLINE_PARSE_LEN as NumericFactor)
+        );
+    }
+
+    #[test]
+    fn test_factor_cache_stats() {
+        use crate::algebraic::{evaluate_as_numeric, factor_cache_stats};
+        // Not numeric, so it'll be cached as None
+        let expr = Factor::from("3^400+123456789");
+        assert_eq!(evaluate_as_numeric(&expr), None);
+        let before = factor_cache_stats()[0];
+        assert!(before.len >= 1);
+        assert_eq!(evaluate_as_numeric(&expr), None);
+        let after = factor_cache_stats()[0];
+        assert!(after.hits > before.hits);
+        assert_eq!(after.name, "Numeric value");
+    }
+
+    #[test]
+    fn test_small_residues_cache() {
+        use crate::algebraic::{factor_cache_stats, small_residues};
+        let target = Factor::from("3^400+2");
+        let candidates = ["2^200", "5^200", "7^150+1", "2^150+1", "10^99"].map(Factor::from);
+        let first_pass: Vec<bool> = candidates
+            .iter()
+            .map(|candidate| candidate.may_be_proper_divisor_of(&target))
+            .collect();
+        // 3^400+2 is 3 mod 4 and 3 mod 25
+        assert!(!first_pass[0]);
+        assert!(!first_pass[1]);
+        assert!(!first_pass[4]);
+
+        let before = factor_cache_stats()[4];
+        assert_eq!(before.name, "Small residues");
+        assert!(before.len >= 1);
+        let second_pass: Vec<bool> = candidates
+            .iter()
+            .map(|candidate| candidate.may_be_proper_divisor_of(&target))
+            .collect();
+        assert_eq!(first_pass, second_pass);
+        // Every probe of the same target reused its residues instead of recomputing them
+        let after = factor_cache_stats()[4];
+        assert!(after.hits >= before.hits + candidates.len() as u64);
+        // and only computed those that a candidate's divisibility depended on
+        let residues = small_residues(&target);
+        let computed = residues
+            .iter()
+            .filter(|residue| residue.get().is_some())
+            .count();
+        assert!(computed > 0);
+        assert!(computed < residues.len() / 2);
+    }
 }
@@ -0,0 +1,77 @@
+use crate::graph::EntryId;
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Links on a `sequences.php` page to other terms of an aliquot sequence, labelled with arrows or
+/// as the previous or next term.
+static TERM_LINK_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new("index\\.php\\?id=([0-9]+)\"[^>]*>([^<]*(?:&lt;|&gt;|[Pp]rev|[Nn]ext)[^<]*)</a>")
+        .unwrap()
+});
+
+/// The neighbors of a number in the aliquot sequence that FactorDB's `sequences.php` shows it in.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct AliquotLinks {
+    /// The term whose aliquot sum is this number.
+    pub predecessor: Option<EntryId>,
+    /// This number's aliquot sum, which becomes worth factoring once this number is factored.
+    pub successor: Option<EntryId>,
+}
+
+/// Reads the predecessor and successor links from a `sequences.php` page. Double arrows jump to
+/// the start or end of the sequence, so they're skipped.
+pub fn parse_aliquot_links(page: &str) -> AliquotLinks {
+    let mut links = AliquotLinks::default();
+    for captures in TERM_LINK_REGEX.captures_iter(page) {
+        let label = &captures[2];
+        if label.contains("&lt;&lt;") || label.contains("&gt;&gt;") {
+            continue;
+        }
+        let Ok(id) = captures[1].parse::<EntryId>() else {
+            continue;
+        };
+        if label.contains("&lt;") || label.contains("rev") {
+            links.predecessor.get_or_insert(id);
+        } else {
+            links.successor.get_or_insert(id);
+        }
+    }
+    links
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::aliquot::{AliquotLinks, parse_aliquot_links};
+
+    #[test]
+    fn test_parse_aliquot_links() {
+        let page = concat!(
+            "<table><tr><td>Sequence 276, index 1577</td></tr>",
+            "<tr><td><a href=\"index.php?id=1100000004444444444\">&lt;&lt;</a> ",
+            "<a href=\"index.php?id=1100000004444444440\">&lt; Prev</a> ",
+            "<a href=\"index.php?id=1100000004444444445\"><font color=\"#002099\">",
+            "1234567890123...</font></a> = 2^3 * ",
+            "<a href=\"index.php?id=1100000004444444446\">154320986265...</a> ",
+            "<a href=\"index.php?id=1100000004444444447\">Next &gt;</a> ",
+            "<a href=\"index.php?id=1100000004444444448\">&gt;&gt;</a></td></tr></table>",
+        );
+        assert_eq!(
+            parse_aliquot_links(page),
+            AliquotLinks {
+                predecessor: Some(1100000004444444440),
+                successor: Some(1100000004444444447),
+            }
+        );
+        assert_eq!(
+            parse_aliquot_links("<a href=\"index.php?id=1\">Next</a>"),
+            AliquotLinks {
+                predecessor: None,
+                successor: Some(1),
+            }
+        );
+        assert_eq!(
+            parse_aliquot_links("Not part of any sequence"),
+            AliquotLinks::default()
+        );
+    }
+}
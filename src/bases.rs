@@ -0,0 +1,274 @@
+use crate::graph::EntryId;
+use log::{error, warn};
+use primitive_types::U256;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::ErrorKind;
+use std::path::PathBuf;
+use std::sync::LazyLock;
+
+static CERTIFICATE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new("(Verified|Processing)").unwrap());
+
+/// Remembers which PRP-check bases we've already had FactorDB run for each PRP that's still in
+/// progress, so that one requeued or interrupted by a restart resumes where it left off. Only
+/// unfinished PRPs are kept, so the file stays small enough to rewrite on every update.
+#[derive(Debug)]
+pub struct CheckedBases {
+    path: PathBuf,
+    bases: BTreeMap<EntryId, U256>,
+}
+
+impl CheckedBases {
+    /// Reads the store from `path`, which holds one line per ID in the form `id,base,base,...`.
+    /// A missing file is treated as empty.
+    pub fn load(path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        let mut bases = BTreeMap::new();
+        match fs::read_to_string(&path) {
+            Ok(text) => {
+                for line in text.lines().filter(|line| !line.is_empty()) {
+                    let mut fields = line.split(',');
+                    let Some(Ok(id)) = fields.next().map(str::parse::<EntryId>) else {
+                        warn!("Ignoring malformed line in {}: {line:?}", path.display());
+                        continue;
+                    };
+                    let mut checked = U256::zero();
+                    for base in fields {
+                        match base.parse::<u8>() {
+                            Ok(base) => checked |= U256::one() << base,
+                            Err(_) => {
+                                warn!("{id}: Ignoring invalid base {base:?} in {}", path.display())
+                            }
+                        }
+                    }
+                    bases.insert(id, checked);
+                }
+            }
+            Err(e) if e.kind() == ErrorKind::NotFound => {}
+            Err(e) => error!("Failed to read {}: {e}", path.display()),
+        }
+        CheckedBases { path, bases }
+    }
+
+    /// Removes the bases already checked for `id` from `bases_left`.
+    pub fn remaining(&self, id: EntryId, bases_left: U256) -> U256 {
+        match self.bases.get(&id) {
+            Some(checked) => bases_left & !*checked,
+            None => bases_left,
+        }
+    }
+
+    pub fn record(&mut self, id: EntryId, base: u8) {
+        *self.bases.entry(id).or_default() |= U256::one() << base;
+        self.save();
+    }
+
+    /// Forgets `id` once it's no longer a PRP or all its bases have been checked.
+    pub fn finish(&mut self, id: EntryId) {
+        if self.bases.remove(&id).is_some() {
+            self.save();
+        }
+    }
+
+    fn save(&self) {
+        let mut text = String::new();
+        for (id, checked) in &self.bases {
+            text.push_str(&id.to_string());
+            for base in (0..=(u8::MAX as usize)).filter(|i| checked.bit(*i)) {
+                text.push(',');
+                text.push_str(&base.to_string());
+            }
+            text.push('\n');
+        }
+        // Write to a temporary file first, so that a crash mid-write can't lose the old contents
+        let temp_path = self.path.with_extension("tmp");
+        if let Err(e) = fs::write(&temp_path, text).and_then(|_| fs::rename(&temp_path, &self.path))
+        {
+            error!(
+                "Failed to save checked PRP bases to {}: {e}",
+                self.path.display()
+            );
+        }
+    }
+}
+
+/// Extracts the list of bases FactorDB has checked from a PRP's `frame_prime.php` page. The list
+/// may be split across lines or table cells, and separated by commas, spaces or "and".
+///
+/// Returns an empty list if the page has no "Bases checked" section, and `None` if it has one but
+/// no bases could be read from it, which means the page format has changed.
+pub fn parse_checked_bases(page: &str) -> Option<Vec<u8>> {
+    let Some((_, section)) = page.split_once("Bases checked") else {
+        return Some(vec![]);
+    };
+    let mut text = String::with_capacity(section.len());
+    let mut in_tag = false;
+    for c in section.chars() {
+        match c {
+            '<' => in_tag = true,
+            '>' if in_tag => {
+                in_tag = false;
+                text.push(' ');
+            }
+            _ if !in_tag => text.push(c),
+            _ => {}
+        }
+    }
+    let text = text.replace("&nbsp;", " ");
+    let mut bases = Vec::new();
+    for token in text
+        .split(|c: char| c == ',' || c == ':' || c.is_whitespace())
+        .filter(|token| !token.is_empty())
+    {
+        match token.parse::<u16>() {
+            Ok(base) => match u8::try_from(base) {
+                Ok(base) => bases.push(base),
+                Err(_) => warn!("Ignoring PRP-check base {base}, which is too large"),
+            },
+            Err(_) if token.eq_ignore_ascii_case("and") => {}
+            Err(_) if bases.is_empty() && token.eq_ignore_ascii_case("none") => {
+                return Some(vec![]);
+            }
+            // Anything else ends the list
+            Err(_) => break,
+        }
+    }
+    if bases.is_empty() { None } else { Some(bases) }
+}
+
+/// What FactorDB's page for a PRP check with one base (`index.php?id=...&basetocheck=...`) says
+/// about the number.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum PrpCheckOutcome {
+    /// Still a PRP, so the remaining bases are worth checking.
+    StillPrp,
+    /// The check showed it's composite.
+    RuledOutComposite,
+    /// It has a primality certificate, which has been verified or is being processed.
+    HasCertificate,
+    /// It's no longer a PRP for some other reason, usually an N-1 or N+1 primality proof or a
+    /// factor that someone found.
+    SolvedByNpm1,
+    /// Not a result page at all, e.g. an error page from the proxy.
+    Unparseable,
+}
+
+impl PrpCheckOutcome {
+    pub fn parse(page: &str) -> Self {
+        if !page.contains(">number<") {
+            PrpCheckOutcome::Unparseable
+        } else if CERTIFICATE_REGEX.is_match(page) {
+            PrpCheckOutcome::HasCertificate
+        } else if page.contains("set to C") {
+            PrpCheckOutcome::RuledOutComposite
+        } else if !page.contains("PRP") {
+            PrpCheckOutcome::SolvedByNpm1
+        } else {
+            PrpCheckOutcome::StillPrp
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::bases::{CheckedBases, PrpCheckOutcome, parse_checked_bases};
+    use primitive_types::U256;
+    use rand::{Rng, rng};
+    use std::env::temp_dir;
+
+    #[test]
+    fn test_resume_skips_checked_bases() {
+        let path = temp_dir().join(rng().next_u64().to_string());
+        let all_bases = U256::MAX - 3;
+        let mut checked_bases = CheckedBases::load(&path);
+        assert_eq!(checked_bases.remaining(1, all_bases), all_bases);
+        checked_bases.record(1, 2);
+        checked_bases.record(1, 3);
+        checked_bases.record(2, 255);
+        drop(checked_bases);
+
+        // Simulates a restart
+        let mut checked_bases = CheckedBases::load(&path);
+        let remaining = checked_bases.remaining(1, all_bases);
+        assert!(!remaining.bit(2));
+        assert!(!remaining.bit(3));
+        assert!(remaining.bit(4));
+        assert_eq!(remaining, all_bases - 12);
+        assert!(!checked_bases.remaining(2, all_bases).bit(255));
+
+        checked_bases.finish(1);
+        let checked_bases = CheckedBases::load(&path);
+        assert_eq!(checked_bases.remaining(1, all_bases), all_bases);
+        assert!(!checked_bases.remaining(2, all_bases).bit(255));
+    }
+
+    #[test]
+    fn test_parse_checked_bases() {
+        assert_eq!(
+            parse_checked_bases("<tr><td>Bases checked</td></tr>\n<tr><td>2, 3, 5</td></tr>"),
+            Some(vec![2, 3, 5])
+        );
+        // Wrapped across lines
+        assert_eq!(
+            parse_checked_bases("Bases checked<br>\n2, 3, 5, 7,\n11, 13<br>\nProcessing"),
+            Some(vec![2, 3, 5, 7, 11, 13])
+        );
+        assert_eq!(
+            parse_checked_bases("<td>Bases checked:&nbsp;2, 3 and 255</td><td>Certificate</td>"),
+            Some(vec![2, 3, 255])
+        );
+        assert_eq!(
+            parse_checked_bases("<td>Bases checked</td><td>none</td>"),
+            Some(vec![])
+        );
+        assert_eq!(parse_checked_bases("<td>PRP</td>"), Some(vec![]));
+        // The section is there, but nothing in it could be read
+        assert_eq!(
+            parse_checked_bases("<td>Bases checked</td><td>see below</td>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_parse_prp_check_outcome() {
+        const HEADER: &str = "<table><tr><td>Status <a href=\"help.php?page=0\">(?)</a></td>\
+            <td>digits</td><td>number</td></tr>";
+        let page =
+            |status: &str, footer: &str| format!("{HEADER}<tr><td>{status}</td></tr>{footer}");
+        assert_eq!(
+            PrpCheckOutcome::parse(&page(
+                "PRP",
+                "<tr><td>PRP check with base 3: passed</td></tr>"
+            )),
+            PrpCheckOutcome::StillPrp
+        );
+        assert_eq!(
+            PrpCheckOutcome::parse(&page(
+                "C",
+                "<tr><td>PRP check with base 5 failed, number set to C</td></tr>"
+            )),
+            PrpCheckOutcome::RuledOutComposite
+        );
+        assert_eq!(
+            PrpCheckOutcome::parse(&page(
+                "PRP",
+                "<tr><td>Primality certificate</td><td>Processing</td></tr>"
+            )),
+            PrpCheckOutcome::HasCertificate
+        );
+        assert_eq!(
+            PrpCheckOutcome::parse(&page("P", "<tr><td>Certificate</td><td>Verified</td></tr>")),
+            PrpCheckOutcome::HasCertificate
+        );
+        assert_eq!(
+            PrpCheckOutcome::parse(&page("P", "<tr><td>Proven by N-1</td></tr>")),
+            PrpCheckOutcome::SolvedByNpm1
+        );
+        assert_eq!(
+            PrpCheckOutcome::parse("<h1>502 Proxy Error</h1>"),
+            PrpCheckOutcome::Unparseable
+        );
+    }
+}
@@ -1,17 +1,30 @@
 use async_backtrace::framed;
 use log::{info, warn};
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
 use tokio::select;
 use tokio::sync::mpsc::{OwnedPermit, Receiver, Sender, channel};
 
+/// An item that's failed this many times in a row is probably stuck (e.g. FactorDB keeps sending a
+/// response we can't parse), so the next failure drops it instead of requeueing it.
+pub const MAX_REQUEUES: usize = 32;
+
+/// Bound on how many distinct items have their failures counted; past this, the counts start over.
+const MAX_TRACKED_REQUEUES: usize = 1 << 14;
+
 pub struct PushbackReceiver<T: Debug> {
     receiver: Receiver<T>,
     sender: Sender<T>,
     return_sender: Sender<T>,
     return_receiver: Receiver<T>,
+    requeue_counts: HashMap<T, usize>,
+    /// The item last received, until it's requeued. If it's still here when the next item is
+    /// received, it was handled.
+    in_flight: Option<T>,
 }
 
-impl<T: Debug> PushbackReceiver<T> {
+impl<T: Debug + Clone + Eq + Hash> PushbackReceiver<T> {
     pub fn new(receiver: Receiver<T>, sender: &Sender<T>) -> Self {
         let (return_sender, return_receiver) = channel((sender.max_capacity() >> 2).max(2));
         PushbackReceiver {
@@ -19,6 +32,25 @@ impl<T: Debug> PushbackReceiver<T> {
             sender: sender.clone(),
             return_sender,
             return_receiver,
+            requeue_counts: HashMap::new(),
+            in_flight: None,
+        }
+    }
+
+    /// Counts a failure of `item`. Returns false, after warning once, if it's failed too many times
+    /// to keep.
+    fn count_requeue(requeue_counts: &mut HashMap<T, usize>, item: &T) -> bool {
+        if requeue_counts.len() >= MAX_TRACKED_REQUEUES {
+            requeue_counts.clear();
+        }
+        let count = requeue_counts.entry(item.clone()).or_insert(0);
+        *count += 1;
+        if *count > MAX_REQUEUES {
+            requeue_counts.remove(item);
+            warn!("Dropping {item:?}, which has failed {MAX_REQUEUES} times in a row already");
+            false
+        } else {
+            true
         }
     }
 
@@ -54,8 +86,46 @@ impl<T: Debug> PushbackReceiver<T> {
         }
     }
 
+    /// Queues a new item behind those already waiting, unless the channel is full, in which case
+    /// it returns false.
+    pub fn try_push(&self, item: T) -> bool {
+        self.sender.try_send(item).is_ok()
+    }
+
+    /// Returns `item` to the queue after a failure, such as a response that couldn't be parsed.
+    /// After [MAX_REQUEUES] failures in a row, it's dropped instead. Returns whether it was
+    /// requeued.
+    pub fn requeue_after_failure(&mut self, item: T, permit: OwnedPermit<T>) -> bool {
+        self.in_flight = None;
+        if Self::count_requeue(&mut self.requeue_counts, &item) {
+            permit.send(item);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Returns `item` to the queue because FactorDB is throttling us. That says nothing about the
+    /// item itself, so it doesn't count towards [MAX_REQUEUES].
+    pub fn requeue_after_throttling(&mut self, item: T, permit: OwnedPermit<T>) {
+        self.in_flight = None;
+        permit.send(item);
+    }
+
+    /// Receives the next item, which may be one that was returned earlier, and a permit to
+    /// return it with.
     #[framed]
     pub async fn recv(&mut self) -> (T, OwnedPermit<T>) {
+        // The last item wasn't requeued, so its earlier failures no longer count
+        if let Some(handled) = self.in_flight.take() {
+            self.requeue_counts.remove(&handled);
+        }
+        let (item, permit) = self.recv_any().await;
+        self.in_flight = Some(item.clone());
+        (item, permit)
+    }
+
+    async fn recv_any(&mut self) -> (T, OwnedPermit<T>) {
         self.redrive_returned();
         let return_sender = self.return_sender.clone();
         let return_permit = return_sender.try_reserve_owned();
@@ -98,3 +168,51 @@ impl<T: Debug> PushbackReceiver<T> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::channel::{MAX_REQUEUES, PushbackReceiver};
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_stuck_item_is_dropped() {
+        let (sender, receiver) = channel(8);
+        let mut receiver = PushbackReceiver::new(receiver, &sender);
+        sender.send(42u64).await.unwrap();
+        // Received once from the search, then once after each requeue
+        for _ in 0..MAX_REQUEUES {
+            let (item, return_permit) = receiver.recv().await;
+            assert_eq!(item, 42);
+            assert!(receiver.requeue_after_failure(item, return_permit));
+        }
+        let (item, return_permit) = receiver.recv().await;
+        assert!(!receiver.requeue_after_failure(item, return_permit));
+        sender.send(7).await.unwrap();
+        assert_eq!(receiver.recv().await.0, 7);
+        assert!(receiver.return_receiver.is_empty());
+        assert!(receiver.receiver.is_empty());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_only_failures_in_a_row_count() {
+        let (sender, receiver) = channel(8);
+        let mut receiver = PushbackReceiver::new(receiver, &sender);
+        sender.send(42u64).await.unwrap();
+        for _ in 0..MAX_REQUEUES {
+            let (item, return_permit) = receiver.recv().await;
+            assert!(receiver.requeue_after_failure(item, return_permit));
+        }
+        // Being throttled doesn't count
+        let (item, return_permit) = receiver.recv().await;
+        receiver.requeue_after_throttling(item, return_permit);
+        assert_eq!(receiver.requeue_counts[&42], MAX_REQUEUES);
+
+        // Handling it clears its failures once the next item is received
+        let (item, _) = receiver.recv().await;
+        assert_eq!(item, 42);
+        sender.send(42).await.unwrap();
+        let (item, return_permit) = receiver.recv().await;
+        assert!(!receiver.requeue_counts.contains_key(&42));
+        assert!(receiver.requeue_after_failure(item, return_permit));
+    }
+}
@@ -0,0 +1,343 @@
+use crate::digits::DigitRange;
+use crate::graph::EntryId;
+use crate::monitor::Monitor;
+use crate::net::FactorDbClient;
+use crate::net::FetchOutcome::{Failed, Fetched, Throttled};
+use crate::{C_RESULTS_PER_PAGE, NumberLength, SEARCH_RETRY_DELAY, THROTTLED_RETRY_DELAY};
+use async_backtrace::framed;
+use hipstr::HipStr;
+use log::{info, warn};
+use rand::rngs::StdRng;
+use rand::{RngExt, SeedableRng};
+use std::iter::once;
+use tokio::time::sleep;
+
+/// Position of a search that sweeps through FactorDB's result listings page by page, starting over
+/// from the beginning once it passes `max_start`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SearchCursor {
+    start: EntryId,
+    max_start: EntryId,
+}
+
+impl SearchCursor {
+    pub fn new(start: EntryId, max_start: EntryId) -> Self {
+        SearchCursor {
+            start: if start > max_start { 0 } else { start },
+            max_start,
+        }
+    }
+
+    pub fn start(&self) -> EntryId {
+        self.start
+    }
+
+    /// Moves past a page of `results` results.
+    pub fn advance(&mut self, results: usize) {
+        self.start = self.start.saturating_add(results as EntryId);
+        if self.start > self.max_start {
+            self.start = 0;
+        }
+    }
+}
+
+/// 2^64 divided by the golden ratio, which spreads consecutive multiples as evenly as possible.
+const GOLDEN_RATIO_64: u64 = 0x9E37_79B9_7F4A_7C15;
+
+/// Starting point in `0..=max_start` for parallel run `run_id`, so that parallel runs search
+/// different parts of the listing. Run IDs are spread by the golden ratio, so that consecutive ones
+/// land far apart, and then offset by up to `max_jitter` using an RNG seeded from the run ID, so
+/// that a restarted run starts where it did before.
+pub fn jittered_start(run_id: EntryId, max_start: EntryId, max_jitter: EntryId) -> EntryId {
+    let span = max_start.saturating_add(1);
+    let fraction = (run_id as u64).wrapping_mul(GOLDEN_RATIO_64);
+    let spread = (EntryId::from(fraction).saturating_mul(span)) >> 64;
+    let jitter = StdRng::seed_from_u64(run_id as u64).random_range(0..=max_jitter);
+    spread.saturating_add(jitter) % span
+}
+
+/// A page of search results, and the digit size and page size it was fetched with.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct SearchPage {
+    pub page: HipStr<'static>,
+    pub digits: NumberLength,
+    pub results_per_page: usize,
+}
+
+/// Digit sizes for a search to try, in order: `digits` itself, then one more and one fewer as long
+/// as they're within `range`.
+pub fn fallback_digit_sizes(
+    digits: NumberLength,
+    range: DigitRange,
+) -> impl Iterator<Item = NumberLength> {
+    let neighbors = [digits.checked_add(1), digits.checked_sub(1)]
+        .into_iter()
+        .flatten()
+        .filter(move |neighbor| (range.min..=range.max).contains(neighbor));
+    once(digits).chain(neighbors)
+}
+
+/// Fetches a page of up to `results_per_page` C's starting at `start`. A failed fetch is retried
+/// with half as many results per page. If there are no C's with `digits` digits there (according to
+/// `has_results`), or the listing can't be fetched at all, the adjacent digit sizes are tried
+/// before giving up, so that one empty slice of the listing doesn't waste a whole search cycle.
+/// Returns `None` if they're all empty or shutdown has begun.
+#[framed]
+pub async fn fetch_composites_page(
+    http: &impl FactorDbClient,
+    shutdown: &mut Monitor,
+    digits: NumberLength,
+    range: DigitRange,
+    start: EntryId,
+    results_per_page: usize,
+    has_results: impl Fn(&str) -> bool,
+) -> Option<SearchPage> {
+    let mut empty_digits = None;
+    for digits in fallback_digit_sizes(digits, range) {
+        if let Some(empty_digits) = empty_digits {
+            warn!("No {empty_digits}-digit C's from {start}; falling back to {digits} digits");
+        }
+        let mut results_per_page = results_per_page;
+        while results_per_page > 0 {
+            if shutdown.check_for_shutdown() {
+                return None;
+            }
+            info!("Retrieving {digits}-digit C's starting from {start}");
+            let url = format!(
+                "https://factordb.com/listtype.php?t=3&perpage={results_per_page}&start={start}&mindig={digits}"
+            );
+            match http.try_fetch(&url).await {
+                Fetched(page) => {
+                    if has_results(&page) {
+                        return Some(SearchPage {
+                            page,
+                            digits,
+                            results_per_page,
+                        });
+                    }
+                    break;
+                }
+                Throttled(retry_after) => {
+                    sleep(retry_after.unwrap_or(THROTTLED_RETRY_DELAY)).await;
+                }
+                Failed => {
+                    results_per_page >>= 1;
+                    sleep(SEARCH_RETRY_DELAY).await;
+                }
+            }
+        }
+        empty_digits = Some(digits);
+    }
+    warn!("No C's found from {start} with any digit size tried");
+    None
+}
+
+/// Assembles a batch of up to [C_RESULTS_PER_PAGE] C's from consecutive pages of `page_size`
+/// results, which is gentler on FactorDB than one giant request and less likely to time out. Once
+/// the first page settles the digit size, later pages stick to it, and the batch ends early at the
+/// first page with no results. Returns no pages if even the first is empty or shutdown has begun.
+#[framed]
+pub async fn fetch_composites_batch(
+    http: &impl FactorDbClient,
+    shutdown: &mut Monitor,
+    digits: NumberLength,
+    range: DigitRange,
+    start: EntryId,
+    page_size: usize,
+    has_results: impl Fn(&str) -> bool,
+) -> Vec<SearchPage> {
+    let page_size = page_size.clamp(1, C_RESULTS_PER_PAGE);
+    let mut pages: Vec<SearchPage> = Vec::new();
+    let mut fetched = 0;
+    while fetched < C_RESULTS_PER_PAGE {
+        let (digits, range) = match pages.first() {
+            Some(first) => (
+                first.digits,
+                DigitRange {
+                    min: first.digits,
+                    max: first.digits,
+                },
+            ),
+            None => (digits, range),
+        };
+        let Some(page) = fetch_composites_page(
+            http,
+            shutdown,
+            digits,
+            range,
+            start.saturating_add(fetched as EntryId),
+            page_size.min(C_RESULTS_PER_PAGE - fetched),
+            &has_results,
+        )
+        .await
+        else {
+            break;
+        };
+        fetched += page.results_per_page;
+        pages.push(page);
+    }
+    pages
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::cursor::{
+        SearchCursor, SearchPage, fallback_digit_sizes, fetch_composites_batch,
+        fetch_composites_page, jittered_start,
+    };
+    use crate::digits::DigitRange;
+    use crate::monitor::Monitor;
+    use crate::net::FetchOutcome::Fetched;
+    use crate::net::MockFactorDbClient;
+
+    #[test]
+    fn test_search_cursor() {
+        let mut cursor = SearchCursor::new(0, 10_000);
+        assert_eq!(cursor.start(), 0);
+        cursor.advance(5000);
+        assert_eq!(cursor.start(), 5000);
+        cursor.advance(2500);
+        assert_eq!(cursor.start(), 7500);
+        cursor.advance(2500);
+        assert_eq!(cursor.start(), 10_000);
+        cursor.advance(5000);
+        assert_eq!(cursor.start(), 0);
+
+        assert_eq!(SearchCursor::new(20_000, 10_000).start(), 0);
+    }
+
+    #[test]
+    fn test_jittered_start() {
+        const MAX_START: u128 = 100_000;
+        const MAX_JITTER: u128 = 1000;
+        let starts: Vec<_> = (1..=8)
+            .map(|run_id| jittered_start(run_id, MAX_START, MAX_JITTER))
+            .collect();
+        assert!(starts.iter().all(|&start| start <= MAX_START));
+        for (i, a) in starts.iter().enumerate() {
+            for b in &starts[i + 1..] {
+                assert!(a.abs_diff(*b) > MAX_START / 16, "{starts:?}");
+            }
+        }
+        // The same run starts at the same place every time
+        assert_eq!(jittered_start(3, MAX_START, MAX_JITTER), starts[2]);
+        // Without jitter, only the spread remains
+        assert_eq!(jittered_start(1, MAX_START, 0), 61_804);
+        assert_eq!(jittered_start(2, MAX_START, 0), 23_607);
+    }
+
+    #[test]
+    fn test_fallback_digit_sizes() {
+        let range = DigitRange { min: 92, max: 300 };
+        assert_eq!(
+            fallback_digit_sizes(100, range).collect::<Vec<_>>(),
+            [100, 101, 99]
+        );
+        assert_eq!(
+            fallback_digit_sizes(92, range).collect::<Vec<_>>(),
+            [92, 93]
+        );
+        assert_eq!(
+            fallback_digit_sizes(300, range).collect::<Vec<_>>(),
+            [300, 299]
+        );
+    }
+
+    /// Mocks a C listing in which only the given digit sizes have any results.
+    fn mock_listing(nonempty_digits: &'static [u32]) -> MockFactorDbClient {
+        let mut http = MockFactorDbClient::new();
+        http.expect_try_fetch().returning(move |url| {
+            let digits = url.rsplit_once("mindig=").unwrap().1.parse().unwrap();
+            Fetched(if nonempty_digits.contains(&digits) {
+                format!("results for {digits} digits").into()
+            } else {
+                "".into()
+            })
+        });
+        http
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_empty_page_falls_back_to_adjacent_digits() {
+        let range = DigitRange { min: 92, max: 300 };
+        let (_shutdown_sender, mut shutdown) = Monitor::new();
+        let has_results = |page: &str| !page.is_empty();
+        let fetch = async |nonempty_digits: &'static [u32]| {
+            fetch_composites_page(
+                &mock_listing(nonempty_digits),
+                &mut shutdown.clone(),
+                100,
+                range,
+                0,
+                crate::C_RESULTS_PER_PAGE,
+                has_results,
+            )
+            .await
+            .map(|SearchPage { digits, .. }| digits)
+        };
+        assert_eq!(fetch(&[100, 101]).await, Some(100));
+        assert_eq!(fetch(&[101]).await, Some(101));
+        assert_eq!(fetch(&[99]).await, Some(99));
+        assert_eq!(fetch(&[102]).await, None);
+
+        let page = fetch_composites_page(
+            &mock_listing(&[101]),
+            &mut shutdown,
+            100,
+            range,
+            0,
+            crate::C_RESULTS_PER_PAGE,
+            has_results,
+        )
+        .await
+        .unwrap();
+        assert_eq!(page.page.as_str(), "results for 101 digits");
+        assert_eq!(page.results_per_page, crate::C_RESULTS_PER_PAGE);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_batch_concatenates_pages() {
+        let range = DigitRange { min: 92, max: 300 };
+        let (_shutdown_sender, mut shutdown) = Monitor::new();
+        let page_size = crate::C_RESULTS_PER_PAGE / 2;
+        let mut http = MockFactorDbClient::new();
+        http.expect_try_fetch().times(2).returning(move |url| {
+            assert!(url.contains(&format!("perpage={page_size}&")));
+            let start = url
+                .split_once("start=")
+                .unwrap()
+                .1
+                .split_once('&')
+                .unwrap()
+                .0;
+            Fetched(format!("page from {start}").into())
+        });
+        let pages = fetch_composites_batch(
+            &http,
+            &mut shutdown,
+            100,
+            range,
+            1000,
+            page_size,
+            |page: &str| !page.is_empty(),
+        )
+        .await;
+        assert_eq!(
+            pages
+                .iter()
+                .map(|page| page.page.as_str())
+                .collect::<Vec<_>>(),
+            [
+                "page from 1000".to_string(),
+                format!("page from {}", 1000 + page_size)
+            ]
+        );
+        assert_eq!(
+            pages
+                .iter()
+                .map(|page| page.results_per_page)
+                .sum::<usize>(),
+            crate::C_RESULTS_PER_PAGE
+        );
+    }
+}
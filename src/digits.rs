@@ -0,0 +1,280 @@
+use crate::algebraic::{Factor, estimate_log10};
+use crate::{
+    C_MAX_DIGITS, C_MIN_DIGITS, NumberLength, PRP_MAX_DIGITS, PRP_MIN_DIGITS, U_MAX_DIGITS,
+    U_MIN_DIGITS,
+};
+use anyhow::{anyhow, bail};
+use log::{error, info};
+use rand::RngExt;
+use std::ops::RangeInclusive;
+
+/// Inclusive range of digit counts that one kind of search covers.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DigitRange {
+    pub min: NumberLength,
+    pub max: NumberLength,
+}
+
+impl DigitRange {
+    /// Applies `{prefix}_MIN_DIGITS` and `{prefix}_MAX_DIGITS` overrides (given as `min` and
+    /// `max`) to the defaults, and checks that the result is a sensible range.
+    fn with_overrides(
+        prefix: &str,
+        min: Option<String>,
+        max: Option<String>,
+        default_min: NumberLength,
+        default_max: NumberLength,
+    ) -> anyhow::Result<Self> {
+        let parse = |value: Option<String>, bound: &str, default: NumberLength| match value {
+            None => Ok(default),
+            Some(value) => value
+                .trim()
+                .parse::<NumberLength>()
+                .map_err(|e| anyhow!("Invalid {prefix}_{bound}_DIGITS {value:?}: {e}")),
+        };
+        let min = parse(min, "MIN", default_min)?;
+        let max = parse(max, "MAX", default_max)?;
+        if min == 0 {
+            bail!("{prefix}_MIN_DIGITS must be at least 1");
+        }
+        if min > max {
+            bail!("{prefix}_MIN_DIGITS ({min}) is greater than {prefix}_MAX_DIGITS ({max})");
+        }
+        Ok(DigitRange { min, max })
+    }
+
+    fn from_env(
+        prefix: &str,
+        default_min: NumberLength,
+        default_max: NumberLength,
+    ) -> anyhow::Result<Self> {
+        Self::with_overrides(
+            prefix,
+            std::env::var(format!("{prefix}_MIN_DIGITS")).ok(),
+            std::env::var(format!("{prefix}_MAX_DIGITS")).ok(),
+            default_min,
+            default_max,
+        )
+    }
+
+    /// Number of digit counts in the range.
+    pub fn span(&self) -> NumberLength {
+        self.max - self.min + 1
+    }
+}
+
+/// Digit ranges for the PRP, C and U searches, which the `PRP_MIN_DIGITS`, `PRP_MAX_DIGITS`,
+/// `C_MIN_DIGITS`, `C_MAX_DIGITS`, `U_MIN_DIGITS` and `U_MAX_DIGITS` environment variables can
+/// override.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DigitRanges {
+    pub prp: DigitRange,
+    pub c: DigitRange,
+    pub u: DigitRange,
+}
+
+impl DigitRanges {
+    pub fn from_env() -> anyhow::Result<Self> {
+        Ok(DigitRanges {
+            prp: DigitRange::from_env("PRP", PRP_MIN_DIGITS, PRP_MAX_DIGITS)?,
+            c: DigitRange::from_env("C", C_MIN_DIGITS, C_MAX_DIGITS)?,
+            u: DigitRange::from_env("U", U_MIN_DIGITS, U_MAX_DIGITS)?,
+        })
+    }
+}
+
+/// Whether `u` certainly has more than `max_digits` digits, judging only by [estimate_log10]. Used
+/// to keep Us that FactorDB would refuse to PRP-check out of the queue, instead of finding out from
+/// the response.
+pub fn too_large_for_prp_check(u: &Factor, max_digits: NumberLength) -> bool {
+    let (log10_lower, _) = estimate_log10(u);
+    log10_lower >= max_digits
+}
+
+/// Relative weights for choosing how many digits the numbers in a search should have. FactorDB has
+/// far more unfactored numbers in some digit ranges than in others, so searches can be biased
+/// toward the "hot" ranges instead of sampling every digit count uniformly.
+#[derive(Clone, Debug)]
+pub struct DigitWeights {
+    segments: Box<[RangeInclusive<NumberLength>]>,
+    /// Running total of (weight per digit count) * (digit counts in segment), ending at each
+    /// segment.
+    cumulative_weights: Box<[u64]>,
+}
+
+impl DigitWeights {
+    pub fn uniform(min_digits: NumberLength, max_digits: NumberLength) -> Self {
+        DigitWeights {
+            segments: [min_digits..=max_digits].into(),
+            cumulative_weights: [u64::from(max_digits - min_digits) + 1].into(),
+        }
+    }
+
+    /// Parses a comma-separated list of `min-max:weight` entries. Digit counts between
+    /// `min_digits` and `max_digits` that no entry covers get weight 1, and entries are clamped to
+    /// that range. Returns `None` if the list is malformed or has overlapping entries.
+    pub fn parse(spec: &str, min_digits: NumberLength, max_digits: NumberLength) -> Option<Self> {
+        let mut hot_ranges = Vec::new();
+        for entry in spec
+            .split(',')
+            .map(str::trim)
+            .filter(|entry| !entry.is_empty())
+        {
+            let (range, weight) = entry.split_once(':')?;
+            let (start, end) = range.split_once('-')?;
+            let start = start.trim().parse::<NumberLength>().ok()?.max(min_digits);
+            let end = end.trim().parse::<NumberLength>().ok()?.min(max_digits);
+            let weight = weight.trim().parse::<u64>().ok()?;
+            if start <= end {
+                hot_ranges.push((start, end, weight));
+            }
+        }
+        hot_ranges.sort_unstable();
+        let mut segments = Vec::with_capacity(hot_ranges.len() * 2 + 1);
+        let mut cumulative_weights = Vec::with_capacity(hot_ranges.len() * 2 + 1);
+        let mut total = 0u64;
+        let mut next_uncovered = min_digits;
+        let mut push_segment = |start: NumberLength, end: NumberLength, weight: u64| {
+            total = total.checked_add((u64::from(end - start) + 1).checked_mul(weight)?)?;
+            segments.push(start..=end);
+            cumulative_weights.push(total);
+            Some(())
+        };
+        for (start, end, weight) in hot_ranges {
+            if start < next_uncovered {
+                return None;
+            }
+            if start > next_uncovered {
+                push_segment(next_uncovered, start - 1, 1)?;
+            }
+            push_segment(start, end, weight)?;
+            next_uncovered = end + 1;
+        }
+        if next_uncovered <= max_digits {
+            push_segment(next_uncovered, max_digits, 1)?;
+        }
+        if total == 0 {
+            return None;
+        }
+        Some(DigitWeights {
+            segments: segments.into(),
+            cumulative_weights: cumulative_weights.into(),
+        })
+    }
+
+    /// Reads weights from the given environment variable, falling back to uniform selection if
+    /// it's unset or malformed.
+    pub fn from_env(var: &str, min_digits: NumberLength, max_digits: NumberLength) -> Self {
+        let Ok(spec) = std::env::var(var) else {
+            return Self::uniform(min_digits, max_digits);
+        };
+        match Self::parse(&spec, min_digits, max_digits) {
+            Some(weights) => {
+                info!("Using digit weights from {var}: {spec}");
+                weights
+            }
+            None => {
+                error!("Invalid {var} {spec:?}; using uniform digit selection");
+                Self::uniform(min_digits, max_digits)
+            }
+        }
+    }
+
+    pub fn sample(&self, rng: &mut impl RngExt) -> NumberLength {
+        let total = *self.cumulative_weights.last().unwrap();
+        let point = rng.random_range(0..total);
+        let index = self
+            .cumulative_weights
+            .partition_point(|&cumulative| cumulative <= point);
+        let segment = &self.segments[index];
+        rng.random_range(segment.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::U_MAX_DIGITS;
+    use crate::algebraic::Factor;
+    use crate::digits::{DigitRange, DigitWeights, too_large_for_prp_check};
+    use rand::rng;
+
+    #[test]
+    fn test_weighted_sampling_skew() {
+        const SAMPLES: usize = 100_000;
+        let weights = DigitWeights::parse("92-100:99", 92, 300).unwrap();
+        let mut rng = rng();
+        let mut hot = 0;
+        for _ in 0..SAMPLES {
+            let digits = weights.sample(&mut rng);
+            assert!((92..=300).contains(&digits));
+            if digits <= 100 {
+                hot += 1;
+            }
+        }
+        // 9 digit counts at weight 99 vs. 200 at weight 1
+        let expected = 891.0 / 1091.0;
+        let actual = hot as f64 / SAMPLES as f64;
+        assert!((actual - expected).abs() < 0.01, "{actual} vs {expected}");
+
+        let uniform = DigitWeights::uniform(92, 300);
+        let hot = (0..SAMPLES)
+            .filter(|_| uniform.sample(&mut rng) <= 100)
+            .count();
+        let actual = hot as f64 / SAMPLES as f64;
+        assert!((actual - 9.0 / 209.0).abs() < 0.01, "{actual}");
+    }
+
+    #[test]
+    fn test_parse_digit_weights() {
+        assert!(DigitWeights::parse("", 92, 300).is_some());
+        assert!(DigitWeights::parse("100-200:3,250-400:2", 92, 300).is_some());
+        assert!(DigitWeights::parse("100-200:3,150-250:2", 92, 300).is_none());
+        assert!(DigitWeights::parse("100-200", 92, 300).is_none());
+        assert!(DigitWeights::parse("92-300:0", 92, 300).is_none());
+    }
+
+    #[test]
+    fn test_digit_range_overrides() {
+        assert_eq!(
+            DigitRange::with_overrides("C", None, None, 92, 300).unwrap(),
+            DigitRange { min: 92, max: 300 }
+        );
+        assert_eq!(
+            DigitRange::with_overrides("C", Some("100".into()), None, 92, 300).unwrap(),
+            DigitRange { min: 100, max: 300 }
+        );
+        let error =
+            DigitRange::with_overrides("C", Some("250".into()), Some("200".into()), 92, 300)
+                .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "C_MIN_DIGITS (250) is greater than C_MAX_DIGITS (200)"
+        );
+        // Overriding only one bound can also put them out of order
+        assert!(DigitRange::with_overrides("U", None, Some("1000".into()), 2001, 199_999).is_err());
+        assert!(DigitRange::with_overrides("PRP", Some("0".into()), None, 300, 80_000).is_err());
+        assert!(DigitRange::with_overrides("PRP", Some("many".into()), None, 300, 80_000).is_err());
+    }
+
+    #[test]
+    fn test_too_large_for_prp_check() {
+        assert!(too_large_for_prp_check(
+            &Factor::from("10^200001+7"),
+            U_MAX_DIGITS
+        ));
+        assert!(!too_large_for_prp_check(
+            &Factor::from("10^199990+7"),
+            U_MAX_DIGITS
+        ));
+        assert!(!too_large_for_prp_check(
+            &Factor::from("2^127-1"),
+            U_MAX_DIGITS
+        ));
+        // Can't tell how big these are, so they're given the benefit of the doubt
+        assert!(!too_large_for_prp_check(
+            &Factor::from("1234567890...1234567890"),
+            U_MAX_DIGITS
+        ));
+        assert!(too_large_for_prp_check(&Factor::from("10^2000"), 1000));
+    }
+}
@@ -4,9 +4,12 @@ use crate::ReportFactorResult::{Accepted, AlreadyFullyFactored, DoesNotDivide, O
 use crate::algebraic::ComplexFactor::Multiply;
 use crate::algebraic::Factor::Numeric;
 use crate::algebraic::div_exact;
-use crate::algebraic::{ComplexFactor, simplify_divide};
 use crate::algebraic::{
-    Factor, NumericFactor, estimate_log10, evaluate_as_numeric, find_unique_factors,
+    ComplexFactor, divides, is_prime, multiplicity, power_multiset, simplify, simplify_divide,
+};
+use crate::algebraic::{
+    Factor, NumericFactor, digit_count_from_log10_bounds, estimate_log10, evaluate_as_numeric,
+    find_unique_factors,
 };
 use crate::graph::Divisibility::{Direct, NotFactor, Transitive};
 use crate::graph::FactorsKnownToFactorDb::{NotUpToDate, UpToDate};
@@ -17,30 +20,44 @@ use crate::net::{
     FactorDbClient, FactorDbClientReadIdsAndExprs, NumberStatus, NumberStatusExt,
     ProcessedStatusApiResponse,
 };
-use crate::{FAILED_U_SUBMISSIONS_OUT, NumberLength, NumberSpecifier, SUBMIT_FACTOR_MAX_ATTEMPTS};
+use crate::pool::{JobOutcome, factoring_pool};
+use crate::shuffle::shuffle_rng;
+use crate::snapshot::{GRAPH_SNAPSHOT_DIR, GraphSnapshot, VertexSnapshot, root_hash};
+use crate::{
+    BignumElision, COMPRESS_GRAPH_SNAPSHOTS, CONTRIBUTE_ONLY, FACTOR_FINDER_TIMEOUT_SECS,
+    FAILED_U_SUBMISSIONS_OUT, GRAPH_SNAPSHOT_INTERVAL, MAX_DECOMPOSITION_DEPTH, MAX_GRAPH_VERTICES,
+    MIN_SUBMITTED_FACTOR_DIGITS, NumberLength, NumberSpecifier, ReportFactorResult,
+    SUBMIT_FACTOR_MAX_ATTEMPTS, VERIFY_FACTORS, with_bignum_elision,
+};
 use alloc::borrow::Cow::Borrowed;
 use alloc::vec::IntoIter;
 use async_backtrace::framed;
 use itertools::Itertools;
-use log::{debug, error, info, warn};
+use log::{Level, debug, error, info, log_enabled, warn};
 use petgraph::Direction::{Incoming, Outgoing};
 use petgraph::algo::spfa;
 use petgraph::prelude::EdgeRef;
 use petgraph::stable_graph::{NodeIndex, StableGraph};
 use petgraph::visit::IntoEdgeReferences;
 use petgraph::{Directed, Direction};
-use rand::rng;
 use rand::seq::SliceRandom;
 use replace_with::replace_with_or_abort;
+use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
 use std::collections::{BTreeMap, BTreeSet, VecDeque};
+use std::fmt::{self, Display, Formatter};
 use std::io::Write;
+use std::iter::once;
 use std::mem::replace;
+use std::path::Path;
 use std::sync::OnceLock;
+use std::sync::atomic::Ordering::Acquire;
+use std::time::{Duration, Instant};
+use tokio::task;
 
 pub type EntryId = u128;
 
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Ord, PartialOrd, Hash, Serialize, Deserialize)]
 pub enum Divisibility {
     NotFactor,
     Transitive,
@@ -55,13 +72,55 @@ pub type DivisibilityGraph = StableGraph<Factor, Divisibility, Directed>;
 pub struct FactorData {
     pub divisibility_graph: DivisibilityGraph,
     pub deleted_synonyms: BTreeMap<VertexId, VertexId>,
+    /// Number of original vertices merged into each surviving vertex, when more than 1.
+    pub merged_vertex_counts: BTreeMap<VertexId, usize>,
     pub number_facts_map: BTreeMap<VertexId, NumberFacts>,
     pub vertex_id_by_expr: BTreeMap<Factor, VertexId>,
     pub vertex_id_by_entry_id: BTreeMap<EntryId, VertexId>,
+    /// How many rounds of decomposition separate each vertex from the root, when more than 0.
+    pub decomposition_depths: BTreeMap<VertexId, usize>,
+    /// Wall-clock time spent waiting for the factor finder while building this graph.
+    pub factoring_time: Duration,
+    pub propagation_stats: PropagationStats,
+}
+
+/// How much work divisibility propagation has done on a graph, to show when dense graphs make it
+/// the bottleneck.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct PropagationStats {
+    pub items_processed: usize,
+    pub max_worklist_len: usize,
+    pub edges_added: usize,
+    /// Transitive edges that became direct.
+    pub edges_upgraded: usize,
+}
+
+impl Display for PropagationStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} propagation items (worklist peaked at {}), {} edges added, {} upgraded",
+            self.items_processed, self.max_worklist_len, self.edges_added, self.edges_upgraded
+        )
+    }
 }
 
 const DEFAULT_NODE_CAPACITY: usize = 256;
 
+/// [FactorData::all_divisors] gives up rather than list more divisors than this.
+const MAX_DIVISORS: usize = 1 << 16;
+
+/// The number of divisors of the product of `primes` raised to their exponents, saturating at
+/// [usize::MAX].
+fn count_divisors(primes: &[(Factor, NumberLength)]) -> usize {
+    primes
+        .iter()
+        .try_fold(1usize, |count, (_, exponent)| {
+            count.checked_mul(*exponent as usize + 1)
+        })
+        .unwrap_or(usize::MAX)
+}
+
 impl Default for FactorData {
     fn default() -> Self {
         FactorData {
@@ -70,9 +129,13 @@ impl Default for FactorData {
                 DEFAULT_NODE_CAPACITY * (DEFAULT_NODE_CAPACITY - 1),
             ),
             deleted_synonyms: BTreeMap::new(),
+            merged_vertex_counts: BTreeMap::new(),
             number_facts_map: BTreeMap::new(),
             vertex_id_by_entry_id: BTreeMap::new(),
             vertex_id_by_expr: BTreeMap::new(),
+            decomposition_depths: BTreeMap::new(),
+            factoring_time: Duration::ZERO,
+            propagation_stats: PropagationStats::default(),
         }
     }
 }
@@ -101,19 +164,44 @@ impl FactorData {
     ) -> VertexId {
         let mut synonyms_to_forward = Vec::new();
         while let Some(synonym) = deleted_synonyms.get(&vertex_id) {
-            synonyms_to_forward.push(*synonym);
+            synonyms_to_forward.push(vertex_id);
             vertex_id = *synonym;
         }
-        if let Some(last) = synonyms_to_forward.last() {
-            vertex_id = *last;
-            // Optimization: path compression
-            for synonym in synonyms_to_forward.into_iter().rev().skip(1) {
-                deleted_synonyms.insert(synonym, vertex_id);
-            }
+        // Optimization: path compression
+        // The last synonym already points directly to vertex_id
+        synonyms_to_forward.pop();
+        for synonym in synonyms_to_forward {
+            deleted_synonyms.insert(synonym, vertex_id);
         }
         vertex_id
     }
 
+    pub fn decomposition_depth(&mut self, vertex_id: VertexId) -> usize {
+        let vertex_id = self.resolve_vid(vertex_id);
+        self.decomposition_depths
+            .get(&vertex_id)
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Records that the number at `vertex_id` was found `depth` rounds of decomposition below the
+    /// root, unless it was already found closer to the root. A number with no recorded depth
+    /// takes `depth`, so one that's been decomposed at the depth [FactorData::decomposition_depth]
+    /// reports for it should have that depth recorded first.
+    fn record_decomposition_depth(&mut self, vertex_id: VertexId, depth: usize) {
+        let vertex_id = self.resolve_vid(vertex_id);
+        let recorded = self.decomposition_depths.entry(vertex_id).or_insert(depth);
+        *recorded = (*recorded).min(depth);
+    }
+
+    #[inline(always)]
+    fn merged_vertex_count(&self, vertex_id: VertexId) -> usize {
+        self.merged_vertex_counts
+            .get(&vertex_id)
+            .copied()
+            .unwrap_or(1)
+    }
+
     pub fn vid_for_entry_id(&mut self, entry_id: EntryId) -> Option<VertexId> {
         let raw_vid = self.vertex_id_by_entry_id.get_mut(&entry_id)?;
         let vid = Self::resolve_vid_internal(&mut self.deleted_synonyms, *raw_vid);
@@ -179,6 +267,9 @@ impl FactorData {
             if !already_processed.insert(item.clone()) {
                 continue;
             }
+            let stats = &mut self.propagation_stats;
+            stats.items_processed += 1;
+            stats.max_worklist_len = stats.max_worklist_len.max(worklist.len() + 1);
 
             match item {
                 WorkItem::Propagate {
@@ -202,6 +293,7 @@ impl FactorData {
                             Transitive => {
                                 if !transitive {
                                     self.divisibility_graph[edge_id] = Direct;
+                                    self.propagation_stats.edges_upgraded += 1;
                                     added_or_upgraded = true;
                                 }
                             }
@@ -213,6 +305,7 @@ impl FactorData {
                             dest,
                             if transitive { Transitive } else { Direct },
                         );
+                        self.propagation_stats.edges_added += 1;
                         added_or_upgraded = true;
                     }
 
@@ -293,6 +386,7 @@ impl FactorData {
                     }
 
                     self.divisibility_graph.add_edge(nonfactor, dest, NotFactor);
+                    self.propagation_stats.edges_added += 1;
 
                     for (neighbor, divisibility) in
                         neighbor_vids(&self.divisibility_graph, dest, Incoming)
@@ -336,6 +430,130 @@ impl FactorData {
             == 0.0
     }
 
+    /// Lists every vertex known not to divide the number at `vid`: those with a [NotFactor] edge
+    /// into it, plus any known multiple of one of those, since a multiple of a non-factor can't be
+    /// a factor either. Explains why [FactorData::is_known_factor] rules candidates out.
+    pub fn known_non_factors(&mut self, vid: VertexId) -> Vec<VertexId> {
+        let vid = self.resolve_vid(vid);
+        let mut non_factors = BTreeSet::new();
+        for (nonfactor, divisibility) in neighbor_vids(&self.divisibility_graph, vid, Incoming) {
+            if divisibility != NotFactor {
+                continue;
+            }
+            non_factors.insert(nonfactor);
+            // Divisibility edges are already transitively closed, so one step finds every multiple
+            for (multiple, divisibility) in
+                neighbor_vids(&self.divisibility_graph, nonfactor, Outgoing)
+            {
+                if multiple != vid && matches!(divisibility, Direct | Transitive) {
+                    non_factors.insert(multiple);
+                }
+            }
+        }
+        non_factors.into_iter().collect()
+    }
+
+    /// True if the graph shows that `factor_vid` can't divide `dest_vid`, even without a
+    /// [NotFactor] edge between them: a factor of `factor_vid` that doesn't divide a multiple of
+    /// `dest_vid` (or `dest_vid` itself) rules it out, as does `factor_vid` not dividing such a
+    /// multiple.
+    pub fn is_known_non_factor(&mut self, factor_vid: VertexId, dest_vid: VertexId) -> bool {
+        let factor_vid = self.resolve_vid(factor_vid);
+        let dest_vid = self.resolve_vid(dest_vid);
+        let divisors = once(factor_vid).chain(
+            neighbor_vids(&self.divisibility_graph, factor_vid, Incoming)
+                .into_iter()
+                .filter(|(_, divisibility)| matches!(divisibility, Direct | Transitive))
+                .map(|(divisor, _)| divisor),
+        );
+        let multiples: Vec<_> = once(dest_vid)
+            .chain(
+                neighbor_vids(&self.divisibility_graph, dest_vid, Outgoing)
+                    .into_iter()
+                    .filter(|(_, divisibility)| matches!(divisibility, Direct | Transitive))
+                    .map(|(multiple, _)| multiple),
+            )
+            .collect();
+        let divisors: Vec<_> = divisors.collect();
+        divisors.into_iter().any(|divisor| {
+            multiples
+                .iter()
+                .any(|&multiple| self.get_edge(divisor, multiple) == Some(NotFactor))
+        })
+    }
+
+    /// Divides the factor at `factor_vid` by the factors it's already known to have that can't be
+    /// shared with `target_vid`: the target's denominator, if it's a quotient, and any divisor of
+    /// the factor that's known not to divide the target. If the quotient may still be a factor of
+    /// the target and is new to the graph, adds it and returns its vertex ID, so that it can be
+    /// submitted in place of the original factor. The denominator is only divided out once per
+    /// factor.
+    pub fn divide_out_known_factors(
+        &mut self,
+        factor_vid: VertexId,
+        target_vid: VertexId,
+        http: &impl FactorDbClient,
+    ) -> Option<VertexId> {
+        let factor_vid = self.resolve_vid(factor_vid);
+        let target = self.get_factor(target_vid);
+        let factor = self.get_factor(factor_vid);
+        let mut divided = factor.clone();
+        if let Complex { inner: ref c, .. } = target
+            && let ComplexFactor::Divide {
+                ref right,
+                right_hash,
+                ..
+            } = **c
+        {
+            if replace(
+                &mut self.facts_mut(factor_vid).checked_for_known_common_factors,
+                true,
+            ) {
+                return None;
+            }
+            let denominator = Complex {
+                inner: Multiply {
+                    terms_hash: right_hash,
+                    terms: right.clone(),
+                }
+                .into(),
+                hash: OnceLock::new(),
+            };
+            if denominator.may_be_proper_divisor_of(&divided) {
+                divided = div_exact(&divided, &denominator)
+                    .unwrap_or_else(|| simplify_divide(&divided, right));
+            }
+        }
+        let known_divisors: Vec<VertexId> = self
+            .divisibility_graph
+            .edges_directed(factor_vid, Incoming)
+            .filter(|edge| matches!(edge.weight(), Direct | Transitive))
+            .map(|edge| edge.source())
+            .collect();
+        // Includes multiples of non-factors, which may not have a NotFactor edge of their own
+        let target_non_factors: BTreeSet<VertexId> =
+            self.known_non_factors(target_vid).into_iter().collect();
+        for divisor_vid in known_divisors {
+            if target_non_factors.contains(&divisor_vid)
+                && let Some(quotient) = div_exact(&divided, &self.get_factor(divisor_vid))
+            {
+                divided = quotient;
+            }
+        }
+        if divided == factor || !divided.may_be_proper_divisor_of(&target) {
+            return None;
+        }
+        debug!("Divided known common factors out of {factor} to get {divided}");
+        let (divided_vid, added) = add_factor_node(self, divided, None, http);
+        if !added {
+            return None;
+        }
+        // Don't apply this recursively, except when divided was already in the graph for another
+        // reason
+        self.facts_mut(divided_vid).checked_for_known_common_factors = true;
+        Some(divided_vid)
+    }
+
     pub fn merge_equivalent_expressions(
         &mut self,
         factor_vid: VertexId,
@@ -400,12 +618,54 @@ impl FactorData {
         }
     }
 
+    /// Records that the number at `vid` is FactorDB entry `entry_id`, and if another vertex is
+    /// already known by that ID, merges the two and propagates their divisibility relations.
+    /// Returns the surviving vertex and any new factor vertices the merge turned up.
+    pub fn merge_by_entry_id(
+        &mut self,
+        vid: VertexId,
+        entry_id: EntryId,
+        http: &impl FactorDbClient,
+    ) -> (VertexId, Vec<VertexId>) {
+        let vid = self.resolve_vid(vid);
+        let facts = self.facts_mut(vid);
+        if let Some(known_id) = facts.entry_id
+            && known_id != entry_id
+        {
+            warn!("{known_id}: Not merging with {entry_id}, since they're different entries");
+            return (vid, vec![]);
+        }
+        facts.entry_id = Some(entry_id);
+        let new_factor_vids = match self.vid_for_entry_id(entry_id) {
+            Some(existing_vid) if existing_vid != vid => {
+                info!(
+                    "{entry_id}: Merging {} and {}, which share this ID",
+                    self.get_factor(existing_vid),
+                    self.get_factor(vid)
+                );
+                merge_vertices(self, http, vid, existing_vid)
+            }
+            _ => vec![],
+        };
+        let vid = self.resolve_vid(vid);
+        self.vertex_id_by_entry_id.insert(entry_id, vid);
+        (vid, new_factor_vids)
+    }
+
     fn add_from_factor_finder(
         &mut self,
         factor: &Factor,
         http: &impl FactorDbClient,
     ) -> Vec<VertexId> {
-        find_unique_factors(factor)
+        self.add_found_factors(find_unique_factors(factor), http)
+    }
+
+    fn add_found_factors(
+        &mut self,
+        found_factors: Box<[Factor]>,
+        http: &impl FactorDbClient,
+    ) -> Vec<VertexId> {
+        found_factors
             .into_iter()
             .filter_map(|new_factor| {
                 let entry_id = http
@@ -434,6 +694,328 @@ impl FactorData {
                 .unwrap_or_else(|| Expression(Cow::Owned(factor)))
         }
     }
+
+    /// Returns the vertex with the smallest upper bound that may still be composite and isn't
+    /// known to be fully factored, since finishing off small cofactors makes progress fastest.
+    /// Skips primes, and numbers small enough that we can factor them ourselves.
+    pub fn smallest_unresolved_cofactor(&self) -> Option<VertexId> {
+        self.number_facts_map
+            .iter()
+            .filter(|(vid, _)| !self.deleted_synonyms.contains_key(vid))
+            .filter(|(_, facts)| {
+                facts.numeric_value.is_none()
+                    && facts.last_known_status != Some(Prime)
+                    && !facts.is_known_fully_factored()
+            })
+            .min_by_key(|(_, facts)| facts.estimated_digits())
+            .map(|(&vid, _)| vid)
+    }
+
+    fn is_known_prime(&mut self, vid: VertexId) -> bool {
+        self.facts(vid)
+            .is_some_and(|facts| match facts.numeric_value {
+                Some(value) => is_prime(value),
+                None => facts.last_known_status == Some(Prime),
+            })
+    }
+
+    /// The number at `vid` and its prime factorization, as the primes the graph knows divide it
+    /// with their multiplicities. Returns None unless those primes are known to make up the whole
+    /// number.
+    fn prime_factorization(
+        &mut self,
+        vid: VertexId,
+    ) -> Option<(Factor, Vec<(Factor, NumberLength)>)> {
+        let vid = self.resolve_vid(vid);
+        let number = self.get_factor(vid);
+        let number_upper_bound_log10 = self.facts(vid)?.upper_bound_log10;
+        let prime_vids: Vec<VertexId> = if self.is_known_prime(vid) {
+            vec![vid]
+        } else {
+            self.divisibility_graph
+                .edges_directed(vid, Incoming)
+                .filter(|edge| matches!(edge.weight(), Direct | Transitive))
+                .map(|edge| edge.source())
+                .collect()
+        };
+        let mut primes = Vec::with_capacity(prime_vids.len());
+        for prime_vid in prime_vids {
+            if !self.is_known_prime(prime_vid) {
+                continue;
+            }
+            let prime = self.get_factor(prime_vid);
+            let exponent = match multiplicity(&prime, &number) {
+                Some(exponent) => exponent,
+                // A prime whose square is larger than the number can only divide it once
+                None if self.facts(prime_vid).is_some_and(|prime_facts| {
+                    prime_facts.lower_bound_log10.saturating_mul(2)
+                        > number_upper_bound_log10.saturating_add(1)
+                }) =>
+                {
+                    1
+                }
+                None => {
+                    debug!(
+                        "Can't list the divisors of {number}, since {prime} may divide it more than once"
+                    );
+                    return None;
+                }
+            };
+            primes.push((prime, exponent));
+        }
+        let is_complete = match evaluate_as_numeric(&number) {
+            Some(value) => {
+                primes
+                    .iter()
+                    .try_fold(1 as NumericFactor, |product, (prime, exponent)| {
+                        product.checked_mul(evaluate_as_numeric(prime)?.checked_pow(*exponent)?)
+                    })
+                    == Some(value)
+            }
+            // Complete only if dividing out every prime power leaves nothing behind
+            None => primes
+                .iter()
+                .try_fold(number.clone(), |quotient, (prime, exponent)| {
+                    (0..*exponent).try_fold(quotient, |quotient, _| div_exact(&quotient, prime))
+                })
+                .is_some_and(|quotient| evaluate_as_numeric(&quotient) == Some(1)),
+        };
+        is_complete.then_some((number, primes))
+    }
+
+    /// How many divisors the number at `vid` has, including 1 and the number itself, without
+    /// listing them. Returns None unless the graph knows the number's whole factorization.
+    pub fn divisor_count(&mut self, vid: VertexId) -> Option<usize> {
+        let (_, primes) = self.prime_factorization(vid)?;
+        Some(count_divisors(&primes))
+    }
+
+    /// Lists every divisor of the number at `vid`, including 1 and the number itself, from the
+    /// primes the graph knows divide it. Returns an empty list unless those primes (with their
+    /// multiplicities) are known to make up the whole number, or if there'd be more than
+    /// [MAX_DIVISORS] divisors.
+    pub fn all_divisors(&mut self, vid: VertexId) -> Vec<Factor> {
+        let Some((number, primes)) = self.prime_factorization(vid) else {
+            return vec![];
+        };
+        let count = count_divisors(&primes);
+        if count > MAX_DIVISORS {
+            warn!("{number} has {count} divisors, which is too many to list");
+            return vec![];
+        }
+        let exponents = primes
+            .iter()
+            .enumerate()
+            .map(|(index, (_, exponent))| (index, *exponent))
+            .collect();
+        power_multiset(exponents)
+            .into_iter()
+            .map(|subset| {
+                let divisor = Factor::multiply(
+                    subset
+                        .into_iter()
+                        .map(|(index, exponent)| (primes[index].0.clone(), exponent))
+                        .collect(),
+                );
+                evaluate_as_numeric(&divisor)
+                    .map(Numeric)
+                    .unwrap_or_else(|| simplify(&divisor))
+            })
+            .collect()
+    }
+
+    /// Captures the graph, so that [FactorData::from_snapshot] can rebuild it after a restart.
+    pub fn snapshot(&mut self, root_id: EntryId, root_hash: u64) -> GraphSnapshot {
+        let vids: Vec<_> = self.divisibility_graph.node_indices().collect();
+        let positions: BTreeMap<_, _> = vids.iter().enumerate().map(|(i, &vid)| (vid, i)).collect();
+        let mut vertices = Vec::with_capacity(vids.len());
+        for &vid in &vids {
+            let (known_factors, factors_up_to_date) =
+                match &self.number_facts_map[&vid].factors_known_to_factordb {
+                    UpToDate(known_factors) => (known_factors.clone(), true),
+                    NotUpToDate(known_factors) => (known_factors.clone(), false),
+                };
+            let factors_known_to_factordb = known_factors
+                .into_iter()
+                .filter_map(|known_factor| positions.get(&self.resolve_vid(known_factor)).copied())
+                .collect();
+            let facts = &self.number_facts_map[&vid];
+            vertices.push(VertexSnapshot {
+                expr: self.divisibility_graph[vid]
+                    .to_unelided_string()
+                    .to_string(),
+                entry_id: facts.entry_id,
+                last_known_status: facts.last_known_status,
+                factors_known_to_factordb,
+                factors_up_to_date,
+                checked_for_listed_algebraic: facts.checked_for_listed_algebraic,
+                checked_in_factor_finder: facts.checked_in_factor_finder,
+                expression_form_checked_in_factor_finder: facts
+                    .expression_form_checked_in_factor_finder,
+                checked_for_known_common_factors: facts.checked_for_known_common_factors,
+            });
+        }
+        let edges = self
+            .divisibility_graph
+            .edge_references()
+            .map(|edge| {
+                (
+                    positions[&edge.source()],
+                    positions[&edge.target()],
+                    *edge.weight(),
+                )
+            })
+            .collect();
+        GraphSnapshot {
+            root_id,
+            root_hash,
+            vertices,
+            edges,
+        }
+    }
+
+    /// Rebuilds a graph saved by [FactorData::snapshot], and returns it with the root's vertex ID.
+    /// Returns None if the root is missing from the snapshot.
+    pub fn from_snapshot(snapshot: GraphSnapshot) -> Option<(Self, VertexId)> {
+        let mut data = FactorData::default();
+        let mut vids = Vec::with_capacity(snapshot.vertices.len());
+        for vertex in &snapshot.vertices {
+            let factor = Factor::from(vertex.expr.as_str());
+            let vid = data.divisibility_graph.add_node(factor.clone());
+            let (lower_bound_log10, upper_bound_log10) = estimate_log10(&factor);
+            if let Some(entry_id) = vertex.entry_id {
+                data.vertex_id_by_entry_id.insert(entry_id, vid);
+            }
+            data.number_facts_map.insert(
+                vid,
+                NumberFacts {
+                    last_known_status: vertex.last_known_status,
+                    factors_known_to_factordb: NotUpToDate(vec![]),
+                    numeric_value: evaluate_as_numeric(&factor),
+                    lower_bound_log10,
+                    upper_bound_log10,
+                    entry_id: vertex.entry_id,
+                    checked_for_listed_algebraic: vertex.checked_for_listed_algebraic,
+                    checked_in_factor_finder: vertex.checked_in_factor_finder,
+                    expression_form_checked_in_factor_finder: vertex
+                        .expression_form_checked_in_factor_finder,
+                    checked_for_known_common_factors: vertex.checked_for_known_common_factors,
+                },
+            );
+            data.vertex_id_by_expr.insert(factor, vid);
+            vids.push(vid);
+        }
+        for (vertex, &vid) in snapshot.vertices.iter().zip(&vids) {
+            let known_factors = vertex
+                .factors_known_to_factordb
+                .iter()
+                .filter_map(|&i| vids.get(i).copied())
+                .collect();
+            data.facts_mut(vid).factors_known_to_factordb = if vertex.factors_up_to_date {
+                UpToDate(known_factors)
+            } else {
+                NotUpToDate(known_factors)
+            };
+        }
+        for &(source, dest, divisibility) in &snapshot.edges {
+            if let (Some(&source), Some(&dest)) = (vids.get(source), vids.get(dest)) {
+                data.divisibility_graph.add_edge(source, dest, divisibility);
+            }
+        }
+        let root_vid = data.vid_for_entry_id(snapshot.root_id)?;
+        Some((data, root_vid))
+    }
+
+    /// If the graph has more than `max_vertices` vertices, evicts fully-processed primes that are
+    /// already known to divide the root, smallest first, until it doesn't. Only a prime whose
+    /// divisibility into every number not yet known to be fully factored is already known either
+    /// way is evicted, since it has nowhere left to be submitted; forgetting it frees memory that
+    /// the remaining propagation would otherwise have to walk. Vertices in `pending` are never
+    /// evicted. Returns the evicted vertex IDs.
+    pub fn compact(
+        &mut self,
+        max_vertices: usize,
+        root_vid: VertexId,
+        pending: impl IntoIterator<Item = VertexId>,
+    ) -> Vec<VertexId> {
+        let node_count = self.divisibility_graph.node_count();
+        if node_count <= max_vertices {
+            return vec![];
+        }
+        let root_vid = self.resolve_vid(root_vid);
+        let pending: BTreeSet<VertexId> = pending
+            .into_iter()
+            .map(|vid| self.resolve_vid(vid))
+            .collect();
+        let mut candidates: Vec<_> = self
+            .number_facts_map
+            .iter()
+            .filter(|(vid, facts)| {
+                **vid != root_vid
+                    && !pending.contains(vid)
+                    && facts.last_known_status == Some(Prime)
+                    && facts.is_fully_processed()
+            })
+            .map(|(vid, facts)| (facts.lower_bound_log10, *vid))
+            .collect();
+        candidates.retain(|(_, vid)| self.is_known_factor(*vid, root_vid));
+        let unfactored: Vec<VertexId> = self
+            .number_facts_map
+            .iter()
+            .filter(|(_, facts)| !facts.is_known_fully_factored())
+            .map(|(vid, _)| *vid)
+            .collect();
+        candidates.retain(|(_, vid)| {
+            unfactored.iter().all(|&other| {
+                self.divisibility_graph
+                    .edges_connecting(*vid, other)
+                    .next()
+                    .is_some()
+            })
+        });
+        candidates.sort_unstable();
+        let evicted: BTreeSet<VertexId> = candidates
+            .into_iter()
+            .take(node_count - max_vertices)
+            .map(|(_, vid)| vid)
+            .collect();
+        if evicted.is_empty() {
+            return vec![];
+        }
+        // Includes synonyms that resolve to an evicted vertex
+        let dead: BTreeSet<VertexId> = self
+            .deleted_synonyms
+            .keys()
+            .copied()
+            .collect::<Vec<_>>()
+            .into_iter()
+            .filter(|&synonym| evicted.contains(&self.resolve_vid(synonym)))
+            .chain(evicted.iter().copied())
+            .collect();
+        for vid in dead.iter() {
+            self.deleted_synonyms.remove(vid);
+        }
+        for vid in evicted.iter() {
+            self.divisibility_graph.remove_node(*vid);
+            self.number_facts_map.remove(vid);
+            self.merged_vertex_counts.remove(vid);
+        }
+        self.vertex_id_by_expr.retain(|_, vid| !dead.contains(vid));
+        self.vertex_id_by_entry_id
+            .retain(|_, vid| !dead.contains(vid));
+        self.decomposition_depths
+            .retain(|vid, _| !dead.contains(vid));
+        for facts in self.number_facts_map.values_mut() {
+            facts
+                .factors_known_to_factordb
+                .retain(|vid| !dead.contains(vid));
+        }
+        info!(
+            "Evicted {} fully-processed primes from a divisibility graph of {node_count} vertices",
+            evicted.len()
+        );
+        evicted.into_iter().collect()
+    }
 }
 
 pub fn add_factor_node(
@@ -442,6 +1024,10 @@ pub fn add_factor_node(
     entry_id: Option<EntryId>,
     http: &impl FactorDbClient,
 ) -> (VertexId, bool) {
+    // So that trivially equal forms such as x^1 and x*1 share x's vertex, as do forms that
+    // evaluate to the same number, such as (2^9+1)^2 and 263169
+    let factor = simplify(&factor);
+    let factor = evaluate_as_numeric(&factor).map_or(factor, Numeric);
     let existing_vertex = data.vid_for_expr(&factor);
     let entry_id = entry_id.or_else(|| data.facts(existing_vertex?)?.entry_id);
     let cached_factors = entry_id
@@ -475,7 +1061,7 @@ pub fn add_factor_node(
                     checked_for_listed_algebraic: has_cached,
                     checked_in_factor_finder: has_cached,
                     expression_form_checked_in_factor_finder: has_cached,
-                    checked_with_root_denominator: has_cached,
+                    checked_for_known_common_factors: has_cached,
                 },
             );
 
@@ -515,7 +1101,8 @@ pub fn add_factor_node(
             }
         }
     }
-    (merge_dest, added)
+    // merge_dest may have been merged into matching_vid
+    (data.resolve_vid(merge_dest), added)
 }
 
 fn merge_vertices(
@@ -533,7 +1120,19 @@ fn merge_vertices(
     if data.deleted_synonyms.contains_key(&matching_vid) {
         return vec![]; // Already being merged
     }
+    // Union by size: redirect the smaller set of synonyms into the larger one, so that synonym
+    // chains stay short
+    let dest_count = data.merged_vertex_count(merge_dest);
+    let matching_count = data.merged_vertex_count(matching_vid);
+    let (merge_dest, matching_vid) = if matching_count > dest_count {
+        (matching_vid, merge_dest)
+    } else {
+        (merge_dest, matching_vid)
+    };
     data.deleted_synonyms.insert(matching_vid, merge_dest);
+    data.merged_vertex_counts.remove(&matching_vid);
+    data.merged_vertex_counts
+        .insert(merge_dest, dest_count + matching_count);
     let mut worklist = BTreeSet::new();
     neighbor_vids(&data.divisibility_graph, matching_vid, Incoming)
         .into_iter()
@@ -629,10 +1228,10 @@ fn merge_vertices(
                     .expression_form_checked_in_factor_finder
                     && old_facts.expression_form_checked_in_factor_finder,
 
-                // root_denominator only has to be done with one or the other, because it doesn't depend
-                // on the expression form among equivalents
-                checked_with_root_denominator: facts.checked_with_root_denominator
-                    || old_facts.checked_with_root_denominator,
+                // divide_out_known_factors only has to be done with one or the other, because it
+                // doesn't depend on the expression form among equivalents
+                checked_for_known_common_factors: facts.checked_for_known_common_factors
+                    || old_facts.checked_for_known_common_factors,
             }
         });
     }
@@ -745,6 +1344,12 @@ impl FactorsKnownToFactorDb {
             NotUpToDate(_) => true,
         }
     }
+
+    fn retain(&mut self, keep: impl FnMut(&VertexId) -> bool) {
+        match self {
+            NotUpToDate(vids) | UpToDate(vids) => vids.retain(keep),
+        }
+    }
 }
 
 #[derive(Debug)]
@@ -758,7 +1363,7 @@ pub struct NumberFacts {
     checked_for_listed_algebraic: bool,
     checked_in_factor_finder: bool,
     expression_form_checked_in_factor_finder: bool,
-    checked_with_root_denominator: bool,
+    checked_for_known_common_factors: bool,
 }
 
 impl PartialEq<Self> for NumberFacts {
@@ -796,42 +1401,331 @@ impl NumberFacts {
             && self.expression_form_checked_in_factor_finder
             && self.checked_in_factor_finder
     }
+
+    /// Approximate number of decimal digits; exact if the value is known.
+    #[inline(always)]
+    fn estimated_digits(&self) -> NumberLength {
+        match self.numeric_value {
+            Some(numeric_value) => numeric_value.checked_ilog10().unwrap_or(0) + 1,
+            None => digit_count_from_log10_bounds(self.lower_bound_log10, self.upper_bound_log10),
+        }
+    }
+
+    /// Upper bound on the number of decimal digits.
+    #[inline(always)]
+    fn max_digits(&self) -> NumberLength {
+        match self.numeric_value {
+            Some(numeric_value) => numeric_value.checked_ilog10().unwrap_or(0) + 1,
+            None => self.upper_bound_log10.saturating_add(1),
+        }
+    }
+}
+
+/// False if `verify` is set and `factor` definitely doesn't divide `dest`.
+fn passes_verification(id: EntryId, factor: &Factor, dest: &Factor, verify: bool) -> bool {
+    if verify && divides(factor, dest) == Some(false) {
+        // In full, so that the mismatch can be reproduced
+        with_bignum_elision(BignumElision::UNELIDED, || {
+            error!("{id}: {factor} doesn't actually divide {dest}, so not submitting it")
+        });
+        false
+    } else {
+        true
+    }
+}
+
+/// Submits `factor` to `dest`, as a power if `dest` is known to be divisible by it more than once.
+async fn report_factor(
+    http: &impl FactorDbClient,
+    dest_specifier: NumberSpecifier<'_>,
+    factor: &Factor,
+    dest: &Factor,
+) -> ReportFactorResult {
+    match multiplicity(factor, dest) {
+        Some(exponent @ 2..) => {
+            info!("Submitting {factor}^{exponent} to {dest}");
+            http.report_factor_with_exponent(dest_specifier, factor, exponent)
+                .await
+        }
+        _ => http.try_report_factor(dest_specifier, factor).await,
+    }
+}
+
+/// True if `factor_vid` is too small to be worth submitting on its own (see
+/// [SubmissionOptions::min_submitted_factor_digits]).
+fn is_below_submission_threshold(
+    data: &mut FactorData,
+    factor_vid: VertexId,
+    min_digits: NumberLength,
+) -> bool {
+    min_digits > 0
+        && data
+            .facts(factor_vid)
+            .is_some_and(|facts| facts.max_digits() < min_digits)
+}
+
+/// True if `contribute_only` is set and FactorDB already lists `factor_vid` among the factors of
+/// `dest_vid`, so submitting it there wouldn't tell FactorDB anything new. A factor FactorDB lists
+/// for one number can still be new to its multiples.
+fn is_withheld_as_known_to_factordb(
+    data: &mut FactorData,
+    factor_vid: VertexId,
+    dest_vid: VertexId,
+    contribute_only: bool,
+) -> bool {
+    if !contribute_only {
+        return false;
+    }
+    let known_factor_vids = match data.facts(dest_vid) {
+        Some(facts) => match facts.factors_known_to_factordb {
+            UpToDate(ref vids) | NotUpToDate(ref vids) => vids.clone(),
+        },
+        None => return false,
+    };
+    let factor_vid = data.resolve_vid(factor_vid);
+    known_factor_vids
+        .into_iter()
+        .any(|known_factor_vid| data.resolve_vid(known_factor_vid) == factor_vid)
 }
 
 #[inline(always)]
 fn dedup_and_shuffle<T: Ord>(deque: &mut VecDeque<T>) {
     let deque_as_set = BTreeSet::from_iter(deque.drain(..));
     deque.extend(deque_as_set);
-    deque.make_contiguous().shuffle(&mut rng());
+    deque.make_contiguous().shuffle(&mut shuffle_rng());
+}
+
+/// Moves the factors in `pending_elided` that now have an equivalent non-elided expression (e.g.
+/// because their vertex was merged with one) to the front of `queue`, so they're retried.
+fn requeue_expressible_factors(
+    data: &mut FactorData,
+    pending_elided: &mut BTreeSet<VertexId>,
+    queue: &mut VecDeque<VertexId>,
+) {
+    let expressible: Vec<_> = pending_elided
+        .iter()
+        .copied()
+        .filter(|&vid| !data.get_factor(vid).is_elided())
+        .collect();
+    for vid in expressible {
+        pending_elided.remove(&vid);
+        let vid = data.resolve_vid(vid);
+        info!(
+            "Retrying submission of a factor that was missing digits as {}",
+            data.get_factor(vid)
+        );
+        if !queue.contains(&vid) {
+            queue.push_front(vid);
+        }
+    }
+}
+
+#[inline(always)]
+fn next_factor_to_submit(
+    data: &mut FactorData,
+    pending_elided: &mut BTreeSet<VertexId>,
+    queue: &mut VecDeque<VertexId>,
+) -> Option<VertexId> {
+    requeue_expressible_factors(data, pending_elided, queue);
+    queue.pop_front()
+}
+
+/// What [find_and_submit_factors] accomplished.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum SubmissionOutcome {
+    /// FactorDB already knew the full factorization, so there was nothing to submit.
+    AlreadyFullyFactored,
+    /// FactorDB accepted this many factors.
+    Accepted(usize),
+    NoProgress,
+}
+
+impl SubmissionOutcome {
+    fn from_accepted_count(accepted_factors: usize) -> Self {
+        if accepted_factors > 0 {
+            SubmissionOutcome::Accepted(accepted_factors)
+        } else {
+            SubmissionOutcome::NoProgress
+        }
+    }
+
+    pub fn made_progress(self) -> bool {
+        self != SubmissionOutcome::NoProgress
+    }
+}
+
+/// How long a [find_and_submit_factors] call took.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct SubmissionTiming {
+    pub total: Duration,
+    /// The part of `total` spent waiting for the factor finder.
+    pub factoring: Duration,
+    pub propagation: PropagationStats,
 }
 
+/// Settings that decide which factors [find_and_submit_factors] submits. They're passed in rather
+/// than read from the globals that `main` sets, so that tests can vary them independently.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SubmissionOptions {
+    /// Factors with fewer digits are only submitted when nothing larger is accepted (see
+    /// [MIN_SUBMITTED_FACTOR_DIGITS]).
+    pub min_submitted_factor_digits: NumberLength,
+    /// Whether to check that factors really divide their destinations before submitting them
+    /// (see [VERIFY_FACTORS]).
+    pub verify_factors: bool,
+    /// Whether to skip factors that FactorDB already lists for the destination (see
+    /// [CONTRIBUTE_ONLY]).
+    pub contribute_only: bool,
+    /// Whether to prime the graph with [FactorDbClient::small_factors] before fetching the root's
+    /// full factor list.
+    pub prime_with_small_factors: bool,
+}
+
+impl SubmissionOptions {
+    /// The options configured for this process.
+    pub fn from_globals() -> Self {
+        SubmissionOptions {
+            min_submitted_factor_digits: MIN_SUBMITTED_FACTOR_DIGITS.load(Acquire),
+            verify_factors: VERIFY_FACTORS.load(Acquire),
+            contribute_only: CONTRIBUTE_ONLY.load(Acquire),
+            prime_with_small_factors: true,
+        }
+    }
+}
+
+/// Calls to [find_and_submit_factors] that take longer than this are logged as warnings, so that
+/// pathological expressions can be found.
+const SLOW_SUBMISSION_THRESHOLD: Duration = Duration::from_secs(300);
+
 #[framed]
 pub async fn find_and_submit_factors(
     http: &impl FactorDbClientReadIdsAndExprs,
     id: EntryId,
     root_factor: Factor,
     skip_looking_up_known: bool,
-) -> bool {
-    let mut digits_or_expr_full = Vec::new();
-    let mut data = FactorData::default();
-    let elided = root_factor.is_elided();
-    let (mut root_vid, _) = add_factor_node(&mut data, root_factor, Some(id), http);
-    let mut factor_found = false;
-    if (!skip_looking_up_known) || elided {
-        let ProcessedStatusApiResponse {
-            factors: known_factors,
-            status,
-            ..
-        } = http.known_factors_as_digits(Id(id), false, true).await;
-        if status.is_known_fully_factored() {
-            warn!("{id}: Already fully factored");
-            return true;
-        }
-        if known_factors.len() == 1 && status != Some(PartlyFactoredComposite) {
-            factor_found |= !data
-                .merge_equivalent_expressions(
-                    root_vid,
-                    known_factors.into_iter().next().unwrap(),
+    options: SubmissionOptions,
+) -> SubmissionOutcome {
+    find_and_submit_factors_timed(http, id, root_factor, skip_looking_up_known, options)
+        .await
+        .0
+}
+
+#[framed]
+async fn find_and_submit_factors_timed(
+    http: &impl FactorDbClientReadIdsAndExprs,
+    id: EntryId,
+    root_factor: Factor,
+    skip_looking_up_known: bool,
+    options: SubmissionOptions,
+) -> (SubmissionOutcome, SubmissionTiming) {
+    let start = Instant::now();
+    let snapshot_root_hash =
+        (GRAPH_SNAPSHOT_INTERVAL.load(Acquire) > 0).then(|| root_hash(&root_factor));
+    let mut data = if let Some(root_hash) = snapshot_root_hash
+        && let Ok(Some(snapshot)) = task::spawn_blocking(move || {
+            GraphSnapshot::load(Path::new(GRAPH_SNAPSHOT_DIR), id, root_hash)
+        })
+        .await
+        && let Some((data, _)) = FactorData::from_snapshot(snapshot)
+    {
+        info!(
+            "{id}: Resuming with a saved divisibility graph of {} vertices",
+            data.divisibility_graph.node_count()
+        );
+        data
+    } else {
+        FactorData::default()
+    };
+    let outcome = submit_factors_using_graph(
+        http,
+        &mut data,
+        id,
+        root_factor.clone(),
+        skip_looking_up_known,
+        options,
+    )
+    .await;
+    if let Some(root_hash) = snapshot_root_hash
+        && let Err(e) = task::spawn_blocking(move || {
+            GraphSnapshot::delete(Path::new(GRAPH_SNAPSHOT_DIR), id, root_hash)
+        })
+        .await
+    {
+        error!("{id}: Deleting divisibility graph snapshot failed: {e}");
+    }
+    let timing = SubmissionTiming {
+        total: start.elapsed(),
+        factoring: data.factoring_time,
+        propagation: data.propagation_stats,
+    };
+    info!(
+        "{id}: Finished with {root_factor} in {:?} ({:?} of it factoring; {}): {outcome:?}",
+        timing.total, timing.factoring, timing.propagation
+    );
+    if timing.total > SLOW_SUBMISSION_THRESHOLD {
+        warn!(
+            "{id}: Submitting factors of {root_factor} took {:?}, of which {:?} was factoring",
+            timing.total, timing.factoring
+        );
+    }
+    (outcome, timing)
+}
+
+#[framed]
+async fn submit_factors_using_graph(
+    http: &impl FactorDbClientReadIdsAndExprs,
+    data: &mut FactorData,
+    id: EntryId,
+    root_factor: Factor,
+    skip_looking_up_known: bool,
+    options: SubmissionOptions,
+) -> SubmissionOutcome {
+    let mut digits_or_expr_full = Vec::new();
+    let max_depth = MAX_DECOMPOSITION_DEPTH.load(Acquire);
+    let min_digits = options.min_submitted_factor_digits;
+    let elided = root_factor.is_elided();
+    let snapshot_interval = GRAPH_SNAPSHOT_INTERVAL.load(Acquire);
+    let snapshot_root_hash = (snapshot_interval > 0).then(|| root_hash(&root_factor));
+    let (mut root_vid, _) = add_factor_node(data, root_factor, Some(id), http);
+    data.record_decomposition_depth(root_vid, 0);
+    let mut factor_found = false;
+    let small_factors = if skip_looking_up_known || elided || !options.prime_with_small_factors {
+        None
+    } else {
+        http.small_factors(Id(id))
+            .await
+            .filter(|small_factors| !small_factors.is_empty())
+    };
+    if let Some(small_factors) = small_factors {
+        info!(
+            "{id}: Priming graph with {} small factors",
+            small_factors.len()
+        );
+        let root_factors = small_factors
+            .iter()
+            .map(|&small_factor| {
+                let (factor_vid, _) = add_factor_node(data, Numeric(small_factor), None, http);
+                data.propagate_divisibility(factor_vid, root_vid, false);
+                factor_vid
+            })
+            .collect();
+        // Not up to date, so add_factors_to_graph will still do the full fetch for the root
+        data.facts_mut(root_vid).factors_known_to_factordb = NotUpToDate(root_factors);
+    } else if (!skip_looking_up_known) || elided {
+        let ProcessedStatusApiResponse {
+            factors: known_factors,
+            status,
+            ..
+        } = http.known_factors_as_digits(Id(id), false, true).await;
+        if status.is_known_fully_factored() {
+            warn!("{id}: Already fully factored");
+            return SubmissionOutcome::AlreadyFullyFactored;
+        }
+        if known_factors.len() == 1 && status != Some(PartlyFactoredComposite) {
+            factor_found |= !data
+                .merge_equivalent_expressions(
+                    root_vid,
+                    known_factors.into_iter().next().unwrap(),
                     http,
                     true,
                 )
@@ -843,8 +1737,7 @@ pub async fn find_and_submit_factors(
                     let entry_id = http
                         .cached_factors(&Expression(Borrowed(&known_factor)))
                         .and_then(|f| f.id);
-                    let (factor_vid, added) =
-                        add_factor_node(&mut data, known_factor, entry_id, http);
+                    let (factor_vid, added) = add_factor_node(data, known_factor, entry_id, http);
                     if added {
                         data.propagate_divisibility(factor_vid, root_vid, false);
                         digits_or_expr_full.push(factor_vid);
@@ -872,14 +1765,16 @@ pub async fn find_and_submit_factors(
         root_facts.factors_known_to_factordb = UpToDate(vec![root_vid]);
         root_facts.last_known_status = Some(UnfactoredComposite);
     }
-    let root_factor = data.get_factor(root_vid);
-    debug!("{id}: Root node for {root_factor} has vertex ID {root_vid:?}",);
+    debug!(
+        "{id}: Root node for {} has vertex ID {root_vid:?}",
+        data.get_factor(root_vid)
+    );
     digits_or_expr_full.push(root_vid);
     let mut accepted_factors = 0;
     let mut any_unprocessed = false;
     for factor_vid in digits_or_expr_full.into_iter().rev() {
         let factor_vid = data.resolve_vid(factor_vid);
-        factor_found |= !add_factors_to_graph(http, &mut data, factor_vid)
+        factor_found |= !add_factors_to_graph(http, data, factor_vid, max_depth)
             .await
             .is_empty();
         let factor_vid = data.resolve_vid(factor_vid);
@@ -891,37 +1786,33 @@ pub async fn find_and_submit_factors(
     }
     if !factor_found && !any_unprocessed {
         info!("{id}: No factors to submit");
-        return false;
+        return SubmissionOutcome::NoProgress;
     }
     // Simplest case: try submitting all factors as factors of the root
-    let (root_denominator_terms, root_denominator) = if let Complex { inner: ref c, .. } =
-        root_factor
-        && let ComplexFactor::Divide {
-            right, right_hash, ..
-        } = &**c
-    {
-        let multiply = Complex {
-            inner: Multiply {
-                terms_hash: *right_hash,
-                terms: right.clone(),
-            }
-            .into(),
-            hash: OnceLock::new(),
-        };
-        (Some(right.clone()), Some(multiply))
-    } else {
-        (None, None)
-    };
     let mut all_vids: BTreeSet<VertexId> = data.divisibility_graph.node_indices().collect();
     let mut known_factors: Vec<_> = all_vids
         .iter()
         .copied()
         .filter(|&v| v != root_vid)
         .collect();
-    known_factors.shuffle(&mut rng());
+    known_factors.shuffle(&mut shuffle_rng());
     let mut known_factors = VecDeque::from(known_factors);
     let mut factors_to_submit_in_graph = VecDeque::new();
-    while let Some(factor_vid) = known_factors.pop_front() {
+    // Factors we can't submit until we learn an expression for them that has all its digits
+    let mut pending_elided = BTreeSet::new();
+    // Small factors are held back, and only submitted if nothing else is accepted
+    let mut deferred_small_factors = VecDeque::new();
+    let mut submit_small_factors = false;
+    while let Some(factor_vid) = known_factors.pop_front().or_else(|| {
+        if accepted_factors == 0 && !deferred_small_factors.is_empty() {
+            info!("{id}: No larger factors were accepted, so submitting small factors");
+            submit_small_factors = true;
+            known_factors.append(&mut deferred_small_factors);
+            known_factors.pop_front()
+        } else {
+            None
+        }
+    }) {
         let factor = data.get_factor(factor_vid);
         debug!("{id}: Factor {factor} has vertex ID {factor_vid:?}");
         match data.get_edge(factor_vid, root_vid) {
@@ -937,56 +1828,64 @@ pub async fn find_and_submit_factors(
             _ => {}
         }
         if factor.is_elided() {
+            if fetch_unelided_digits(http, data, factor_vid).await {
+                known_factors.push_front(data.resolve_vid(factor_vid));
+                continue;
+            }
             // Can't submit a factor that we can't express, but
             // running add_factors_to_graph may provide an equivalent expression, else we can save
             // it in case we find out the ID later
             info!("{id}: Temporarily skipping {factor} because digits are missing");
-            let factors_of_factor = add_factors_to_graph(http, &mut data, factor_vid).await;
+            let factors_of_factor = add_factors_to_graph(http, data, factor_vid, max_depth).await;
             if !factors_of_factor.is_empty() {
                 all_vids.extend(factors_of_factor.iter().copied());
                 factors_to_submit_in_graph.extend(factors_of_factor);
                 dedup_and_shuffle(&mut factors_to_submit_in_graph);
             }
-            if !factors_to_submit_in_graph.contains(&factor_vid) {
-                factors_to_submit_in_graph.push_back(factor_vid);
-            }
+            pending_elided.insert(factor_vid);
             continue;
         }
-        match http.try_report_factor(Id(id), &factor).await {
-            AlreadyFullyFactored => return true,
+        if !submit_small_factors && is_below_submission_threshold(data, factor_vid, min_digits) {
+            debug!("{id}: Deferring submission of {factor} because it's below the minimum size");
+            deferred_small_factors.push_back(factor_vid);
+            continue;
+        }
+        if is_withheld_as_known_to_factordb(data, factor_vid, root_vid, options.contribute_only) {
+            debug!("{id}: Not submitting {factor} because FactorDB already lists it");
+            continue;
+        }
+        if !passes_verification(
+            id,
+            &factor,
+            &data.get_factor(root_vid),
+            options.verify_factors,
+        ) {
+            data.rule_out_divisibility(factor_vid, root_vid);
+            continue;
+        }
+        match report_factor(http, Id(id), &factor, &data.get_factor(root_vid)).await {
+            AlreadyFullyFactored => return SubmissionOutcome::AlreadyFullyFactored,
             Accepted => {
                 data.propagate_divisibility(factor_vid, root_vid, false);
-                mark_stale(&mut data, root_vid, http);
-                let new_root_factors = add_factors_to_graph(http, &mut data, root_vid).await;
+                mark_stale(data, root_vid, http);
+                let new_root_factors = add_factors_to_graph(http, data, root_vid, max_depth).await;
                 all_vids.extend(new_root_factors.iter().copied());
                 accepted_factors += 1;
             }
             DoesNotDivide => {
-                let subfactors = add_factors_to_graph(http, &mut data, factor_vid).await;
+                let subfactors = add_factors_to_graph(http, data, factor_vid, max_depth).await;
                 let subfactors_found = !subfactors.is_empty();
                 if subfactors_found {
                     all_vids.extend(subfactors.iter().copied());
                     factors_to_submit_in_graph.extend(subfactors);
                     dedup_and_shuffle(&mut factors_to_submit_in_graph);
                 }
-                if !subfactors_found && let Some(ref root_denominator) = root_denominator {
-                    data.facts_mut(factor_vid).checked_with_root_denominator = true;
-                    if root_denominator.may_be_proper_divisor_of(&factor) {
-                        let divided = div_exact(&factor, root_denominator).unwrap_or_else(|| {
-                            simplify_divide(&factor, root_denominator_terms.as_ref().unwrap())
-                        });
-                        if divided.may_be_proper_divisor_of(&root_factor) {
-                            let (divided_vid, added) =
-                                add_factor_node(&mut data, divided, None, http);
-                            if added {
-                                all_vids.insert(divided_vid);
-                                factors_to_submit_in_graph.push_back(divided_vid);
-                                // Don't apply this recursively, except when divided was already in
-                                // the graph for another reason
-                                data.facts_mut(divided_vid).checked_with_root_denominator = true;
-                            }
-                        }
-                    }
+                if !subfactors_found
+                    && let Some(divided_vid) =
+                        data.divide_out_known_factors(factor_vid, root_vid, http)
+                {
+                    all_vids.insert(divided_vid);
+                    factors_to_submit_in_graph.push_back(divided_vid);
                 }
             }
             OtherError => {
@@ -995,9 +1894,16 @@ pub async fn find_and_submit_factors(
         }
         root_vid = data.resolve_vid(root_vid);
     }
+    requeue_expressible_factors(data, &mut pending_elided, &mut factors_to_submit_in_graph);
     if factors_to_submit_in_graph.is_empty() {
         info!("{id}: {accepted_factors} factors accepted in a single pass");
-        return accepted_factors > 0;
+        if !pending_elided.is_empty() {
+            info!(
+                "{id}: {} factors couldn't be submitted because digits are missing",
+                pending_elided.len()
+            );
+        }
+        return SubmissionOutcome::from_accepted_count(accepted_factors);
     }
 
     // A submission failed retryably, so now it gets more complicated:
@@ -1008,9 +1914,11 @@ pub async fn find_and_submit_factors(
     //     directly to the root.
     // (3) If both (1) and (2) apply, then we may end up with a factor that's a factor of multiple
     //     cofactors, so we need to report it to *all* of them to ensure FactorDB knows its full
-    //     exponent.
+    //     exponent. Once one cofactor accepts it, we keep going through the rest in the same pass,
+    //     rather than requeueing the factor and rebuilding its list of cofactors for each one.
     let mut iters_without_progress = 0;
     let mut iters_to_next_report = 0;
+    let mut iters_to_next_snapshot = snapshot_interval;
     info!(
         "{id}: {} factors left to submit after first pass",
         factors_to_submit_in_graph.len()
@@ -1021,12 +1929,24 @@ pub async fn find_and_submit_factors(
         .is_known_fully_factored()
         && let node_count = data.divisibility_graph.node_count()
         && iters_without_progress < node_count * SUBMIT_FACTOR_MAX_ATTEMPTS
-        && let Some(factor_vid) = factors_to_submit_in_graph.pop_front()
+        && let Some(factor_vid) =
+            next_factor_to_submit(data, &mut pending_elided, &mut factors_to_submit_in_graph)
         && let edge_count = data.divisibility_graph.edge_count()
         && let complete_graph_edge_count = node_count * (node_count - 1)
         && edge_count < complete_graph_edge_count
     {
         if iters_to_next_report == 0 {
+            for evicted_vid in data.compact(
+                MAX_GRAPH_VERTICES.load(Acquire),
+                root_vid,
+                factors_to_submit_in_graph
+                    .iter()
+                    .chain(pending_elided.iter())
+                    .copied()
+                    .chain(once(factor_vid)),
+            ) {
+                all_vids.remove(&evicted_vid);
+            }
             iters_to_next_report = node_count.min(20);
             let (direct_divisors, non_factors) = data
                 .divisibility_graph
@@ -1053,9 +1973,26 @@ pub async fn find_and_submit_factors(
                     .filter(|facts| facts.entry_id.is_some())
                     .count()
             );
+            info!("{id}: So far: {}", data.propagation_stats);
         }
         iters_without_progress += 1;
         iters_to_next_report -= 1;
+        if let Some(root_hash) = snapshot_root_hash {
+            iters_to_next_snapshot -= 1;
+            if iters_to_next_snapshot == 0 {
+                iters_to_next_snapshot = snapshot_interval;
+                let snapshot = data.snapshot(id, root_hash);
+                let compress = COMPRESS_GRAPH_SNAPSHOTS.load(Acquire);
+                // Awaited so that the snapshot can't be written after it's deleted on completion
+                if let Err(e) = task::spawn_blocking(move || {
+                    snapshot.save(Path::new(GRAPH_SNAPSHOT_DIR), compress)
+                })
+                .await
+                {
+                    error!("{id}: Saving divisibility graph snapshot failed: {e}");
+                }
+            }
+        }
         // root can't be a factor of any other number we'll encounter
         data.rule_out_divisibility(root_vid, factor_vid);
         // elided numbers and numbers over 65500 digits without an expression form can only
@@ -1064,18 +2001,25 @@ pub async fn find_and_submit_factors(
         // later
         let factor = data.get_factor(factor_vid);
         if factor.is_elided() {
+            if fetch_unelided_digits(http, data, factor_vid).await {
+                factors_to_submit_in_graph.push_front(data.resolve_vid(factor_vid));
+                continue;
+            }
             info!("{id}: Temporarily skipping {factor} because digits are missing");
             // Can't submit a factor that we can't express, but
             // running add_factors_to_graph may provide an equivalent expression, else we can save
             // it in case we find out the ID later
-            let new_factors_of_factor = add_factors_to_graph(http, &mut data, factor_vid).await;
+            let new_factors_of_factor =
+                add_factors_to_graph(http, data, factor_vid, max_depth).await;
             if !new_factors_of_factor.is_empty() {
                 factors_to_submit_in_graph.extend(new_factors_of_factor);
                 dedup_and_shuffle(&mut factors_to_submit_in_graph);
             }
-            if !factors_to_submit_in_graph.contains(&factor_vid) {
-                factors_to_submit_in_graph.push_back(factor_vid);
-            }
+            pending_elided.insert(factor_vid);
+            continue;
+        }
+        if !submit_small_factors && is_below_submission_threshold(data, factor_vid, min_digits) {
+            debug!("{id}: Skipping {factor} because it's below the minimum size to submit");
             continue;
         }
         let mut dest_factors = all_vids
@@ -1085,12 +2029,19 @@ pub async fn find_and_submit_factors(
                     // if this edge exists, FactorDB already knows whether factor is a factor of dest
                     dest_vid != factor_vid && data.get_edge(factor_vid, dest_vid).is_none())
             .collect::<Vec<_>>();
-        dest_factors.shuffle(&mut rng());
+        dest_factors.shuffle(&mut shuffle_rng());
+        // Finishing off the smallest cofactor makes progress fastest, so try it first
+        if let Some(smallest_vid) = data.smallest_unresolved_cofactor()
+            && let Some(index) = dest_factors.iter().position(|&vid| vid == smallest_vid)
+        {
+            dest_factors.swap(0, index);
+        }
         if dest_factors.is_empty() {
             info!("{id}: Skipping {factor} because there are no more cofactors it can divide");
             continue;
         };
         let mut put_factor_back_into_queue = false;
+        let mut accepting_cofactors = 0usize;
         'per_cofactor: for cofactor_vid in dest_factors.into_iter() {
             if factor_vid == cofactor_vid {
                 error!("dest_factors included factor_vid");
@@ -1107,6 +2058,27 @@ pub async fn find_and_submit_factors(
                 data.propagate_divisibility(factor_vid, cofactor_vid, true);
                 continue;
             }
+            if data.is_known_non_factor(factor_vid, cofactor_vid) {
+                info!(
+                    "{id}: Skipping submission of {factor} to {cofactor} because it's ruled out (based on graph check)"
+                );
+                data.rule_out_divisibility(factor_vid, cofactor_vid);
+                if cofactor_vid == root_vid {
+                    continue 'graph_iter; // Skip put_factor_back_in_queue check
+                }
+                continue;
+            }
+            if is_withheld_as_known_to_factordb(
+                data,
+                factor_vid,
+                cofactor_vid,
+                options.contribute_only,
+            ) {
+                debug!(
+                    "{id}: Not submitting {factor} to {cofactor} because FactorDB already lists it"
+                );
+                continue;
+            }
             let factor_facts = data.facts(factor_vid)
                 .expect("{id}: Reached factors_known_to_factordb check for a number not entered in number_facts_map");
             match factor_facts.factors_known_to_factordb {
@@ -1135,11 +2107,21 @@ pub async fn find_and_submit_factors(
                 warn!(
                     "{id}: Found duplicate vertices: {factor_vid:?} and {cofactor_vid:?} are both {factor}"
                 );
-                let new_vids = merge_vertices(&mut data, http, factor_vid, cofactor_vid);
+                let new_vids = merge_vertices(data, http, factor_vid, cofactor_vid);
                 // Merge any new factor vids found during the merge
                 for vid in new_vids {
                     let new_subfactor = data.get_factor(vid);
-                    let _ = add_factor_node(&mut data, new_subfactor, None, http);
+                    let _ = add_factor_node(data, new_subfactor, None, http);
+                }
+                let merged_vid = data.resolve_vid(factor_vid);
+                if merged_vid != factor_vid {
+                    // factor_vid was the one merged away, so resubmit under its new vertex ID
+                    all_vids.remove(&factor_vid);
+                    root_vid = data.resolve_vid(root_vid);
+                    if merged_vid != root_vid && !factors_to_submit_in_graph.contains(&merged_vid) {
+                        factors_to_submit_in_graph.push_back(merged_vid);
+                    }
+                    continue 'graph_iter;
                 }
                 all_vids.remove(&cofactor_vid);
                 continue;
@@ -1172,7 +2154,7 @@ pub async fn find_and_submit_factors(
                 let mut by_status = known_factor_vids
                     .into_iter()
                     .filter_map(|known_factor_vid| {
-                        if data.get_edge(factor_vid, known_factor_vid) != Some(NotFactor) {
+                        if data.get_edge(factor_vid, known_factor_vid) == Some(NotFactor) {
                             None
                         } else if factor.may_be_proper_divisor_of(&data.get_factor(known_factor_vid)) && cofactor_upper_bound_log10
                             >= data.facts(known_factor_vid)
@@ -1198,7 +2180,7 @@ pub async fn find_and_submit_factors(
                     }
                     data.rule_out_divisibility(factor_vid, cofactor_vid);
                     let factors_to_submit_instead =
-                        add_factors_to_graph(http, &mut data, factor_vid).await;
+                        add_factors_to_graph(http, data, factor_vid, max_depth).await;
                     if !factors_to_submit_instead.is_empty() {
                         all_vids.extend(factors_to_submit_instead.iter().copied());
                         factors_to_submit_in_graph.extend(factors_to_submit_instead);
@@ -1272,7 +2254,7 @@ pub async fn find_and_submit_factors(
                 );
 
                 // Running add_factors_to_graph may yield an equivalent expression
-                let new_factors_of_cofactor = add_factors_to_graph(http, &mut data, cofactor_vid).await;
+                let new_factors_of_cofactor = add_factors_to_graph(http, data, cofactor_vid, max_depth).await;
                 if !new_factors_of_cofactor.is_empty() {
                     all_vids.extend(new_factors_of_cofactor.iter().copied());
                     factors_to_submit_in_graph
@@ -1282,64 +2264,71 @@ pub async fn find_and_submit_factors(
                 put_factor_back_into_queue = true;
                 break 'per_cofactor;
             }
+            if !passes_verification(id, &factor, &cofactor, options.verify_factors) {
+                data.rule_out_divisibility(factor_vid, cofactor_vid);
+                continue;
+            }
             let cofactor_specifier = data.as_specifier(cofactor_vid, http);
-            match http.try_report_factor(cofactor_specifier, &factor).await {
+            match report_factor(http, cofactor_specifier, &factor, &cofactor).await {
                 AlreadyFullyFactored => {
                     if cofactor_vid == root_vid {
                         warn!("{id}: Already fully factored");
-                        return true;
+                        return SubmissionOutcome::AlreadyFullyFactored;
                     }
-                    mark_fully_factored(cofactor_vid, &mut data);
+                    mark_fully_factored(cofactor_vid, data);
                     continue;
                 }
                 Accepted => {
                     data.propagate_divisibility(factor_vid, cofactor_vid, false);
-                    mark_stale(&mut data, cofactor_vid, http);
+                    mark_stale(data, cofactor_vid, http);
                     accepted_factors += 1;
                     iters_without_progress = 0;
+                    // A factor of a cofactor is also a factor of the root, and may have been the
+                    // last one FactorDB needed to finish factoring it
+                    let root_may_be_complete =
+                        cofactor_vid != root_vid && data.is_known_factor(cofactor_vid, root_vid);
+                    if root_may_be_complete {
+                        mark_stale(data, root_vid, http);
+                    }
                     // Move newly-accepted factor to the back of the list
                     if cofactor_vid == root_vid || cofactor_upper_bound_log10 >= 50000 {
                         let new_root_factors =
-                            add_factors_to_graph(http, &mut data, root_vid).await;
+                            add_factors_to_graph(http, data, root_vid, max_depth).await;
                         all_vids.extend(new_root_factors.iter().copied());
                         // skip put_factor_back_into_queue check
                         continue 'graph_iter;
                     }
+                    if root_may_be_complete {
+                        let new_root_factors =
+                            add_factors_to_graph(http, data, root_vid, max_depth).await;
+                        all_vids.extend(new_root_factors.iter().copied());
+                        root_vid = data.resolve_vid(root_vid);
+                        if data
+                            .facts(root_vid)
+                            .expect("{id}: Root not entered in number_facts_map after refresh")
+                            .is_known_fully_factored()
+                        {
+                            info!("{id}: Fully factored after submitting {factor} to {cofactor}");
+                            break 'graph_iter;
+                        }
+                    }
+                    // Keep reporting it to the remaining cofactors, since the divisibility we just
+                    // propagated lets us skip the ones that are multiples of this one
+                    accepting_cofactors += 1;
                     put_factor_back_into_queue = true;
-                    break 'per_cofactor;
                 }
                 DoesNotDivide => {
                     data.rule_out_divisibility(factor_vid, cofactor_vid);
-                    let subfactors = add_factors_to_graph(http, &mut data, factor_vid).await;
+                    let subfactors = add_factors_to_graph(http, data, factor_vid, max_depth).await;
                     if !subfactors.is_empty() {
                         all_vids.extend(subfactors.iter().copied());
                         factors_to_submit_in_graph.extend(subfactors);
                         dedup_and_shuffle(&mut factors_to_submit_in_graph);
-                    } else if let Some(ref root_denominator) = root_denominator {
-                        let facts = data.facts_mut(factor_vid);
-                        if !replace(&mut facts.checked_with_root_denominator, true)
-                            && root_denominator.may_be_proper_divisor_of(&factor)
-                        {
-                            let divided =
-                                div_exact(&factor, root_denominator).unwrap_or_else(|| {
-                                    simplify_divide(
-                                        &factor,
-                                        root_denominator_terms.as_ref().unwrap(),
-                                    )
-                                });
-                            if divided.may_be_proper_divisor_of(&root_factor) {
-                                let (divided_vid, added) =
-                                    add_factor_node(&mut data, divided, None, http);
-                                if added {
-                                    all_vids.insert(divided_vid);
-                                    factors_to_submit_in_graph.push_back(divided_vid);
-                                    // Don't apply this recursively, except when divided was already in
-                                    // the graph for another reason
-                                    data.facts_mut(divided_vid).checked_with_root_denominator =
-                                        true;
-                                }
-                            }
-                        }
+                    } else if let Some(divided_vid) =
+                        data.divide_out_known_factors(factor_vid, cofactor_vid, http)
+                    {
+                        all_vids.insert(divided_vid);
+                        factors_to_submit_in_graph.push_back(divided_vid);
                     }
                     if cofactor_vid == root_vid {
                         continue 'graph_iter; // Skip put_factor_back_into_queue check for factors that don't divide the root
@@ -1357,7 +2346,7 @@ pub async fn find_and_submit_factors(
                 OtherError => {
                     put_factor_back_into_queue = true;
                     let new_cofactor_factors =
-                        add_factors_to_graph(http, &mut data, cofactor_vid).await;
+                        add_factors_to_graph(http, data, cofactor_vid, max_depth).await;
                     if !new_cofactor_factors.is_empty() {
                         all_vids.extend(new_cofactor_factors.iter().copied());
                         iters_without_progress = 0;
@@ -1365,6 +2354,9 @@ pub async fn find_and_submit_factors(
                 }
             }
         }
+        if accepting_cofactors > 1 {
+            info!("{id}: {factor} was accepted as a factor of {accepting_cofactors} cofactors");
+        }
         if put_factor_back_into_queue && !factors_to_submit_in_graph.contains(&factor_vid) {
             factors_to_submit_in_graph.push_back(factor_vid);
         }
@@ -1379,7 +2371,10 @@ pub async fn find_and_submit_factors(
             continue;
         }
         let factor = factor.to_unelided_string();
-        if data.is_known_factor(factor_vid, root_vid) {
+        if data.is_known_factor(factor_vid, root_vid)
+            || (!submit_small_factors
+                && is_below_submission_threshold(data, factor_vid, min_digits))
+        {
             continue;
         }
         match FAILED_U_SUBMISSIONS_OUT
@@ -1396,7 +2391,22 @@ pub async fn find_and_submit_factors(
             ),
         }
     }
-    accepted_factors > 0
+    // Handy for aliquot and amicable-number work, which needs every divisor
+    if data
+        .facts(root_vid)
+        .is_some_and(NumberFacts::is_known_fully_factored)
+        && let Some(count) = data.divisor_count(root_vid)
+    {
+        info!("{id}: Fully factored, with {count} divisors");
+        // Listing them can mean building tens of thousands of Factors, so only when they're logged
+        if log_enabled!(Level::Debug) {
+            let divisors = data.all_divisors(root_vid);
+            if !divisors.is_empty() {
+                debug!("{id}: Divisors: {}", divisors.iter().join(", "));
+            }
+        }
+    }
+    SubmissionOutcome::from_accepted_count(accepted_factors)
 }
 
 #[inline(always)]
@@ -1436,7 +2446,7 @@ fn mark_fully_factored_internal(
     facts.checked_for_listed_algebraic = true;
     facts.checked_in_factor_finder = true;
     facts.expression_form_checked_in_factor_finder = true;
-    facts.checked_with_root_denominator = true;
+    facts.checked_for_known_common_factors = true;
     let no_other_factors = if let UpToDate(factors) = &facts.factors_known_to_factordb {
         if factors.len() == 1 {
             facts.last_known_status = Some(Prime);
@@ -1483,12 +2493,87 @@ fn mark_fully_factored_internal(
     }
 }
 
+/// Runs [find_unique_factors] on the [factoring_pool], and gives up on it if it takes longer than
+/// `timeout` or shutdown begins, so that a pathological expression can't stall the task submitting
+/// factors. The abandoned search still runs to completion in the background, and will populate
+/// the cache.
+async fn find_unique_factors_with_timeout(
+    factor: &Factor,
+    timeout: Duration,
+) -> Option<Box<[Factor]>> {
+    let owned_factor = factor.clone();
+    match factoring_pool()
+        .run(move || find_unique_factors(&owned_factor), timeout)
+        .await
+    {
+        JobOutcome::Done(found_factors) => Some(found_factors),
+        JobOutcome::Failed(e) => {
+            error!("Factor finder failed for {factor}: {e}");
+            None
+        }
+        JobOutcome::TimedOut => {
+            warn!("Factor finder timed out after {timeout:?} for {factor}; skipping it");
+            None
+        }
+        JobOutcome::ShutDown => {
+            warn!("Abandoning factor finder for {factor} because of shutdown");
+            None
+        }
+    }
+}
+
+/// Replaces the elided form of the number at `vid` with its full digits, if we know its ID and
+/// FactorDB will tell us them, so that it can be submitted without waiting for an expression form.
+/// Returns true if the vertex now has a form that can be submitted.
+#[framed]
+async fn fetch_unelided_digits(
+    http: &impl FactorDbClient,
+    data: &mut FactorData,
+    vid: VertexId,
+) -> bool {
+    let Some(entry_id) = data.facts(vid).and_then(|facts| facts.entry_id) else {
+        return false;
+    };
+    let ProcessedStatusApiResponse {
+        status, factors, ..
+    } = http.known_factors_as_digits(Id(entry_id), true, true).await;
+    // For a fully or partly factored number, the factors don't show their exponents, so a single
+    // factor may be the base of a prime power rather than the number itself
+    if matches!(status, Some(FullyFactored | PartlyFactoredComposite)) {
+        return false;
+    }
+    let [ref digits] = *factors else {
+        return false;
+    };
+    if digits.is_elided() {
+        return false;
+    }
+    info!(
+        "{entry_id}: Fetched the missing digits of {}",
+        data.get_factor(vid)
+    );
+    data.merge_equivalent_expressions(vid, digits.clone(), http, false);
+    let vid = data.resolve_vid(vid);
+    !data.get_factor(vid).is_elided()
+}
+
 #[framed]
 async fn add_factors_to_graph(
     http: &impl FactorDbClientReadIdsAndExprs,
     data: &mut FactorData,
     factor_vid: VertexId,
+    max_depth: usize,
 ) -> Box<[VertexId]> {
+    let depth = data.decomposition_depth(factor_vid);
+    // Kept, so that finding this number again further down can't push it past the limit
+    data.record_decomposition_depth(factor_vid, depth);
+    if depth >= max_depth {
+        warn!(
+            "Not decomposing {}, since it's already {depth} levels below the root",
+            data.get_factor(factor_vid)
+        );
+        return Box::new([]);
+    }
     let (mut id, needs_update) = {
         let facts = data.facts(factor_vid).expect(
             "add_factors_to_graph called on a number that's not entered in number_facts_map",
@@ -1545,7 +2630,8 @@ async fn add_factors_to_graph(
             }
         }
         if let Some(id) = id {
-            data.vertex_id_by_entry_id.insert(id, factor_vid);
+            let (_, merged_vids) = data.merge_by_entry_id(factor_vid, id, http);
+            added.extend(merged_vids);
         }
     }
 
@@ -1574,7 +2660,7 @@ async fn add_factors_to_graph(
                 facts.checked_for_listed_algebraic = true;
                 let algebraic_factors = http.read_ids_and_exprs(&listed_algebraic);
                 for (subfactor_entry_id, factor_digits_or_expr) in algebraic_factors {
-                    let subfactor = Factor::from(factor_digits_or_expr);
+                    let subfactor = Factor::parse(factor_digits_or_expr).await;
                     let (subfactor_vid, is_new) =
                         add_factor_node(data, subfactor, Some(subfactor_entry_id), http);
                     if is_new {
@@ -1590,7 +2676,16 @@ async fn add_factors_to_graph(
     // Next, check if factor_finder can find factors
     let facts = data.facts_mut(factor_vid);
     if !replace(&mut facts.checked_in_factor_finder, true) {
-        added.extend(data.add_from_factor_finder(&factor, http));
+        let factoring_start = Instant::now();
+        let found_factors = find_unique_factors_with_timeout(
+            &factor,
+            Duration::from_secs(FACTOR_FINDER_TIMEOUT_SECS.load(Acquire)),
+        )
+        .await;
+        data.factoring_time += factoring_start.elapsed();
+        if let Some(found_factors) = found_factors {
+            added.extend(data.add_found_factors(found_factors, http));
+        }
     }
     let facts = data.facts_mut(factor_vid);
     if let Some(entry_id) = facts.entry_id
@@ -1605,6 +2700,9 @@ async fn add_factors_to_graph(
         }
     }
 
+    for &added_vid in &added {
+        data.record_decomposition_depth(added_vid, depth + 1);
+    }
     added.into_iter().collect()
 }
 
@@ -1622,7 +2720,10 @@ pub mod tests {
     use crate::ReportFactorResult;
     use crate::algebraic::Factor;
     use crate::graph::{EntryId, NumericFactor};
-    use crate::graph::{FactorData, add_factor_node, find_and_submit_factors};
+    use crate::graph::{
+        FactorData, SubmissionOptions, SubmissionOutcome, add_factor_node, find_and_submit_factors,
+        merge_vertices,
+    };
     use crate::net::NumberStatus::Unknown;
     use crate::net::{
         FactorDbClientReadIdsAndExprs, MockFactorDbClient, ProcessedStatusApiResponse,
@@ -1776,6 +2877,7 @@ pub mod tests {
                 11_000_000_004_420_33401,
                 format!("I({})", 2 * 3 * 5 * 7 * 11 * 13 * 17 * 19).into(),
                 false,
+                SubmissionOptions::default(),
             )
             .await
         });
@@ -1825,6 +2927,12 @@ pub mod tests {
         let (vid2, added2) = add_factor_node(&mut data, factor, None, &http);
         assert!(!added2);
         assert_eq!(vid1, vid2);
+
+        // Trivially equal forms share a vertex too
+        let (vid3, added3) = add_factor_node(&mut data, Factor::from("(2^32+1)^1"), None, &http);
+        assert!(!added3);
+        assert_eq!(vid1, vid3);
+        assert_eq!(data.divisibility_graph.node_count(), 1);
     }
 
     #[test]
@@ -1877,86 +2985,557 @@ pub mod tests {
     }
 
     #[test]
-    fn test_propagate_divisibility_transitive() {
+    fn test_merge_vertices_synonym_depth() {
+        const VERTICES: usize = 256;
         let mut data = FactorData::default();
         let mut http = MockFactorDbClient::new();
         http.expect_cached_factors().return_const(None);
 
-        let (a, _) = add_factor_node(&mut data, Factor::from("a"), None, &http);
-        let (b, _) = add_factor_node(&mut data, Factor::from("b"), None, &http);
-        let (c, _) = add_factor_node(&mut data, Factor::from("c"), None, &http);
+        let vids: Vec<_> = (0..VERTICES)
+            .map(|i| {
+                add_factor_node(
+                    &mut data,
+                    Factor::from(format!("a{i}").as_str()),
+                    None,
+                    &http,
+                )
+                .0
+            })
+            .collect();
+        // Always merging the existing group into the newest vertex would build a linear chain
+        // without union by size
+        for pair in vids.windows(2) {
+            merge_vertices(&mut data, &http, pair[1], pair[0]);
+        }
+        let max_depth = data
+            .deleted_synonyms
+            .keys()
+            .map(|&vid| {
+                let mut depth = 0;
+                let mut vid = vid;
+                while let Some(&synonym) = data.deleted_synonyms.get(&vid) {
+                    vid = synonym;
+                    depth += 1;
+                }
+                depth
+            })
+            .max()
+            .unwrap();
+        assert!(max_depth <= VERTICES.ilog2() as usize, "{max_depth}");
+        let root = data.resolve_vid(vids[0]);
+        assert!(vids.iter().all(|&vid| data.resolve_vid(vid) == root));
+        assert_eq!(data.divisibility_graph.node_count(), 1);
+    }
 
-        // a divides b
-        data.propagate_divisibility(a, b, false);
-        // b divides c
-        data.propagate_divisibility(b, c, false);
+    #[test]
+    fn test_merge_by_entry_id() {
+        use crate::graph::Divisibility::Direct;
 
-        // Should know a divides c transitively
-        assert!(data.is_known_factor(a, c));
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
 
-        // And direct ones
-        assert!(data.is_known_factor(a, b));
-        assert!(data.is_known_factor(b, c));
+        // Two forms of the same number, added before FactorDB told us either one's ID
+        let (first, _) = add_factor_node(&mut data, Factor::from("a"), Some(1234), &http);
+        let (second, _) = add_factor_node(&mut data, Factor::from("b"), None, &http);
+        let (factor, _) = add_factor_node(&mut data, Factor::from("c"), None, &http);
+        let (multiple, _) = add_factor_node(&mut data, Factor::from("d"), None, &http);
+        data.propagate_divisibility(factor, second, false);
+        data.propagate_divisibility(first, multiple, false);
+        assert_ne!(data.resolve_vid(first), data.resolve_vid(second));
+
+        let (merged, _) = data.merge_by_entry_id(second, 1234, &http);
+        assert_eq!(data.resolve_vid(first), merged);
+        assert_eq!(data.resolve_vid(second), merged);
+        assert_eq!(data.vid_for_entry_id(1234), Some(merged));
+        assert_eq!(data.facts(merged).unwrap().entry_id, Some(1234));
+        // Each form's relations now apply to both, and propagate through the merged vertex
+        assert_eq!(data.get_edge(factor, merged), Some(Direct));
+        assert!(data.is_known_factor(first, multiple));
+        assert!(data.is_known_factor(factor, multiple));
+
+        // A vertex that already has a different ID isn't merged
+        let (other, _) = add_factor_node(&mut data, Factor::from("e"), Some(5678), &http);
+        assert_eq!(data.merge_by_entry_id(other, 1234, &http), (other, vec![]));
+        assert_eq!(data.facts(other).unwrap().entry_id, Some(5678));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_find_unique_factors_timeout() {
+        use crate::graph::find_unique_factors_with_timeout;
+        use std::iter::repeat_n;
+        use std::time::{Duration, Instant};
+
+        let expr = Factor::from(repeat_n("(2^11+1)", 1 << 16).join("*").as_str());
+        let start = Instant::now();
+        assert!(
+            find_unique_factors_with_timeout(&expr, Duration::from_millis(1))
+                .await
+                .is_none()
+        );
+        assert!(start.elapsed() < Duration::from_secs(5));
     }
 
     #[test]
-    fn test_rule_out_divisibility_propagation() {
+    fn test_numerically_equal_forms_share_vertex() {
         let mut data = FactorData::default();
         let mut http = MockFactorDbClient::new();
         http.expect_cached_factors().return_const(None);
 
-        let (a, _) = add_factor_node(&mut data, Factor::from("a"), None, &http);
-        let (b, _) = add_factor_node(&mut data, Factor::from("b"), None, &http);
-        let (c, _) = add_factor_node(&mut data, Factor::from("c"), None, &http);
+        let (vid, added) = add_factor_node(&mut data, Factor::from("(2^9+1)^2"), None, &http);
+        assert!(added);
+        for form in ["513^2", "263169"] {
+            let (form_vid, added) = add_factor_node(&mut data, Factor::from(form), None, &http);
+            assert!(!added);
+            assert_eq!(form_vid, vid);
+        }
+        assert_eq!(data.get_factor(vid), Factor::Numeric(263169));
+        assert_eq!(data.divisibility_graph.node_count(), 1);
+    }
 
-        // c divides b (c is a factor of b)
-        data.propagate_divisibility(c, b, false);
+    #[test]
+    fn test_pending_elided_factor_requeued() {
+        use crate::graph::next_factor_to_submit;
+        use std::collections::{BTreeSet, VecDeque};
 
-        // Rule out a divides b (a is NOT a factor of b)
-        data.rule_out_divisibility(a, b);
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
 
-        // If A !| B. And C | B. Does A !| C? Not necessarily. 2 !| 9, 3 | 9. 2 !| 3.
-        // But if A | C and C | B, then A | B.
-        // So if A !| B, and C | B, then A cannot divide C?
-        // If A | C, then since C | B -> A | B. But A !| B. Contradiction.
-        // So yes, A !| C.
+        let (elided, _) = add_factor_node(&mut data, Factor::from("1234...5678"), None, &http);
+        let mut pending_elided = BTreeSet::from([elided]);
+        let mut queue = VecDeque::new();
+        assert_eq!(
+            next_factor_to_submit(&mut data, &mut pending_elided, &mut queue),
+            None
+        );
+        assert!(pending_elided.contains(&elided));
 
-        use crate::graph::Divisibility::NotFactor;
-        assert_eq!(data.get_edge(a, c), Some(NotFactor));
+        let expression = Factor::from("2^1000+1");
+        data.merge_equivalent_expressions(elided, expression.clone(), &http, false);
+        assert_eq!(
+            next_factor_to_submit(&mut data, &mut pending_elided, &mut queue),
+            Some(elided)
+        );
+        assert!(pending_elided.is_empty());
+        assert_eq!(data.get_factor(elided), expression);
     }
 
     #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
-    pub async fn test_huge_u_memory_usage() {
-        const ID: EntryId = 1100000005875321487;
-        const EXPR: &str = "(10^200000-1)/9-10^58838";
+    async fn test_fetch_unelided_digits() {
+        use crate::NumberSpecifier::Id;
+        use crate::graph::fetch_unelided_digits;
+        use crate::net::NumberStatus::{FullyFactored, UnfactoredComposite};
 
-        const LISTED_ALGEBRAIC_FACTORS_URL: &str =
-            formatcp!("https://factordb.com/frame_moreinfo.php?id={ID}");
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let digits = Factor::from("123456789012345678901234567890123456789012345678901234567890");
+        // FF square, whose only listed factor is the prime it's a power of
+        http.expect_known_factors_as_digits()
+            .withf(|id, _, _| *id == Id(5678))
+            .times(1)
+            .return_const(ProcessedStatusApiResponse {
+                status: Some(FullyFactored),
+                factors: Box::new([Factor::from("1000000000000000000000000000057")]),
+                id: Some(5678),
+            });
+        http.expect_known_factors_as_digits()
+            .withf(|id, include_ff, get_digits_as_fallback| {
+                *id == Id(1234) && *include_ff && *get_digits_as_fallback
+            })
+            .times(1)
+            .return_const(ProcessedStatusApiResponse {
+                status: Some(UnfactoredComposite),
+                factors: Box::new([digits.clone()]),
+                id: Some(1234),
+            });
 
-        #[allow(non_local_definitions)]
-        impl FactorDbClientReadIdsAndExprs for MockFactorDbClient {
-            fn read_ids_and_exprs<'a>(
-                &self,
-                _haystack: &'a str,
-            ) -> impl Iterator<Item = (EntryId, &'a str)> {
-                once((ID, &*String::from(EXPR).leak()))
-            }
-        }
+        // Without an ID, there's nothing to look it up by
+        let (no_id, _) = add_factor_node(&mut data, Factor::from("9876...4321"), None, &http);
+        assert!(!fetch_unelided_digits(&http, &mut data, no_id).await);
 
-        simple_log::console("info").unwrap();
+        let (elided, _) =
+            add_factor_node(&mut data, Factor::from("1234...7890"), Some(1234), &http);
+        assert!(fetch_unelided_digits(&http, &mut data, elided).await);
+        let elided = data.resolve_vid(elided);
+        assert_eq!(data.get_factor(elided), digits);
+        assert_eq!(data.facts(elided).unwrap().entry_id, Some(1234));
+
+        let (square, _) =
+            add_factor_node(&mut data, Factor::from("1000...3249"), Some(5678), &http);
+        assert!(!fetch_unelided_digits(&http, &mut data, square).await);
+        let square = data.resolve_vid(square);
+        assert_eq!(data.get_factor(square), Factor::from("1000...3249"));
+    }
+
+    #[test]
+    fn test_divide_out_known_factors() {
+        let mut data = FactorData::default();
         let mut http = MockFactorDbClient::new();
-        let mut sys = sysinfo::System::new_with_specifics(
-            RefreshKind::nothing().with_memory(MemoryRefreshKind::everything()),
+        http.expect_cached_factors().return_const(None);
+
+        let (root_vid, _) = add_factor_node(
+            &mut data,
+            Factor::from("(2^300+5)*(2^400+9)/(2^200+7)"),
+            Some(1),
+            &http,
+        );
+        let (factor_vid, _) =
+            add_factor_node(&mut data, Factor::from("(2^200+7)*(2^300+5)"), None, &http);
+        let divided_vid = data
+            .divide_out_known_factors(factor_vid, root_vid, &http)
+            .unwrap();
+        assert_eq!(data.get_factor(divided_vid), Factor::from("2^300+5"));
+        // Only done once per factor, and not applied to the quotient
+        assert_eq!(
+            data.divide_out_known_factors(factor_vid, root_vid, &http),
+            None
+        );
+        assert_eq!(
+            data.divide_out_known_factors(divided_vid, root_vid, &http),
+            None
         );
 
-        http.expect_known_factors_as_digits()
-            .returning(|_, _, _| ProcessedStatusApiResponse {
-                status: Some(Unknown),
-                factors: Box::new([Factor::from(
-                    &*repeat('1')
-                        .take(141_161)
-                        .chain(once('0'))
-                        .chain(repeat('1').take(58838))
+        // A known divisor that's known not to divide the target is also divided out
+        let (nonfactor_vid, _) = add_factor_node(&mut data, Factor::from("2^500+3"), None, &http);
+        let (product_vid, _) =
+            add_factor_node(&mut data, Factor::from("(2^500+3)*(2^400+9)"), None, &http);
+        data.propagate_divisibility(nonfactor_vid, product_vid, false);
+        data.rule_out_divisibility(nonfactor_vid, root_vid);
+        let divided_vid = data
+            .divide_out_known_factors(product_vid, root_vid, &http)
+            .unwrap();
+        assert_eq!(data.get_factor(divided_vid), Factor::from("2^400+9"));
+
+        // So is one that's only known to be a multiple of a non-factor
+        let (nonfactor_vid, _) = add_factor_node(&mut data, Factor::from("2^200+1"), None, &http);
+        let (multiple_vid, _) = add_factor_node(&mut data, Factor::from("2^1000+1"), None, &http);
+        let (product_vid, _) = add_factor_node(
+            &mut data,
+            Factor::from("(2^1000+1)*(2^450+11)"),
+            None,
+            &http,
+        );
+        data.rule_out_divisibility(nonfactor_vid, root_vid);
+        data.propagate_divisibility(multiple_vid, product_vid, false);
+        data.propagate_divisibility(nonfactor_vid, multiple_vid, false);
+        let divided_vid = data
+            .divide_out_known_factors(product_vid, root_vid, &http)
+            .unwrap();
+        assert_eq!(data.get_factor(divided_vid), Factor::from("2^450+11"));
+
+        // Only known non-factors are divided out when the target isn't a quotient, and the factor
+        // can still be checked against one that is
+        let (quotient_vid, _) = add_factor_node(
+            &mut data,
+            Factor::from("(2^310+5)*(2^410+9)/(2^210+7)"),
+            Some(2),
+            &http,
+        );
+        let (product_vid, _) = add_factor_node(
+            &mut data,
+            Factor::from("(2^310+5)*(2^410+9)"),
+            Some(3),
+            &http,
+        );
+        let (factor_vid, _) =
+            add_factor_node(&mut data, Factor::from("(2^210+7)*(2^410+9)"), None, &http);
+        assert_eq!(
+            data.divide_out_known_factors(factor_vid, product_vid, &http),
+            None
+        );
+        let (nonfactor_vid, _) = add_factor_node(&mut data, Factor::from("2^510+3"), None, &http);
+        let (other_product_vid, _) =
+            add_factor_node(&mut data, Factor::from("(2^510+3)*(2^310+5)"), None, &http);
+        data.propagate_divisibility(nonfactor_vid, other_product_vid, false);
+        data.rule_out_divisibility(nonfactor_vid, product_vid);
+        let divided_vid = data
+            .divide_out_known_factors(other_product_vid, product_vid, &http)
+            .unwrap();
+        assert_eq!(data.get_factor(divided_vid), Factor::from("2^310+5"));
+        let divided_vid = data
+            .divide_out_known_factors(factor_vid, quotient_vid, &http)
+            .unwrap();
+        assert_eq!(data.get_factor(divided_vid), Factor::from("2^410+9"));
+    }
+
+    #[test]
+    fn test_smallest_unresolved_cofactor() {
+        use crate::net::NumberStatus::{FullyFactored, Prime, UnfactoredComposite};
+
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        assert_eq!(data.smallest_unresolved_cofactor(), None);
+
+        let (root, _) = add_factor_node(&mut data, Factor::from("2^1000+7"), None, &http);
+        let (medium, _) = add_factor_node(&mut data, Factor::from("2^400+9"), None, &http);
+        let (small, _) = add_factor_node(&mut data, Factor::from("2^200+3"), None, &http);
+        data.facts_mut(small).last_known_status = Some(UnfactoredComposite);
+        let (prime, _) = add_factor_node(&mut data, Factor::from("2^127-1"), None, &http);
+        data.facts_mut(prime).last_known_status = Some(Prime);
+        let (fully_factored, _) = add_factor_node(&mut data, Factor::from("2^128+1"), None, &http);
+        data.facts_mut(fully_factored).last_known_status = Some(FullyFactored);
+        let _ = add_factor_node(&mut data, Factor::from("3*5*7"), None, &http);
+        let _ = add_factor_node(&mut data, Factor::one(), None, &http);
+        assert_eq!(data.smallest_unresolved_cofactor(), Some(small));
+
+        data.facts_mut(small).last_known_status = Some(FullyFactored);
+        assert_eq!(data.smallest_unresolved_cofactor(), Some(medium));
+
+        data.facts_mut(medium).last_known_status = Some(FullyFactored);
+        assert_eq!(data.smallest_unresolved_cofactor(), Some(root));
+    }
+
+    #[test]
+    fn test_compact() {
+        use crate::graph::FactorsKnownToFactorDb::UpToDate;
+        use crate::net::NumberStatus::Prime;
+
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+
+        let (root, _) = add_factor_node(&mut data, Factor::from("r"), None, &http);
+        let primes = [2, 3, 5, 7].map(|p| {
+            let (vid, _) = add_factor_node(&mut data, Factor::Numeric(p), None, &http);
+            data.propagate_divisibility(vid, root, false);
+            let facts = data.facts_mut(vid);
+            facts.last_known_status = Some(Prime);
+            facts.factors_known_to_factordb = UpToDate(vec![vid]);
+            facts.checked_for_listed_algebraic = true;
+            facts.checked_in_factor_finder = true;
+            facts.expression_form_checked_in_factor_finder = true;
+            vid
+        });
+        let (unresolved, _) = add_factor_node(&mut data, Factor::from("q"), None, &http);
+        data.propagate_divisibility(unresolved, root, false);
+        // 5 could still be submitted to q, since it isn't known whether it divides q
+        for &prime in &primes[..2] {
+            data.rule_out_divisibility(prime, unresolved);
+        }
+
+        assert!(data.compact(10, root, []).is_empty());
+        let evicted = data.compact(3, root, [primes[3]]);
+        assert_eq!(evicted, primes[..2]);
+        assert_eq!(data.divisibility_graph.node_count(), 4);
+        assert!(data.vid_for_expr(&Factor::Numeric(2)).is_none());
+        assert_eq!(data.vid_for_expr(&Factor::Numeric(5)), Some(primes[2]));
+        assert_eq!(data.vid_for_expr(&Factor::Numeric(7)), Some(primes[3]));
+        assert!(data.facts(unresolved).is_some());
+        assert!(data.is_known_factor(unresolved, root));
+        assert!(data.is_known_factor(primes[3], root));
+    }
+
+    #[test]
+    fn test_propagate_divisibility_transitive() {
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+
+        let (a, _) = add_factor_node(&mut data, Factor::from("a"), None, &http);
+        let (b, _) = add_factor_node(&mut data, Factor::from("b"), None, &http);
+        let (c, _) = add_factor_node(&mut data, Factor::from("c"), None, &http);
+
+        // a divides b
+        data.propagate_divisibility(a, b, false);
+        // b divides c
+        data.propagate_divisibility(b, c, false);
+
+        // Should know a divides c transitively
+        assert!(data.is_known_factor(a, c));
+
+        // And direct ones
+        assert!(data.is_known_factor(a, b));
+        assert!(data.is_known_factor(b, c));
+    }
+
+    #[test]
+    fn test_rule_out_divisibility_propagation() {
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+
+        let (a, _) = add_factor_node(&mut data, Factor::from("a"), None, &http);
+        let (b, _) = add_factor_node(&mut data, Factor::from("b"), None, &http);
+        let (c, _) = add_factor_node(&mut data, Factor::from("c"), None, &http);
+
+        // c divides b (c is a factor of b)
+        data.propagate_divisibility(c, b, false);
+
+        // Rule out a divides b (a is NOT a factor of b)
+        data.rule_out_divisibility(a, b);
+
+        // If A !| B. And C | B. Does A !| C? Not necessarily. 2 !| 9, 3 | 9. 2 !| 3.
+        // But if A | C and C | B, then A | B.
+        // So if A !| B, and C | B, then A cannot divide C?
+        // If A | C, then since C | B -> A | B. But A !| B. Contradiction.
+        // So yes, A !| C.
+
+        use crate::graph::Divisibility::NotFactor;
+        assert_eq!(data.get_edge(a, c), Some(NotFactor));
+    }
+
+    #[test]
+    fn test_known_non_factors() {
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+
+        let [target, a, b, c, d, e] = ["target", "a", "b", "c", "d", "e"]
+            .map(|expr| add_factor_node(&mut data, Factor::from(expr), None, &http).0);
+        data.propagate_divisibility(c, target, false);
+        data.rule_out_divisibility(a, target);
+        data.rule_out_divisibility(b, target);
+        // d is a multiple of b, so it can't divide the target either
+        data.propagate_divisibility(b, d, false);
+
+        let mut non_factors = data.known_non_factors(target);
+        non_factors.sort();
+        let mut expected = vec![a, b, d];
+        expected.sort();
+        assert_eq!(non_factors, expected);
+        // Ruled out by propagation: a doesn't divide the target, so it doesn't divide c; and since
+        // c divides the target, the target doesn't divide c
+        let mut non_factors = data.known_non_factors(c);
+        non_factors.sort();
+        let mut expected = vec![a, target];
+        expected.sort();
+        assert_eq!(non_factors, expected);
+        assert!(data.known_non_factors(e).is_empty());
+    }
+
+    #[test]
+    fn test_is_known_non_factor() {
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+
+        let [factor, divisor, dest, multiple, other] =
+            ["factor", "divisor", "dest", "multiple", "other"]
+                .map(|expr| add_factor_node(&mut data, Factor::from(expr), None, &http).0);
+        // Learned in this order, so the submission loop might not have propagated them yet
+        data.rule_out_divisibility(divisor, multiple);
+        data.propagate_divisibility(divisor, factor, false);
+        data.propagate_divisibility(dest, multiple, false);
+
+        // divisor doesn't divide multiple, so factor (a multiple of divisor) doesn't divide dest
+        // (a factor of multiple), and the loop won't submit it there
+        assert!(data.is_known_non_factor(factor, dest));
+        assert!(data.is_known_non_factor(divisor, dest));
+        assert!(data.is_known_non_factor(factor, multiple));
+        assert!(!data.is_known_factor(factor, dest));
+        // Nothing is known about other
+        assert!(!data.is_known_non_factor(factor, other));
+        assert!(!data.is_known_non_factor(other, dest));
+        // Divisibility doesn't rule anything out
+        assert!(!data.is_known_non_factor(dest, multiple));
+        assert!(!data.is_known_non_factor(divisor, factor));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_no_submission_ruled_out_transitively() {
+        use crate::NumberSpecifier;
+        use crate::NumberSpecifier::{Expression, Id};
+        use crate::graph::Divisibility::NotFactor;
+        use crate::graph::submit_factors_using_graph;
+        use crate::net::NumberStatus::UnfactoredComposite;
+
+        const ID: EntryId = 1;
+        const ROOT: &str = "(2^521+1)*(2^607+1)";
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        fn is_ruled_out(u_id: &NumberSpecifier, factor: &Factor) -> bool {
+            *factor == Factor::from("factor")
+                && matches!(u_id, Expression(dest) if **dest == Factor::from("cofactor"))
+        }
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_invalidate_cached_factors().return_const(());
+        http.expect_known_factors_as_digits()
+            .returning(|id, _, _| match id {
+                Id(ID) => ProcessedStatusApiResponse {
+                    status: Some(UnfactoredComposite),
+                    factors: Box::new([]),
+                    id: Some(ID),
+                },
+                _ => ProcessedStatusApiResponse {
+                    status: None,
+                    factors: Box::new([]),
+                    id: None,
+                },
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().withf(is_ruled_out).never();
+        http.expect_try_report_factor()
+            .withf(|u_id, factor| !is_ruled_out(u_id, factor))
+            .returning(|u_id, _| match u_id {
+                // The root has too many factors, so they have to go to the cofactors instead
+                Id(_) => ReportFactorResult::OtherError,
+                Expression(_) => ReportFactorResult::DoesNotDivide,
+            });
+
+        let mut data = FactorData::default();
+        let (root, _) = add_factor_node(&mut data, Factor::from(ROOT), Some(ID), &http);
+        let [factor, divisor, cofactor] = ["factor", "divisor", "cofactor"]
+            .map(|expr| add_factor_node(&mut data, Factor::from(expr), None, &http).0);
+        // divisor doesn't divide cofactor, so neither does factor, though no edge says so
+        data.propagate_divisibility(cofactor, root, false);
+        data.rule_out_divisibility(divisor, cofactor);
+        data.propagate_divisibility(divisor, factor, false);
+        submit_factors_using_graph(
+            &http,
+            &mut data,
+            ID,
+            Factor::from(ROOT),
+            false,
+            SubmissionOptions::default(),
+        )
+        .await;
+        let factor = data.resolve_vid(factor);
+        let cofactor = data.resolve_vid(cofactor);
+        assert_eq!(data.get_edge(factor, cofactor), Some(NotFactor));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    pub async fn test_huge_u_memory_usage() {
+        const ID: EntryId = 1100000005875321487;
+        const EXPR: &str = "(10^200000-1)/9-10^58838";
+
+        const LISTED_ALGEBRAIC_FACTORS_URL: &str =
+            formatcp!("https://factordb.com/frame_moreinfo.php?id={ID}");
+
+        #[allow(non_local_definitions)]
+        impl FactorDbClientReadIdsAndExprs for MockFactorDbClient {
+            fn read_ids_and_exprs<'a>(
+                &self,
+                _haystack: &'a str,
+            ) -> impl Iterator<Item = (EntryId, &'a str)> {
+                once((ID, &*String::from(EXPR).leak()))
+            }
+        }
+
+        simple_log::console("info").unwrap();
+        let mut http = MockFactorDbClient::new();
+        let mut sys = sysinfo::System::new_with_specifics(
+            RefreshKind::nothing().with_memory(MemoryRefreshKind::everything()),
+        );
+
+        http.expect_known_factors_as_digits()
+            .returning(|_, _, _| ProcessedStatusApiResponse {
+                status: Some(Unknown),
+                factors: Box::new([Factor::from(
+                    &*repeat('1')
+                        .take(141_161)
+                        .chain(once('0'))
+                        .chain(repeat('1').take(58838))
                         .collect::<String>(),
                 )]),
                 id: Some(ID),
@@ -1979,7 +3558,9 @@ pub mod tests {
         // ensure expr uses heap space
         let expr = Factor::from(EXPR);
         let mut reg = stats_alloc::Region::new(&GLOBAL);
-        black_box(find_and_submit_factors(&http, ID, expr, false).await);
+        black_box(
+            find_and_submit_factors(&http, ID, expr, false, SubmissionOptions::default()).await,
+        );
 
         log_stats(&mut reg, &mut sys, &mut None);
     }
@@ -2039,4 +3620,910 @@ pub mod tests {
         assert!(added);
         assert_eq!(data.get_factor(vid), fa);
     }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_no_submissions_after_root_fully_factored() {
+        use crate::NumberSpecifier::{Expression, Id};
+        use crate::net::NumberStatus::{FullyFactored, PartlyFactoredComposite};
+        use std::sync::Arc;
+        use std::sync::atomic::AtomicBool;
+        use std::sync::atomic::Ordering::SeqCst;
+
+        const ID: EntryId = 1;
+
+        let known_factor = Factor::from("2^1000+1");
+        let root_complete = Arc::new(AtomicBool::new(false));
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_known_factors_as_digits().returning({
+            let known_factor = known_factor.clone();
+            let root_complete = root_complete.clone();
+            move |id, include_ff, _| match id {
+                Id(ID) if root_complete.load(SeqCst) => ProcessedStatusApiResponse {
+                    status: Some(FullyFactored),
+                    factors: Box::new([]),
+                    id: Some(ID),
+                },
+                Id(ID) => ProcessedStatusApiResponse {
+                    status: Some(PartlyFactoredComposite),
+                    factors: if include_ff {
+                        Box::new([])
+                    } else {
+                        Box::new([known_factor.clone()])
+                    },
+                    id: Some(ID),
+                },
+                _ => ProcessedStatusApiResponse {
+                    status: None,
+                    factors: Box::new([]),
+                    id: None,
+                },
+            }
+        });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().returning({
+            let known_factor = known_factor.clone();
+            let root_complete = root_complete.clone();
+            move |u_id, factor| {
+                assert!(
+                    !root_complete.load(SeqCst),
+                    "Submitted {factor} after the root was fully factored"
+                );
+                match u_id {
+                    Id(ID) => ReportFactorResult::OtherError,
+                    Expression(ref cofactor) if **cofactor == known_factor => {
+                        // Pretend this was the last factor FactorDB needed
+                        root_complete.store(true, SeqCst);
+                        ReportFactorResult::Accepted
+                    }
+                    _ => ReportFactorResult::DoesNotDivide,
+                }
+            }
+        });
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        assert!(matches!(
+            find_and_submit_factors(
+                &http,
+                ID,
+                Factor::from("r"),
+                false,
+                SubmissionOptions::default()
+            )
+            .await,
+            SubmissionOutcome::Accepted(_)
+        ));
+        assert!(root_complete.load(SeqCst));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_factor_submitted_past_known_factors_not_ruled_out() {
+        use crate::NumberSpecifier::{Expression, Id};
+        use crate::algebraic::{divides, simplify};
+        use crate::net::NumberStatus::{PartlyFactoredComposite, UnfactoredComposite};
+
+        const ID: EntryId = 1;
+        // FactorDB lists these as factors of the root, and each as its own only known factor, but
+        // doesn't know that 3 divides them
+        const COFACTORS: [&str; 2] = ["2^521+1", "2^607+1"];
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_invalidate_cached_factors().return_const(());
+        http.expect_known_factors_as_digits()
+            .returning(|id, _, _| match id {
+                Id(ID) => ProcessedStatusApiResponse {
+                    status: Some(PartlyFactoredComposite),
+                    factors: COFACTORS.into_iter().map(Factor::from).collect(),
+                    id: Some(ID),
+                },
+                Expression(cofactor) if !matches!(*cofactor, Factor::Numeric(_)) => {
+                    ProcessedStatusApiResponse {
+                        status: Some(UnfactoredComposite),
+                        factors: Box::new([cofactor.into_owned()]),
+                        id: None,
+                    }
+                }
+                _ => ProcessedStatusApiResponse {
+                    status: None,
+                    factors: Box::new([]),
+                    id: None,
+                },
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().returning({
+            let reports = reports.clone();
+            move |u_id, factor| match u_id {
+                Id(_) => ReportFactorResult::OtherError,
+                Expression(ref cofactor) if divides(factor, cofactor) == Some(true) => {
+                    reports
+                        .lock()
+                        .unwrap()
+                        .push(((**cofactor).clone(), factor.clone()));
+                    ReportFactorResult::Accepted
+                }
+                Expression(_) => ReportFactorResult::DoesNotDivide,
+            }
+        });
+        find_and_submit_factors(
+            &http,
+            ID,
+            Factor::from("r"),
+            false,
+            SubmissionOptions::default(),
+        )
+        .await;
+
+        // Neither the root's known factors nor a cofactor's own entry rule 3 out, since neither has
+        // a NotFactor edge from 3
+        let reports = reports.lock().unwrap();
+        let three = Factor::Numeric(3);
+        for cofactor in COFACTORS {
+            let cofactor = simplify(&Factor::from(cofactor));
+            assert!(
+                reports.contains(&(cofactor.clone(), three.clone())),
+                "3 wasn't reported to {cofactor}"
+            );
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_shared_factor_reported_to_each_cofactor() {
+        use crate::NumberSpecifier::{Expression, Id};
+        use crate::algebraic::{divides, simplify};
+        use crate::net::NumberStatus::UnfactoredComposite;
+
+        const ID: EntryId = 1;
+        // 3 divides all of these, but FactorDB doesn't know any factors of them or of the root
+        const COFACTORS: [&str; 3] = ["2^521+1", "2^607+1", "2^1279+1"];
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        let reports = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_invalidate_cached_factors().return_const(());
+        http.expect_known_factors_as_digits()
+            .returning(|id, _, _| match id {
+                Id(ID) => ProcessedStatusApiResponse {
+                    status: Some(UnfactoredComposite),
+                    factors: Box::new([]),
+                    id: Some(ID),
+                },
+                _ => ProcessedStatusApiResponse {
+                    status: None,
+                    factors: Box::new([]),
+                    id: None,
+                },
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().returning({
+            let reports = reports.clone();
+            move |u_id, factor| match u_id {
+                // The root has too many factors, so they have to go to the cofactors instead
+                Id(_) => ReportFactorResult::OtherError,
+                Expression(ref cofactor) if divides(factor, cofactor) == Some(true) => {
+                    reports
+                        .lock()
+                        .unwrap()
+                        .push(((**cofactor).clone(), factor.clone()));
+                    ReportFactorResult::Accepted
+                }
+                Expression(_) => ReportFactorResult::DoesNotDivide,
+            }
+        });
+        find_and_submit_factors(
+            &http,
+            ID,
+            Factor::from("(2^521+1)*(2^607+1)*(2^1279+1)"),
+            false,
+            SubmissionOptions::default(),
+        )
+        .await;
+
+        let reports = reports.lock().unwrap();
+        let three = Factor::Numeric(3);
+        for cofactor in COFACTORS {
+            let cofactor = simplify(&Factor::from(cofactor));
+            assert_eq!(
+                reports
+                    .iter()
+                    .filter(|(dest, factor)| *dest == cofactor && *factor == three)
+                    .count(),
+                1,
+                "3 wasn't reported exactly once to {cofactor}"
+            );
+        }
+        // All in the same pass, rather than one cofactor per pass
+        let first = reports
+            .iter()
+            .position(|(_, factor)| *factor == three)
+            .unwrap();
+        assert!(
+            reports[first..first + COFACTORS.len()]
+                .iter()
+                .all(|(_, factor)| *factor == three)
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_find_and_submit_factors_outcomes() {
+        use crate::net::NumberStatus::{FullyFactored, UnfactoredComposite};
+
+        const ID: EntryId = 1;
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_known_factors_as_digits()
+            .returning(|_, _, _| ProcessedStatusApiResponse {
+                status: Some(FullyFactored),
+                factors: Box::new([]),
+                id: Some(ID),
+            });
+        http.expect_try_report_factor().never();
+        assert_eq!(
+            find_and_submit_factors(
+                &http,
+                ID,
+                Factor::from("r"),
+                false,
+                SubmissionOptions::default()
+            )
+            .await,
+            SubmissionOutcome::AlreadyFullyFactored
+        );
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_known_factors_as_digits()
+            .returning(|_, _, _| ProcessedStatusApiResponse {
+                status: Some(UnfactoredComposite),
+                factors: Box::new([]),
+                id: Some(ID),
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().never();
+        assert_eq!(
+            find_and_submit_factors(
+                &http,
+                ID,
+                Factor::from("r"),
+                false,
+                SubmissionOptions::default()
+            )
+            .await,
+            SubmissionOutcome::NoProgress
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_small_factors_prime_graph() {
+        use crate::graph::Divisibility::Direct;
+        use crate::graph::submit_factors_using_graph;
+        use crate::net::NumberStatus::{FullyFactored, PartlyFactoredComposite};
+
+        const ID: EntryId = 1;
+        let options = SubmissionOptions {
+            prime_with_small_factors: true,
+            ..SubmissionOptions::default()
+        };
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_small_factors()
+            .times(1)
+            .returning(|_| Some([3, 5].into()));
+        http.expect_cached_factors().return_const(None);
+        // Only add_factors_to_graph fetches the full factor list, not the up-front lookup
+        http.expect_known_factors_as_digits()
+            .withf(|_, include_ff, _| !*include_ff)
+            .never();
+        http.expect_known_factors_as_digits()
+            .withf(|_, include_ff, _| *include_ff)
+            .returning(|_, _, _| ProcessedStatusApiResponse {
+                status: Some(PartlyFactoredComposite),
+                factors: Box::new([Factor::Numeric(3), Factor::Numeric(5)]),
+                id: Some(ID),
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().never();
+        let mut data = FactorData::default();
+        submit_factors_using_graph(&http, &mut data, ID, Factor::from("r"), false, options).await;
+        let root = data.vid_for_entry_id(ID).unwrap();
+        for small_factor in [3, 5] {
+            let vid = data.vid_for_expr(&Factor::Numeric(small_factor)).unwrap();
+            assert_eq!(data.get_edge(vid, root), Some(Direct));
+        }
+
+        // No small factors, so the full fetch happens up front as before
+        let mut http = MockFactorDbClient::new();
+        http.expect_small_factors().returning(|_| Some([].into()));
+        http.expect_cached_factors().return_const(None);
+        http.expect_known_factors_as_digits()
+            .times(1..)
+            .returning(|_, _, _| ProcessedStatusApiResponse {
+                status: Some(FullyFactored),
+                factors: Box::new([]),
+                id: Some(ID),
+            });
+        assert_eq!(
+            submit_factors_using_graph(
+                &http,
+                &mut FactorData::default(),
+                ID,
+                Factor::from("r"),
+                false,
+                options
+            )
+            .await,
+            SubmissionOutcome::AlreadyFullyFactored
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_decomposition_depth_limit() {
+        use crate::NumberSpecifier::Expression;
+        use crate::graph::add_factors_to_graph;
+        use crate::net::NumberStatus::PartlyFactoredComposite;
+
+        const MAX_DEPTH: usize = 3;
+
+        // Every level of a nested quotient has another composite level inside it
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_known_factors_as_digits()
+            .times(MAX_DEPTH)
+            .returning(|id, _, _| {
+                let Expression(expr) = id else {
+                    panic!("Looked up an ID instead of a nested level");
+                };
+                let level: usize = expr.to_string()[1..].parse().unwrap();
+                ProcessedStatusApiResponse {
+                    status: Some(PartlyFactoredComposite),
+                    factors: Box::new([
+                        Factor::from(format!("n{}", level + 1)),
+                        Factor::from(format!("m{}", level + 1)),
+                    ]),
+                    id: None,
+                }
+            });
+        let mut data = FactorData::default();
+        let (root, _) = add_factor_node(&mut data, Factor::from("n0"), None, &http);
+        let mut vid = root;
+        for level in 0..MAX_DEPTH {
+            assert_eq!(data.decomposition_depth(vid), level);
+            let subfactors = add_factors_to_graph(&http, &mut data, vid, MAX_DEPTH).await;
+            assert_eq!(subfactors.len(), 2);
+            vid = data
+                .vid_for_expr(&Factor::from(format!("n{}", level + 1)))
+                .unwrap();
+        }
+        assert_eq!(data.decomposition_depth(vid), MAX_DEPTH);
+        assert!(
+            add_factors_to_graph(&http, &mut data, vid, MAX_DEPTH)
+                .await
+                .is_empty()
+        );
+        // Turning up again as a deeper level's factor doesn't move a decomposed number down
+        data.record_decomposition_depth(root, MAX_DEPTH);
+        assert_eq!(data.decomposition_depth(root), 0);
+    }
+
+    #[test]
+    fn test_all_divisors() {
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let mut data = FactorData::default();
+        let [twelve, two, three] =
+            [12, 2, 3].map(|n| add_factor_node(&mut data, Factor::Numeric(n), None, &http).0);
+        // Only 2 is known to divide 12 so far, so 3 is missing from the factorization
+        data.propagate_divisibility(two, twelve, false);
+        assert!(data.all_divisors(twelve).is_empty());
+        assert_eq!(data.divisor_count(twelve), None);
+
+        data.propagate_divisibility(three, twelve, false);
+        assert_eq!(data.divisor_count(twelve), Some(6));
+        let mut divisors = data.all_divisors(twelve);
+        assert_eq!(divisors.len(), 6);
+        divisors.sort();
+        assert_eq!(divisors, [1, 2, 3, 4, 6, 12].map(Factor::Numeric));
+        assert_eq!(
+            data.all_divisors(three),
+            [Factor::Numeric(1), Factor::Numeric(3)]
+        );
+    }
+
+    #[test]
+    fn test_all_divisors_with_unknown_multiplicity() {
+        use crate::net::NumberStatus::{FullyFactored, Prime};
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let mut data = FactorData::default();
+        let (prime, _) = add_factor_node(&mut data, Factor::from("2^521-1"), None, &http);
+        let (square, _) = add_factor_node(&mut data, Factor::from("(2^521-1)^2"), None, &http);
+        data.facts_mut(prime).last_known_status = Some(Prime);
+        data.facts_mut(square).last_known_status = Some(FullyFactored);
+        data.propagate_divisibility(prime, square, false);
+        // The prime is too large for its multiplicity to be checked, and small enough that it may
+        // divide more than once, so the divisors can't be listed exactly
+        assert!(data.all_divisors(square).is_empty());
+        assert_eq!(data.divisor_count(square), None);
+    }
+
+    #[test]
+    fn test_snapshot_resumes_graph() {
+        use crate::graph::Divisibility::{Direct, NotFactor};
+        use crate::snapshot::{GraphSnapshot, root_hash};
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let mut data = FactorData::default();
+        let (root, _) = add_factor_node(&mut data, Factor::Numeric(60), Some(1234), &http);
+        let [two, three, seven] =
+            [2, 3, 7].map(|n| add_factor_node(&mut data, Factor::Numeric(n), None, &http).0);
+        data.propagate_divisibility(two, root, false);
+        data.propagate_divisibility(three, root, false);
+        data.rule_out_divisibility(seven, root);
+        let edge_count = data.divisibility_graph.edge_count();
+
+        let dir = temp_dir().join(rng().next_u64().to_string());
+        let hash = root_hash(&Factor::Numeric(60));
+        data.snapshot(1234, hash).save(&dir, false);
+        let snapshot = GraphSnapshot::load(&dir, 1234, hash).unwrap();
+        assert!(GraphSnapshot::load(&dir, 5678, hash).is_none());
+        let (mut data, root) = FactorData::from_snapshot(snapshot).unwrap();
+        assert_eq!(data.divisibility_graph.node_count(), 4);
+        assert_eq!(data.divisibility_graph.edge_count(), edge_count);
+        assert_eq!(data.get_factor(root), Factor::Numeric(60));
+        let two = data.vid_for_expr(&Factor::Numeric(2)).unwrap();
+        let seven = data.vid_for_expr(&Factor::Numeric(7)).unwrap();
+        assert_eq!(data.get_edge(two, root), Some(Direct));
+        assert_eq!(data.get_edge(seven, root), Some(NotFactor));
+
+        // The reloaded graph picks up where the old one left off
+        let (five, _) = add_factor_node(&mut data, Factor::Numeric(5), None, &http);
+        data.propagate_divisibility(five, root, false);
+        assert_eq!(data.all_divisors(root).len(), 12);
+        GraphSnapshot::delete(&dir, 1234, hash);
+        assert!(GraphSnapshot::load(&dir, 1234, hash).is_none());
+    }
+
+    #[test]
+    fn test_snapshots_of_roots_sharing_an_id() {
+        use crate::snapshot::{GraphSnapshot, root_hash};
+        use std::collections::BTreeSet;
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let dir = temp_dir().join(rng().next_u64().to_string());
+        assert!(GraphSnapshot::saved_root_ids(&dir).is_empty());
+        let roots = [Factor::Numeric(60), Factor::Numeric(84)];
+        let hashes = roots.clone().map(|root| root_hash(&root));
+        assert_ne!(hashes[0], hashes[1]);
+        for (root, &hash) in roots.into_iter().zip(&hashes) {
+            let mut data = FactorData::default();
+            add_factor_node(&mut data, root, Some(1234), &http);
+            data.snapshot(1234, hash).save(&dir, hash == hashes[0]);
+        }
+        let mut data = FactorData::default();
+        add_factor_node(&mut data, Factor::Numeric(90), Some(5678), &http);
+        data.snapshot(5678, 0).save(&dir, false);
+        assert_eq!(
+            GraphSnapshot::saved_root_ids(&dir),
+            BTreeSet::from([1234, 5678])
+        );
+
+        // Each root of 1234 gets its own graph back, and finishing one leaves the other
+        for (n, &hash) in [60, 84].into_iter().zip(&hashes) {
+            let snapshot = GraphSnapshot::load(&dir, 1234, hash).unwrap();
+            let (mut data, root) = FactorData::from_snapshot(snapshot).unwrap();
+            assert_eq!(data.get_factor(root), Factor::Numeric(n));
+        }
+        GraphSnapshot::delete(&dir, 1234, hashes[0]);
+        assert!(GraphSnapshot::load(&dir, 1234, hashes[0]).is_none());
+        assert!(GraphSnapshot::load(&dir, 1234, hashes[1]).is_some());
+        GraphSnapshot::delete(&dir, 1234, hashes[1]);
+        GraphSnapshot::delete(&dir, 5678, 0);
+        assert!(GraphSnapshot::saved_root_ids(&dir).is_empty());
+    }
+
+    #[test]
+    fn test_compressed_snapshot_round_trip() {
+        use crate::snapshot::{GraphSnapshot, root_hash};
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let mut data = FactorData::default();
+        let (root, _) = add_factor_node(&mut data, Factor::Numeric(210), Some(1234), &http);
+        for n in [2, 3, 5, 7, 11] {
+            let (vid, _) = add_factor_node(&mut data, Factor::Numeric(n), None, &http);
+            if n == 11 {
+                data.rule_out_divisibility(vid, root);
+            } else {
+                data.propagate_divisibility(vid, root, false);
+            }
+        }
+        let hash = root_hash(&Factor::Numeric(210));
+        let snapshot = data.snapshot(1234, hash);
+        let json = serde_json::to_string(&snapshot).unwrap();
+
+        let dir = temp_dir().join(rng().next_u64().to_string());
+        snapshot.save(&dir, true);
+        let file_name = format!("1234-{hash:016x}.json");
+        assert!(dir.join(format!("{file_name}.gz")).exists());
+        assert!(!dir.join(&file_name).exists());
+        let reloaded = GraphSnapshot::load(&dir, 1234, hash).unwrap();
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+        let (reloaded_data, _) = FactorData::from_snapshot(reloaded).unwrap();
+        assert_eq!(
+            reloaded_data.divisibility_graph.edge_count(),
+            data.divisibility_graph.edge_count()
+        );
+
+        // Switching formats replaces the old snapshot rather than leaving it to be loaded instead
+        snapshot.save(&dir, false);
+        assert!(!dir.join(format!("{file_name}.gz")).exists());
+        let reloaded = GraphSnapshot::load(&dir, 1234, hash).unwrap();
+        assert_eq!(serde_json::to_string(&reloaded).unwrap(), json);
+        snapshot.save(&dir, true);
+        GraphSnapshot::delete(&dir, 1234, hash);
+        assert!(GraphSnapshot::load(&dir, 1234, hash).is_none());
+    }
+
+    #[test]
+    fn test_propagation_stats() {
+        use crate::graph::PropagationStats;
+
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let [a, b, c] = ["a", "b", "c"]
+            .map(|expr| add_factor_node(&mut data, Factor::from(expr), None, &http).0);
+        assert_eq!(data.propagation_stats, PropagationStats::default());
+
+        // a | b, and so b !| a
+        data.propagate_divisibility(a, b, false);
+        assert_eq!(
+            data.propagation_stats,
+            PropagationStats {
+                items_processed: 2,
+                max_worklist_len: 1,
+                edges_added: 2,
+                edges_upgraded: 0,
+            }
+        );
+
+        // b | c, and so a | c (transitively), c !| a and c !| b
+        data.propagate_divisibility(b, c, false);
+        let stats = data.propagation_stats;
+        assert_eq!(stats.edges_added, 6);
+        assert_eq!(stats.edges_added, data.divisibility_graph.edge_count());
+        assert_eq!(stats.max_worklist_len, 3);
+        assert_eq!(stats.items_processed, 6);
+        assert_eq!(stats.edges_upgraded, 0);
+
+        // Learning that a | c directly upgrades the transitive edge
+        data.propagate_divisibility(a, c, false);
+        assert_eq!(data.propagation_stats.edges_upgraded, 1);
+        assert_eq!(data.propagation_stats.edges_added, 6);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_report_factor_with_known_exponent() {
+        use crate::NumberSpecifier::Id;
+        use crate::algebraic::Factor::Numeric;
+        use crate::graph::report_factor;
+
+        let root = Factor::from("3^5*7*(2^127-1)");
+        let mut http = MockFactorDbClient::new();
+        http.expect_report_factor_with_exponent()
+            .withf(|u_id, factor, exponent| {
+                *u_id == Id(1) && *factor == Numeric(3) && *exponent == 5
+            })
+            .times(1)
+            .return_const(ReportFactorResult::Accepted);
+        http.expect_try_report_factor()
+            .withf(|u_id, factor| *u_id == Id(1) && *factor == Numeric(7))
+            .times(1)
+            .return_const(ReportFactorResult::Accepted);
+        assert_eq!(
+            report_factor(&http, Id(1), &Numeric(3), &root).await,
+            ReportFactorResult::Accepted
+        );
+        assert_eq!(
+            report_factor(&http, Id(1), &Numeric(7), &root).await,
+            ReportFactorResult::Accepted
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_find_and_submit_factors_timing() {
+        use crate::graph::find_and_submit_factors_timed;
+        use crate::net::NumberStatus::UnfactoredComposite;
+
+        const ID: EntryId = 1;
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_known_factors_as_digits()
+            .returning(|_, _, _| ProcessedStatusApiResponse {
+                status: Some(UnfactoredComposite),
+                factors: Box::new([]),
+                id: Some(ID),
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().never();
+        // 2^127-1 is prime, so the factor finder runs but nothing gets submitted
+        let (outcome, timing) = find_and_submit_factors_timed(
+            &http,
+            ID,
+            Factor::from("2^127-1"),
+            false,
+            SubmissionOptions::default(),
+        )
+        .await;
+        assert_eq!(outcome, SubmissionOutcome::NoProgress);
+        assert!(timing.factoring > Duration::ZERO);
+        assert!(timing.total >= timing.factoring);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_min_submitted_factor_digits() {
+        use crate::NumberSpecifier::Id;
+        use crate::net::NumberStatus::UnfactoredComposite;
+
+        const ID: EntryId = 1;
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        async fn submitted_factors(known_factor: &'static str) -> Vec<Factor> {
+            let submitted = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+            let mut http = MockFactorDbClient::new();
+            http.expect_cached_factors().return_const(None);
+            http.expect_invalidate_cached_factors().return_const(());
+            http.expect_known_factors_as_digits()
+                .returning(move |id, _, _| match id {
+                    Id(ID) => ProcessedStatusApiResponse {
+                        status: Some(UnfactoredComposite),
+                        factors: Box::new([Factor::from(known_factor)]),
+                        id: Some(ID),
+                    },
+                    _ => ProcessedStatusApiResponse {
+                        status: None,
+                        factors: Box::new([]),
+                        id: None,
+                    },
+                });
+            http.expect_try_get_and_decode().returning(|_| None);
+            http.expect_try_get_expression_form().returning(|_| None);
+            http.expect_try_report_factor().returning({
+                let submitted = submitted.clone();
+                move |_, factor| {
+                    submitted.lock().unwrap().push(factor.clone());
+                    ReportFactorResult::Accepted
+                }
+            });
+            let options = SubmissionOptions {
+                min_submitted_factor_digits: 2,
+                ..SubmissionOptions::default()
+            };
+            find_and_submit_factors(&http, ID, Factor::from("r"), false, options).await;
+            submitted.lock().unwrap().clone()
+        }
+
+        // 2 is held back because a larger factor was accepted
+        let submitted = submitted_factors("2*(3^1000+2)").await;
+        assert!(!submitted.is_empty());
+        assert!(!submitted.contains(&Factor::Numeric(2)));
+        assert!(
+            submitted
+                .iter()
+                .all(|factor| factor.to_unelided_string().len() >= 2)
+        );
+
+        // 2 is submitted when small factors are the only progress available
+        let submitted = submitted_factors("2*3*5*7").await;
+        assert!(submitted.contains(&Factor::Numeric(2)));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_contribute_only_withholds_factordb_factors() {
+        use crate::NumberSpecifier::{Expression, Id};
+        use crate::net::NumberStatus::{FullyFactored, UnfactoredComposite};
+
+        const ID: EntryId = 1;
+        const RSA_100: &str = "1522605027922533360535618378132637429718068114961380688657908494580122963258952897654000350692006139";
+        const P: &str = "37975227936943673922808872755445627854565536638199";
+        const Q: &str = "40094690950920881030683735292761468389214899724061";
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        let submitted = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_invalidate_cached_factors().return_const(());
+        http.expect_known_factors_as_digits()
+            .returning(|id, _, _| match id {
+                Id(ID) => ProcessedStatusApiResponse {
+                    status: Some(UnfactoredComposite),
+                    factors: Box::new([Factor::from(format!("{RSA_100}*(2^1000+7)").as_str())]),
+                    id: Some(ID),
+                },
+                // FactorDB already knows the factors of the part that we can't factor ourselves
+                Expression(expr) if *expr == Factor::from(RSA_100) => ProcessedStatusApiResponse {
+                    status: Some(FullyFactored),
+                    factors: Box::new([Factor::from(P), Factor::from(Q)]),
+                    id: None,
+                },
+                _ => ProcessedStatusApiResponse {
+                    status: None,
+                    factors: Box::new([]),
+                    id: None,
+                },
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().returning({
+            let submitted = submitted.clone();
+            move |u_id, factor| {
+                // None stands for the root
+                let dest = match u_id {
+                    Id(_) => None,
+                    Expression(ref dest) => Some((**dest).clone()),
+                };
+                let accepted = dest.is_none() && *factor != Factor::from(RSA_100);
+                submitted.lock().unwrap().push((dest, factor.clone()));
+                if accepted {
+                    ReportFactorResult::Accepted
+                } else {
+                    ReportFactorResult::DoesNotDivide
+                }
+            }
+        });
+        let options = SubmissionOptions {
+            contribute_only: true,
+            ..SubmissionOptions::default()
+        };
+        find_and_submit_factors(&http, ID, Factor::from("r"), false, options).await;
+
+        let submitted = submitted.lock().unwrap();
+        assert!(submitted.contains(&(None, Factor::from(RSA_100))));
+        // FactorDB has never been told that P and Q divide the root
+        assert!(submitted.contains(&(None, Factor::from(P))));
+        assert!(submitted.contains(&(None, Factor::from(Q))));
+        // But it lists them as the factors of RSA-100
+        assert!(!submitted.contains(&(Some(Factor::from(RSA_100)), Factor::from(P))));
+        assert!(!submitted.contains(&(Some(Factor::from(RSA_100)), Factor::from(Q))));
+    }
+
+    #[test]
+    fn test_withheld_only_from_destinations_factordb_lists() {
+        use crate::graph::FactorsKnownToFactorDb::UpToDate;
+        use crate::graph::is_withheld_as_known_to_factordb;
+
+        let mut data = FactorData::default();
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        let (factor, _) = add_factor_node(&mut data, Factor::from("2^64+1"), None, &http);
+        let (listed_dest, _) = add_factor_node(&mut data, Factor::from("2^128-1"), None, &http);
+        let (multiple, _) = add_factor_node(&mut data, Factor::from("2^256-1"), None, &http);
+        data.facts_mut(listed_dest).factors_known_to_factordb = UpToDate(vec![factor]);
+        data.propagate_divisibility(listed_dest, multiple, false);
+
+        assert!(is_withheld_as_known_to_factordb(
+            &mut data,
+            factor,
+            listed_dest,
+            true
+        ));
+        assert!(!is_withheld_as_known_to_factordb(
+            &mut data,
+            factor,
+            listed_dest,
+            false
+        ));
+        // FactorDB hasn't been told about the factor's relation to any other number
+        assert!(!is_withheld_as_known_to_factordb(
+            &mut data, factor, multiple, true
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_verify_rejects_bogus_factor() {
+        use crate::NumberSpecifier::Id;
+        use crate::algebraic::divides;
+        use crate::net::NumberStatus::UnfactoredComposite;
+
+        const ID: EntryId = 1;
+
+        FAILED_U_SUBMISSIONS_OUT
+            .get_or_init(async || {
+                Mutex::new(File::create_new(temp_dir().join(rng().next_u64().to_string())).unwrap())
+            })
+            .await;
+
+        let root = Factor::from("2^64+1");
+        let mut http = MockFactorDbClient::new();
+        http.expect_cached_factors().return_const(None);
+        http.expect_invalidate_cached_factors().return_const(());
+        // Pretend FactorDB gave a wrong expression form, so that the factor finder comes up with
+        // factors of 2^64+3 (467443687 and 39463029637) that don't divide the root
+        http.expect_known_factors_as_digits()
+            .returning(|id, _, _| match id {
+                Id(ID) => ProcessedStatusApiResponse {
+                    status: Some(UnfactoredComposite),
+                    factors: Box::new([Factor::from("2^64+3")]),
+                    id: Some(ID),
+                },
+                _ => ProcessedStatusApiResponse {
+                    status: None,
+                    factors: Box::new([]),
+                    id: None,
+                },
+            });
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_try_get_expression_form().returning(|_| None);
+        http.expect_try_report_factor().returning({
+            let root = root.clone();
+            move |_, factor| {
+                assert_eq!(
+                    divides(factor, &root),
+                    Some(true),
+                    "Submitted bogus factor {factor}"
+                );
+                ReportFactorResult::Accepted
+            }
+        });
+        let options = SubmissionOptions {
+            verify_factors: true,
+            ..SubmissionOptions::default()
+        };
+
+        assert!(matches!(
+            find_and_submit_factors(&http, ID, root, false, options).await,
+            SubmissionOutcome::Accepted(_)
+        ));
+    }
 }
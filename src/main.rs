@@ -9,17 +9,47 @@ extern crate alloc;
 extern crate core;
 
 mod algebraic;
+mod aliquot;
+mod bases;
 mod channel;
+mod cursor;
+mod digits;
 mod graph;
 mod monitor;
 mod net;
+mod pool;
+mod ratio;
+mod safe_primes;
+mod shuffle;
+mod snapshot;
+mod stats;
+mod watchdog;
+mod yafu;
 
 use crate::NumberSpecifier::{Expression, Id};
 use crate::ReportFactorResult::{Accepted, AlreadyFullyFactored};
-use crate::algebraic::Factor;
-use crate::graph::EntryId;
+use crate::algebraic::{
+    Factor, clear_all_caches, estimate_digit_count, evaluate_as_numeric, factor_cache_stats,
+    find_unique_factors,
+};
+use crate::aliquot::parse_aliquot_links;
+use crate::bases::{CheckedBases, PrpCheckOutcome, parse_checked_bases};
+use crate::cursor::{SearchCursor, fetch_composites_batch, jittered_start};
+use crate::digits::{DigitRanges, DigitWeights, too_large_for_prp_check};
+use crate::graph::{EntryId, SubmissionOptions, SubmissionOutcome};
 use crate::monitor::Monitor;
-use crate::net::{FactorDbClient, FactorDbClientReadIdsAndExprs, ResourceLimits};
+use crate::net::{
+    CpuReserve, FactorDbClient, FactorDbClientReadIdsAndExprs, MIN_BORDERLINE_ID_EQUAL_TO_VALUE,
+    PAUSED, POLITENESS_WINDOWS, PolitenessWindow, ResourceLimits,
+};
+use crate::pool::{FACTORING_POOL, FactoringPool, MAX_CONCURRENT_FACTORING_JOBS};
+use crate::ratio::{WORK_RATIO, WorkCategory};
+use crate::safe_primes::has_prime_sibling;
+use crate::shuffle::{SHUFFLE_RNG, ShuffleRng, shuffle_rng};
+use crate::snapshot::{GRAPH_SNAPSHOT_DIR, GraphSnapshot};
+use crate::stats::RUN_STATS;
+use crate::watchdog::{WATCHDOG, WATCHDOG_TIMEOUT_SECS};
+use crate::yafu::{YAFU_STAGING_CAPACITY, YafuDispatcher};
 use ahash::RandomState;
 use alloc::sync::Arc;
 use async_backtrace::framed;
@@ -29,20 +59,26 @@ use cuckoofilter::CuckooFilter;
 use futures_util::FutureExt;
 use hipstr::HipStr;
 use log::{error, info, warn};
+use net::FetchOutcome::{Failed, Fetched, Throttled};
 use net::NumberStatus::FullyFactored;
-use net::{CPU_TENTHS_SPENT_LAST_CHECK, RealFactorDbClient};
-use net::{NumberStatusExt, ProcessedStatusApiResponse};
+use net::{
+    CPU_TENTHS_SPENT_LAST_CHECK, MAX_PARALLEL_REQUESTS, MAX_REQUESTS_PER_CYCLE,
+    MIN_SEARCH_INTERVAL_SECS, PAGE_CACHE_TTL_SECS, RealFactorDbClient,
+};
+use net::{NumberStatusExt, ProcessedStatusApiResponse, known_factors_of_ids};
 use primitive_types::U256;
 use quick_cache::UnitWeighter;
 use quick_cache::sync::{Cache, DefaultLifecycle};
+use rand::RngExt;
 use rand::seq::SliceRandom;
-use rand::{RngExt, rng};
 use regex::Regex;
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use stats_alloc::StatsAlloc;
 use std::alloc::GlobalAlloc;
 use std::borrow::Cow;
+use std::cell::Cell;
+use std::convert::Infallible;
 use std::fmt::{self, Debug, Display, Formatter};
 use std::fs::File;
 use std::hash::{DefaultHasher, Hash, Hasher};
@@ -50,10 +86,12 @@ use std::io::Write;
 use std::num::NonZeroU32;
 use std::ops::Add;
 use std::panic;
+use std::path::Path;
 use std::process::{abort, exit};
+use std::str::FromStr;
 use std::sync::OnceLock;
-use std::sync::atomic::AtomicBool;
 use std::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize};
 use sysinfo::MemoryRefreshKind;
 use sysinfo::RefreshKind;
 use tokio::signal::ctrl_c;
@@ -111,6 +149,8 @@ const MAX_START: EntryId = 100_000;
 const RETRY_DELAY: Duration = Duration::from_secs(3);
 const SEARCH_RETRY_DELAY: Duration = Duration::from_secs(10);
 const UNPARSEABLE_RESPONSE_RETRY_DELAY: Duration = Duration::from_secs(10);
+/// How long to back off when FactorDB asks us to wait without saying how long.
+const THROTTLED_RETRY_DELAY: Duration = Duration::from_secs(30);
 const PRP_RESULTS_PER_PAGE: usize = 32;
 const PRP_MIN_DIGITS: NumberLength = 300u32;
 const PRP_MAX_DIGITS: NumberLength = 80_000u32; // FIXME: Increase this once FactorDB can handle PRP checks on larger numbers without timing out.
@@ -127,8 +167,11 @@ const U_MIN_DIGITS: NumberLength = 2001;
 const U_MAX_DIGITS: NumberLength = 199_999;
 const SUBMIT_FACTOR_MAX_ATTEMPTS: usize = 5;
 static EXIT_TIME: OnceCell<Instant> = OnceCell::const_new();
-static COMPOSITES_OUT: OnceCell<Mutex<File>> = OnceCell::const_new();
+static COMPOSITES_OUT: OnceLock<YafuDispatcher> = OnceLock::new();
 static FAILED_U_SUBMISSIONS_OUT: OnceCell<Mutex<File>> = OnceCell::const_new();
+/// Audit trail of the factors FactorDB accepted from us, as `id,factor,unix_timestamp` rows. Only
+/// opened when the `LOG_ACCEPTED_FACTORS` environment variable is set.
+static ACCEPTED_FACTORS_OUT: OnceCell<Mutex<File>> = OnceCell::const_new();
 static HAVE_DISPATCHED_TO_YAFU: AtomicBool = AtomicBool::new(false);
 
 #[derive(Clone, Debug, Eq)]
@@ -183,7 +226,16 @@ async fn composites_while_waiting(
             warn!("Timed out waiting for a composite number to check");
             return;
         };
-        check_composite(http, c_filter, id, digits_or_expr, return_permit).await;
+        check_composite(
+            http,
+            COMPOSITES_OUT.get(),
+            c_receiver,
+            c_filter,
+            id,
+            digits_or_expr,
+            return_permit,
+        )
+        .await;
         match end.checked_duration_since(Instant::now()) {
             None => {
                 info!("Out of time while processing composites");
@@ -197,6 +249,8 @@ async fn composites_while_waiting(
 #[framed]
 async fn check_composite(
     http: &impl FactorDbClientReadIdsAndExprs,
+    yafu: Option<&YafuDispatcher>,
+    c_receiver: &mut PushbackReceiver<CompositeCheckTask>,
     c_filter: &mut CuckooFilter<DefaultHasher>,
     id: EntryId,
     digits_or_expr: HipStr<'static>,
@@ -206,55 +260,106 @@ async fn check_composite(
         info!("{id}: Skipping duplicate C");
         return true;
     }
-    let checks_triggered = if http
+    let checks_triggered = if let Some(sequences_page) = http
         .try_get_and_decode(&format!("https://factordb.com/sequences.php?check={id}"))
         .await
-        .is_some()
     {
         info!("{id}: Checked C");
+        // Follow the aliquot chain, since the next term is what this one's factors lead to
+        if let Some(successor) = parse_aliquot_links(&sequences_page).successor
+            && !c_filter.contains(&successor)
+        {
+            if c_receiver.try_push(CompositeCheckTask {
+                id: successor,
+                digits_or_expr: successor.to_string().into(),
+            }) {
+                info!("{id}: Queued the next aliquot term {successor}");
+            } else {
+                warn!("{id}: No room to queue the next aliquot term {successor}");
+            }
+        }
         true
     } else {
         false
     };
     // First, convert the composite to digits
+    let response = http.known_factors_as_digits(Id(id), false, true).await;
+    if let Some(cofactor) = response.crowded_cofactor(MAX_KNOWN_FACTORS.load(Acquire)) {
+        info!(
+            "{id}: Already has {} known factors, so skipping the factor finder",
+            response.factors.len()
+        );
+        let Some(yafu) = yafu else {
+            // Requeueing it would only bring it back here, since there's no yafu to take it
+            info!("{id}: Not requeueing C, since yafu isn't running");
+            return true;
+        };
+        if dispatch_to_yafu(yafu, id, cofactor) {
+            return true;
+        }
+        if c_receiver
+            .requeue_after_failure(CompositeCheckTask { id, digits_or_expr }, return_permit)
+        {
+            info!("{id}: Requeued C");
+        }
+        return false;
+    }
     let ProcessedStatusApiResponse {
         factors, status, ..
-    } = http.known_factors_as_digits(Id(id), false, true).await;
+    } = response;
     if factors.is_empty() {
         if status.is_known_fully_factored() {
             warn!("{id}: Already fully factored");
             true
         } else {
-            return_permit.send(CompositeCheckTask { id, digits_or_expr });
-            info!("{id}: Requeued C");
+            if c_receiver
+                .requeue_after_failure(CompositeCheckTask { id, digits_or_expr }, return_permit)
+            {
+                info!("{id}: Requeued C");
+            }
             false
         }
     } else {
         let mut factors_submitted = false;
         let mut dispatched = false;
+        let mut refused = false;
         for factor in factors {
             if matches!(factor, Factor::Numeric(_)) {
                 continue;
             }
-            if graph::find_and_submit_factors(http, id, factor.clone(), true).await {
-                factors_submitted = true;
-            } else {
-                if let Some(out) = COMPOSITES_OUT.get() {
-                    let mut out = out.lock().await;
-                    let result = out.write_fmt(format_args!("{}\n", factor.to_unelided_string()));
-                    if let Err(error) = result {
-                        error!("{id}: Failed to write factor to FIFO: {error}");
-                    } else {
-                        info!("{id}: Dispatched C to yafu");
-                        HAVE_DISPATCHED_TO_YAFU.store(true, Release);
-                        dispatched = true;
+            match graph::find_and_submit_factors(
+                http,
+                id,
+                factor.clone(),
+                true,
+                SubmissionOptions::from_globals(),
+            )
+            .await
+            {
+                SubmissionOutcome::AlreadyFullyFactored => {
+                    // No other factor needs yafu either
+                    factors_submitted = true;
+                    break;
+                }
+                SubmissionOutcome::Accepted(_) => factors_submitted = true,
+                SubmissionOutcome::NoProgress => {
+                    if let Some(yafu) = yafu {
+                        if dispatch_to_yafu(yafu, id, &factor) {
+                            dispatched = true;
+                        } else {
+                            refused = true;
+                        }
                     }
                 }
             }
         }
-        if !dispatched && !checks_triggered && !factors_submitted {
-            return_permit.send(CompositeCheckTask { id, digits_or_expr });
-            info!("{id}: Requeued C");
+        // Whatever else happened, a factor that yafu had no room for still needs factoring
+        if refused || (!dispatched && !checks_triggered && !factors_submitted) {
+            if c_receiver
+                .requeue_after_failure(CompositeCheckTask { id, digits_or_expr }, return_permit)
+            {
+                info!("{id}: Requeued C");
+            }
             false
         } else {
             true
@@ -262,6 +367,29 @@ async fn check_composite(
     }
 }
 
+/// Hands `factor` to yafu, if it's keeping up. Returns whether it was dispatched.
+fn dispatch_to_yafu(yafu: &YafuDispatcher, id: EntryId, factor: &Factor) -> bool {
+    if yafu.try_dispatch(
+        factor
+            .digits()
+            .unwrap_or_else(|| factor.to_unelided_string()),
+    ) {
+        info!(
+            "{id}: Dispatched C to yafu ({} waiting to be written)",
+            yafu.staging_depth()
+        );
+        HAVE_DISPATCHED_TO_YAFU.store(true, Release);
+        RUN_STATS.record_c_dispatched_to_yafu();
+        true
+    } else {
+        warn!(
+            "{id}: yafu isn't keeping up ({} composites waiting), so not dispatching",
+            yafu.staging_depth()
+        );
+        false
+    }
+}
+
 #[derive(Clone, Debug, Ord, PartialOrd, Eq, PartialEq)]
 enum NumberSpecifier<'a> {
     Id(EntryId),
@@ -278,13 +406,96 @@ impl<'a> Display for NumberSpecifier<'a> {
     }
 }
 
+impl FromStr for NumberSpecifier<'static> {
+    type Err = Infallible;
+
+    /// Parses a bare integer below [MIN_BORDERLINE_ID_EQUAL_TO_VALUE] as an ID, since that's what
+    /// FactorDB uses as such a number's ID, and anything else as an expression. Borderline values
+    /// are parsed as numeric expressions, so that the client checks their IDs before using them.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.trim();
+        if let Ok(id) = s.parse::<EntryId>()
+            && id < MIN_BORDERLINE_ID_EQUAL_TO_VALUE
+        {
+            return Ok(Id(id));
+        }
+        Ok(Expression(Cow::Owned(Factor::from(s))))
+    }
+}
+
+/// Numbers written with at least this many characters are elided. Overridden by the
+/// `BIGNUM_ELISION_THRESHOLD` environment variable.
+static BIGNUM_ELISION_THRESHOLD: AtomicUsize = AtomicUsize::new(300);
+
+/// Leading characters kept when a number is elided. Overridden by the `BIGNUM_ELISION_HEAD`
+/// environment variable.
+static BIGNUM_ELISION_HEAD: AtomicUsize = AtomicUsize::new(20);
+
+/// Trailing characters kept when a number is elided. Overridden by the `BIGNUM_ELISION_TAIL`
+/// environment variable.
+static BIGNUM_ELISION_TAIL: AtomicUsize = AtomicUsize::new(5);
+
+/// How [write_bignum] shortens long numbers: those of at least `threshold` characters are
+/// written as their first `head` and last `tail` characters, followed by the length.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct BignumElision {
+    pub threshold: usize,
+    pub head: usize,
+    pub tail: usize,
+}
+
+impl BignumElision {
+    /// Writes every number in full.
+    pub const UNELIDED: Self = BignumElision {
+        threshold: usize::MAX,
+        head: 0,
+        tail: 0,
+    };
+
+    fn from_globals() -> Self {
+        BignumElision {
+            threshold: BIGNUM_ELISION_THRESHOLD.load(Acquire),
+            head: BIGNUM_ELISION_HEAD.load(Acquire),
+            tail: BIGNUM_ELISION_TAIL.load(Acquire),
+        }
+    }
+}
+
+thread_local! {
+    /// Overrides the elision configured by environment variables, while set.
+    static BIGNUM_ELISION: Cell<Option<BignumElision>> = const { Cell::new(None) };
+}
+
+/// Restores the previous [BIGNUM_ELISION] when dropped, even if the closure panicked.
+struct RestoreBignumElision(Option<BignumElision>);
+
+impl Drop for RestoreBignumElision {
+    fn drop(&mut self) {
+        BIGNUM_ELISION.set(self.0);
+    }
+}
+
+/// Runs `f` with numbers formatted on this thread elided according to `elision`, e.g. to log more
+/// digits while debugging a factor mismatch.
+pub fn with_bignum_elision<T>(elision: BignumElision, f: impl FnOnce() -> T) -> T {
+    let _restore = RestoreBignumElision(BIGNUM_ELISION.replace(Some(elision)));
+    f()
+}
+
 #[inline(always)]
 pub fn write_bignum(f: &mut Formatter, e: &str) -> fmt::Result {
+    let BignumElision {
+        threshold,
+        head,
+        tail,
+    } = BIGNUM_ELISION
+        .get()
+        .unwrap_or_else(BignumElision::from_globals);
     let len = e.len();
-    if len < 300 {
+    if len < threshold || head + tail >= len {
         f.write_str(e)
     } else {
-        write!(f, "{}...{}<{}>", &e[..20], &e[(len - 5)..], len)
+        write!(f, "{}...{}<{}>", &e[..head], &e[(len - tail)..], len)
     }
 }
 
@@ -305,6 +516,65 @@ const MIN_BASES_BETWEEN_RESOURCE_CHECKS: usize = 16;
 
 const MAX_CPU_BUDGET_TENTHS: usize = 6000;
 static NO_RESERVE: AtomicBool = AtomicBool::new(false);
+static CPU_RESERVE: OnceLock<CpuReserve> = OnceLock::new();
+
+/// Divisibility graphs larger than this have their fully-processed primes evicted. Overridden by
+/// the `MAX_GRAPH_VERTICES` environment variable.
+static MAX_GRAPH_VERTICES: AtomicUsize = AtomicUsize::new(1 << 12);
+
+/// Factors found this many rounds of decomposition below the number they're being submitted to
+/// aren't decomposed any further, so that deeply nested quotients can't cause unbounded work.
+/// Overridden by the `MAX_DECOMPOSITION_DEPTH` environment variable.
+static MAX_DECOMPOSITION_DEPTH: AtomicUsize = AtomicUsize::new(16);
+
+/// Every this many iterations of a long-running factor submission, its divisibility graph is saved
+/// so that a restart can resume it. 0 (the default) disables snapshots. Overridden by the
+/// `GRAPH_SNAPSHOT_INTERVAL` environment variable.
+static GRAPH_SNAPSHOT_INTERVAL: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether divisibility graph snapshots are gzipped. Snapshots in either format can be resumed
+/// from. Overridden by the `COMPRESS_GRAPH_SNAPSHOTS` environment variable.
+static COMPRESS_GRAPH_SNAPSHOTS: AtomicBool = AtomicBool::new(true);
+
+/// How many C's to request per page when assembling a batch of [C_RESULTS_PER_PAGE] from
+/// consecutive pages. Overridden by the `C_PAGE_SIZE` environment variable.
+static C_PAGE_SIZE: AtomicUsize = AtomicUsize::new(1000);
+
+/// A C with more known factors than this, all but one of them numeric, goes straight to yafu
+/// instead of through the factor finder. Overridden by the `MAX_KNOWN_FACTORS` environment
+/// variable.
+static MAX_KNOWN_FACTORS: AtomicUsize = AtomicUsize::new(100);
+
+/// How long the factor finder may spend on one number before it's skipped. Overridden by the
+/// `FACTOR_FINDER_TIMEOUT_SECS` environment variable.
+static FACTOR_FINDER_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(300);
+
+/// Factors with fewer digits than this are trivial for FactorDB to find itself, so they're only
+/// submitted when nothing larger is accepted. Overridden by the `MIN_SUBMITTED_FACTOR_DIGITS`
+/// environment variable.
+static MIN_SUBMITTED_FACTOR_DIGITS: AtomicU32 = AtomicU32::new(0);
+
+/// Factorials up to this term are evaluated exactly in 512 bits rather than term by term when
+/// taken modulo a number. 98 is the most that fits. Overridden by the `MAX_BIGNUM_FACTORIAL_TERM`
+/// environment variable.
+static MAX_BIGNUM_FACTORIAL_TERM: AtomicU32 = AtomicU32::new(98);
+
+/// Powers and exponent towers are only evaluated exactly if they can have at most this many bits;
+/// larger ones stay symbolic. Values above 512 have no effect, since that's all that fits.
+/// Overridden by the `MAX_BIGNUM_POWER_BITS` environment variable.
+static MAX_BIGNUM_POWER_BITS: AtomicU32 = AtomicU32::new(512);
+
+/// Us with more digits than this are never queued, since FactorDB won't PRP-check them. Overridden
+/// by the `U_MAX_PRP_CHECK_DIGITS` environment variable.
+static U_MAX_PRP_CHECK_DIGITS: AtomicU32 = AtomicU32::new(U_MAX_DIGITS);
+
+/// Set by `--verify`: before submitting a factor, check that it really divides the destination
+/// whenever the factor is small enough to evaluate, in case our own factor-finding has a bug.
+static VERIFY_FACTORS: AtomicBool = AtomicBool::new(false);
+
+/// Set by the `CONTRIBUTE_ONLY` environment variable: don't submit a factor to a number that
+/// FactorDB already lists it as a factor of, since that tells FactorDB nothing new.
+static CONTRIBUTE_ONLY: AtomicBool = AtomicBool::new(false);
 
 #[framed]
 async fn throttle_if_necessary(
@@ -343,8 +613,9 @@ async fn throttle_if_necessary(
         .as_secs_f64();
     let mut tenths_remaining = MAX_CPU_BUDGET_TENTHS.saturating_sub(cpu_tenths_spent);
     if !NO_RESERVE.load(Acquire) {
-        tenths_remaining = tenths_remaining
-            .saturating_sub((seconds_to_reset * seconds_to_reset / 18000.0) as usize);
+        let reserve = CPU_RESERVE.get().copied().unwrap_or_default();
+        tenths_remaining =
+            tenths_remaining.saturating_sub(reserve.reserved_tenths(seconds_to_reset));
     }
     let mut bases_remaining = (tenths_remaining / 10).min(MAX_BASES_BETWEEN_RESOURCE_CHECKS);
     if bases_remaining <= MIN_BASES_BETWEEN_RESOURCE_CHECKS {
@@ -358,6 +629,7 @@ async fn throttle_if_necessary(
             .is_some_and(|exit_time| *exit_time <= resets_at)
         {
             warn!("Throttling won't end before program exit; exiting now");
+            RUN_STATS.log_summary();
             exit(0);
         }
         composites_while_waiting(resets_at, http, c_receiver, c_filter).await;
@@ -389,7 +661,15 @@ pub fn log_stats<T: GlobalAlloc>(
     sys.refresh_all();
     info!("System used memory: {}", sys.used_memory());
     info!("System available memory: {}", sys.available_memory());
+    for cache_stats in factor_cache_stats() {
+        info!("{cache_stats}");
+    }
+    if sys.available_memory() < sys.total_memory() / 16 {
+        warn!("Clearing factor-finder caches because memory is low");
+        clear_all_caches();
+    }
     info!("Task backtraces:\n{}", taskdump_tree(false));
+    WORK_RATIO.log_weights();
     match backtraces_paused_task {
         Some(task) => {
             if !task.is_finished() {
@@ -406,64 +686,305 @@ pub fn log_stats<T: GlobalAlloc>(
     }));
 }
 
+/// Handles `factor <expr>...` and `eval <expr>...`, which run the algebraic engine on the given
+/// expressions and print the results without contacting FactorDB. For `factor`, each argument may
+/// hold several expressions separated by newlines or `;`, and `-` reads such a batch from stdin.
+async fn run_subcommand(
+    subcommand: &str,
+    exprs: impl Iterator<Item = String>,
+) -> anyhow::Result<()> {
+    let mut stdout = std::io::stdout().lock();
+    match subcommand {
+        "factor" => {
+            for batch in exprs {
+                let batch = if batch == "-" {
+                    std::io::read_to_string(std::io::stdin())?
+                } else {
+                    batch
+                };
+                for factor in Factor::from_batch(&batch) {
+                    for subfactor in find_unique_factors(&factor) {
+                        writeln!(stdout, "{}", subfactor.to_unelided_string())?;
+                    }
+                }
+            }
+        }
+        "eval" => {
+            for expr in exprs {
+                let Ok(factor) = Factor::from_checked(&expr) else {
+                    anyhow::bail!(
+                        "{expr} doesn't print as an expression that parses back the same"
+                    );
+                };
+                let Some(value) = evaluate_as_numeric(&factor) else {
+                    anyhow::bail!("{expr} is too large to evaluate");
+                };
+                writeln!(stdout, "{value}")?;
+            }
+        }
+        _ => anyhow::bail!("Unknown subcommand {subcommand:?}; expected `factor` or `eval`"),
+    }
+    Ok(())
+}
+
 #[tokio::main(flavor = "multi_thread", worker_threads = 1)]
 #[framed]
 async fn main() -> anyhow::Result<()> {
+    let mut args = std::env::args().skip(1).peekable();
+    if args.next_if(|arg| arg == "--verify").is_some() {
+        VERIFY_FACTORS.store(true, Release);
+    }
+    if let Some(subcommand) = args.next() {
+        return run_subcommand(&subcommand, args).await;
+    }
     let mut reg = stats_alloc::Region::new(&GLOBAL);
     let mut sys = sysinfo::System::new_with_specifics(
         RefreshKind::nothing().with_memory(MemoryRefreshKind::everything()),
     );
     let (shutdown_sender, mut shutdown_receiver) = Monitor::new();
     simple_log::console("info,reqwest=debug").unwrap();
+    if let Some(max_concurrent_factoring_jobs) = std::env::var("MAX_CONCURRENT_FACTORING_JOBS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+        .filter(|&jobs| jobs > 0)
+    {
+        MAX_CONCURRENT_FACTORING_JOBS.store(max_concurrent_factoring_jobs, Release);
+    }
+    FACTORING_POOL
+        .set(FactoringPool::new(
+            MAX_CONCURRENT_FACTORING_JOBS.load(Acquire),
+            Some(shutdown_receiver.clone()),
+        ))
+        .expect("Factoring pool initialized twice");
+    // When set, the search order is the same on every run, and so are submission shuffles as long
+    // as the tasks interleave the same way
+    let shuffle_seed = std::env::var("SHUFFLE_SEED")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok());
+    if let Some(shuffle_seed) = shuffle_seed {
+        info!("Shuffling with seed {shuffle_seed}");
+    }
+    SHUFFLE_RNG
+        .set(ShuffleRng::new(shuffle_seed))
+        .expect("Shuffle RNG initialized twice");
 
     let signal_installer = task::spawn(async move {
         let sigint = Box::pin(ctrl_c());
         #[cfg(unix)]
         {
-            let sigterm = tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
-                .expect("Failed to create SIGTERM signal stream");
-            (sigint, tokio_stream::wrappers::SignalStream::new(sigterm))
+            use tokio::signal::unix::{SignalKind, signal};
+            use tokio_stream::wrappers::SignalStream;
+            let sigterm =
+                signal(SignalKind::terminate()).expect("Failed to create SIGTERM signal stream");
+            let sigusr1 = signal(SignalKind::user_defined1())
+                .expect("Failed to create SIGUSR1 signal stream");
+            let sighup =
+                signal(SignalKind::hangup()).expect("Failed to create SIGHUP signal stream");
+            (
+                sigint,
+                SignalStream::new(sigterm),
+                SignalStream::new(sigusr1),
+                SignalStream::new(sighup),
+            )
         }
         #[cfg(not(unix))]
-        (sigint, tokio_stream::pending::<()>())
+        (
+            sigint,
+            tokio_stream::pending::<()>(),
+            tokio_stream::pending::<()>(),
+            tokio_stream::pending::<()>(),
+        )
     });
 
     let is_no_reserve = std::env::var("NO_RESERVE").is_ok();
     NO_RESERVE.store(is_no_reserve, Release);
+    CONTRIBUTE_ONLY.store(std::env::var("CONTRIBUTE_ONLY").is_ok(), Release);
+    CPU_RESERVE
+        .set(CpuReserve::from_env()?)
+        .expect("CPU_RESERVE is only set here");
+    POLITENESS_WINDOWS
+        .set(PolitenessWindow::from_env()?)
+        .expect("POLITENESS_WINDOWS is only set here");
+    // Sophie Germain and safe primes are queued ahead of the other PRPs on the same search page
+    let prioritize_safe_primes = std::env::var("PRIORITIZE_SAFE_PRIMES").is_ok();
+    // Otherwise PRP's always take priority over C's
+    let adaptive_work_ratio = std::env::var("ADAPTIVE_WORK_RATIO").is_ok();
+    if let Some(max_graph_vertices) = std::env::var("MAX_GRAPH_VERTICES")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+    {
+        MAX_GRAPH_VERTICES.store(max_graph_vertices, Release);
+    }
+    if let Some(max_decomposition_depth) = std::env::var("MAX_DECOMPOSITION_DEPTH")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+    {
+        MAX_DECOMPOSITION_DEPTH.store(max_decomposition_depth, Release);
+    }
+    for (var, setting) in [
+        ("BIGNUM_ELISION_THRESHOLD", &BIGNUM_ELISION_THRESHOLD),
+        ("BIGNUM_ELISION_HEAD", &BIGNUM_ELISION_HEAD),
+        ("BIGNUM_ELISION_TAIL", &BIGNUM_ELISION_TAIL),
+    ] {
+        if let Some(value) = std::env::var(var)
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+        {
+            setting.store(value, Release);
+        }
+    }
+    if let Some(graph_snapshot_interval) = std::env::var("GRAPH_SNAPSHOT_INTERVAL")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+    {
+        GRAPH_SNAPSHOT_INTERVAL.store(graph_snapshot_interval, Release);
+    }
+    if let Some(compress_graph_snapshots) = std::env::var("COMPRESS_GRAPH_SNAPSHOTS")
+        .ok()
+        .and_then(|s| s.parse::<bool>().ok())
+    {
+        COMPRESS_GRAPH_SNAPSHOTS.store(compress_graph_snapshots, Release);
+    }
+    if let Some(c_page_size) = std::env::var("C_PAGE_SIZE")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+    {
+        C_PAGE_SIZE.store(c_page_size, Release);
+    }
+    if let Some(max_known_factors) = std::env::var("MAX_KNOWN_FACTORS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+    {
+        MAX_KNOWN_FACTORS.store(max_known_factors, Release);
+    }
+    if let Some(factor_finder_timeout_secs) = std::env::var("FACTOR_FINDER_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+    {
+        FACTOR_FINDER_TIMEOUT_SECS.store(factor_finder_timeout_secs, Release);
+    }
+    if let Some(min_submitted_factor_digits) = std::env::var("MIN_SUBMITTED_FACTOR_DIGITS")
+        .ok()
+        .and_then(|s| s.parse::<NumberLength>().ok())
+    {
+        MIN_SUBMITTED_FACTOR_DIGITS.store(min_submitted_factor_digits, Release);
+    }
+    if let Some(max_bignum_factorial_term) = std::env::var("MAX_BIGNUM_FACTORIAL_TERM")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+    {
+        MAX_BIGNUM_FACTORIAL_TERM.store(max_bignum_factorial_term, Release);
+    }
+    if let Some(max_bignum_power_bits) = std::env::var("MAX_BIGNUM_POWER_BITS")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+    {
+        MAX_BIGNUM_POWER_BITS.store(max_bignum_power_bits, Release);
+    }
+    if let Some(u_max_prp_check_digits) = std::env::var("U_MAX_PRP_CHECK_DIGITS")
+        .ok()
+        .and_then(|s| s.parse::<NumberLength>().ok())
+    {
+        U_MAX_PRP_CHECK_DIGITS.store(u_max_prp_check_digits, Release);
+    }
+    if let Some(page_cache_ttl_secs) = std::env::var("PAGE_CACHE_TTL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+    {
+        PAGE_CACHE_TTL_SECS.store(page_cache_ttl_secs, Release);
+    }
+    if let Some(min_search_interval_secs) = std::env::var("MIN_SEARCH_INTERVAL_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+    {
+        MIN_SEARCH_INTERVAL_SECS.store(min_search_interval_secs, Release);
+    }
+    if let Some(watchdog_timeout_secs) = std::env::var("WATCHDOG_TIMEOUT_SECS")
+        .ok()
+        .and_then(|s| s.parse::<u64>().ok())
+    {
+        WATCHDOG_TIMEOUT_SECS.store(watchdog_timeout_secs, Release);
+    }
+    if let Some(max_requests_per_cycle) = std::env::var("MAX_REQUESTS_PER_CYCLE")
+        .ok()
+        .and_then(|s| s.parse::<u32>().ok())
+    {
+        MAX_REQUESTS_PER_CYCLE.store(max_requests_per_cycle, Release);
+    }
+    if let Some(max_parallel_requests) = std::env::var("MAX_PARALLEL_REQUESTS")
+        .ok()
+        .and_then(|s| s.parse::<usize>().ok())
+    {
+        MAX_PARALLEL_REQUESTS.store(max_parallel_requests, Release);
+    }
     let mut c_digits = std::env::var("C_DIGITS")
         .ok()
         .and_then(|s| s.parse::<NumberLength>().ok());
     let mut u_digits = std::env::var("U_DIGITS")
         .ok()
         .and_then(|s| s.parse::<NumberLength>().ok());
+    let digit_ranges = DigitRanges::from_env()?;
+    let c_digit_weights =
+        DigitWeights::from_env("C_DIGIT_WEIGHTS", digit_ranges.c.min, digit_ranges.c.max);
+    let u_digit_weights =
+        DigitWeights::from_env("U_DIGIT_WEIGHTS", digit_ranges.u.min, digit_ranges.u.max);
+    let run_number = std::env::var("RUN")
+        .ok()
+        .map(|run_number| run_number.parse::<EntryId>())
+        .transpose()?;
+    // When set along with RUN, parallel runs start their searches at well-separated points, each
+    // offset by up to this much; otherwise they start at C_START and PRP_START or at random
+    let search_start_jitter = std::env::var("SEARCH_START_JITTER")
+        .ok()
+        .and_then(|s| s.parse::<EntryId>().ok());
+    let run_start = |max_start| Some(jittered_start(run_number?, max_start, search_start_jitter?));
+    let mut c_cursor = match std::env::var("C_SEARCH_MODE").as_deref() {
+        Ok("sequential") => {
+            let c_max_start = std::env::var("C_MAX_START")
+                .ok()
+                .and_then(|s| s.parse::<EntryId>().ok())
+                .unwrap_or(MAX_START);
+            let c_start = std::env::var("C_START")
+                .ok()
+                .and_then(|s| s.parse::<EntryId>().ok())
+                .or_else(|| run_start(c_max_start))
+                .unwrap_or(0);
+            info!("C search will sweep sequentially from {c_start} up to {c_max_start}");
+            Some(SearchCursor::new(c_start, c_max_start))
+        }
+        Ok("random") | Err(_) => None,
+        Ok(other) => {
+            error!("Unknown C_SEARCH_MODE {other:?}; using random starting points");
+            None
+        }
+    };
     let prp_start = std::env::var("PRP_START")
         .ok()
         .and_then(|s| s.parse::<EntryId>().ok());
     let mut prp_digits = std::env::var("PRP_DIGITS")
         .ok()
         .and_then(|s| s.parse::<NumberLength>().ok());
-    if let Ok(run_number) = std::env::var("RUN") {
-        let run_number = run_number.parse::<EntryId>()?;
+    if let Some(run_number) = run_number {
         if c_digits.is_none() {
-            let mut c_digits_value = C_MAX_DIGITS
+            let mut c_digits_value = digit_ranges.c.max
                 - NumberLength::try_from(
-                    (run_number * 19) % EntryId::from(C_MAX_DIGITS - C_MIN_DIGITS + 2),
+                    (run_number * 19) % (EntryId::from(digit_ranges.c.span()) + 1),
                 )?;
-            if c_digits_value == C_MIN_DIGITS - 1 {
+            if c_digits_value == digit_ranges.c.min - 1 {
                 c_digits_value = 1;
             }
             c_digits = Some(c_digits_value);
         }
         if u_digits.is_none() {
-            let u_digits_value: NumberLength = U_MIN_DIGITS
+            let u_digits_value: NumberLength = digit_ranges.u.min
                 + NumberLength::try_from(
-                    (run_number * 19793) % EntryId::from(U_MAX_DIGITS - U_MIN_DIGITS + 1),
+                    (run_number * 19793) % EntryId::from(digit_ranges.u.span()),
                 )?;
             u_digits = Some(u_digits_value);
         }
         if prp_digits.is_none() {
-            prp_digits = Some(PRP_MIN_DIGITS.saturating_add(NumberLength::try_from(
-                (run_number * 9973) % EntryId::from(PRP_MAX_DIGITS - PRP_MIN_DIGITS + 1),
+            prp_digits = Some(digit_ranges.prp.min.saturating_add(NumberLength::try_from(
+                (run_number * 9973) % EntryId::from(digit_ranges.prp.span()),
             )?));
         }
         info!("Run number is {run_number}");
@@ -487,32 +1008,49 @@ async fn main() -> anyhow::Result<()> {
     } else {
         Duration::from_mins(3)
     };
-    let mut prp_digits =
-        prp_digits.unwrap_or_else(|| rng().random_range(PRP_MIN_DIGITS..=PRP_MAX_DIGITS));
+    let mut prp_digits = prp_digits
+        .unwrap_or_else(|| shuffle_rng().random_range(digit_ranges.prp.min..=digit_ranges.prp.max));
     let mut prp_start = prp_start.unwrap_or_else(|| {
         if prp_digits > PRP_MAX_DIGITS_FOR_START_OFFSET {
             0
         } else {
-            rng().random_range(0..=MAX_START)
+            run_start(MAX_START).unwrap_or_else(|| shuffle_rng().random_range(0..=MAX_START))
         }
     });
     if prp_digits > 0 {
         info!("PRP initial start is {prp_start}");
+        RUN_STATS.set_prp_start(prp_start as u64);
     }
     let rph_limit: NonZeroU32 = if is_no_reserve { 6400 } else { 6100 }.try_into()?;
     let (prp_sender, prp_receiver) = channel(PRP_TASK_BUFFER_SIZE);
     let (u_sender, u_receiver) = channel(U_TASK_BUFFER_SIZE);
     let (c_sender, c_raw_receiver) = channel(C_TASK_BUFFER_SIZE);
     let mut c_receiver = PushbackReceiver::new(c_raw_receiver, &c_sender);
+    if GRAPH_SNAPSHOT_INTERVAL.load(Acquire) > 0 {
+        // Finish the submissions that were interrupted, rather than waiting for a search to find
+        // them again
+        for id in GraphSnapshot::saved_root_ids(Path::new(GRAPH_SNAPSHOT_DIR)) {
+            if c_receiver.try_push(CompositeCheckTask {
+                id,
+                digits_or_expr: id.to_string().into(),
+            }) {
+                info!("{id}: Queued C to resume from its saved divisibility graph");
+            } else {
+                warn!("{id}: No room to queue C to resume from its saved divisibility graph");
+            }
+        }
+    }
     if std::env::var("CI").is_ok() {
         EXIT_TIME.set(Instant::now().add(Duration::from_mins(355)))?;
-        COMPOSITES_OUT
-            .get_or_init(async || {
-                Mutex::new(File::options().append(true).open("composites").unwrap())
-            })
-            .await;
+        COMPOSITES_OUT.get_or_init(|| {
+            YafuDispatcher::new(
+                File::options().append(true).open("composites").unwrap(),
+                YAFU_STAGING_CAPACITY,
+            )
+        });
     }
-    let http = Arc::new(RealFactorDbClient::new(rph_limit));
+    let http =
+        Arc::new(RealFactorDbClient::new(rph_limit).with_shutdown(shutdown_receiver.clone()));
     let mut c_shutdown_receiver = shutdown_receiver.clone();
     FAILED_U_SUBMISSIONS_OUT
         .get_or_init(async || {
@@ -525,44 +1063,105 @@ async fn main() -> anyhow::Result<()> {
             )
         })
         .await;
+    if std::env::var("LOG_ACCEPTED_FACTORS").is_ok() {
+        ACCEPTED_FACTORS_OUT
+            .get_or_init(async || {
+                Mutex::new(
+                    File::options()
+                        .create(true)
+                        .append(true)
+                        .open("accepted-factors.csv")
+                        .unwrap(),
+                )
+            })
+            .await;
+    }
     let mut prp_filter: CuckooFilter<DefaultHasher> = CuckooFilter::with_capacity(4096);
 
     // Task to consume PRP's, C's and U's dispatched from the other tasks
     let mut prp_receiver = PushbackReceiver::new(prp_receiver, &prp_sender);
     let mut u_receiver = PushbackReceiver::new(u_receiver, &u_sender);
+    // Each task gets its own generator, forked before any of them start, so that its draws with
+    // SHUFFLE_SEED set don't depend on how the tasks interleave
+    let mut check_c_and_prp_rng = shuffle_rng();
+    let mut u_rng = shuffle_rng();
+    let mut c_rng = shuffle_rng();
     let check_c_and_prp_http = http.clone();
     let mut check_c_and_prp_shutdown_receiver = shutdown_receiver.clone();
+    let check_c_and_prp_heartbeat = WATCHDOG.register("check_c_and_prp");
     let check_c_and_prp = task::spawn(async_backtrace::location!().named_const("Check PRPs/Cs").frame(async move {
         let mut c_filter = CuckooFilter::with_capacity(4096);
+        let mut checked_bases = CheckedBases::load("prp-checked-bases.csv");
         let nm1_regex = Regex::new("id=([0-9]+)\">N-1<").unwrap();
         let np1_regex = Regex::new("id=([0-9]+)\">N\\+1<").unwrap();
-        let bases_regex = Regex::new("Bases checked[^\n]*\n[^\n]*([0-9, ]+)").unwrap();
         let mut bases_before_next_cpu_check = 1;
-        let cert_regex = Regex::new("(Verified|Processing)").unwrap();
+        enum NextCheck {
+            Prp((EntryId, OwnedPermit<EntryId>)),
+            C((CompositeCheckTask, OwnedPermit<CompositeCheckTask>)),
+        }
+        let mut current_work = None;
         loop {
+            check_c_and_prp_heartbeat.beat();
+            if let Some(snapshot) = current_work.take() {
+                WORK_RATIO.finish(snapshot);
+            }
             info!("check_c_and_prp: Polling for next task");
-            select! {
-                biased;
-                _ = check_c_and_prp_shutdown_receiver.recv() => {
-                    warn!("check_c_and_prp received shutdown signal; exiting");
-                    return;
+            let next_check = check_c_and_prp_heartbeat.idle_while(async {
+                if adaptive_work_ratio && WORK_RATIO.prefer_c_over_prp(&mut check_c_and_prp_rng) {
+                    select! {
+                        biased;
+                        _ = check_c_and_prp_shutdown_receiver.recv() => None,
+                        c_task = c_receiver.recv() => Some(NextCheck::C(c_task)),
+                        prp_task = prp_receiver.recv() => Some(NextCheck::Prp(prp_task)),
+                    }
+                } else {
+                    select! {
+                        biased;
+                        _ = check_c_and_prp_shutdown_receiver.recv() => None,
+                        prp_task = prp_receiver.recv() => Some(NextCheck::Prp(prp_task)),
+                        c_task = c_receiver.recv() => Some(NextCheck::C(c_task)),
+                    }
                 }
-                (id, task_return_permit) = prp_receiver.recv() => {
+            }).await;
+            let Some(next_check) = next_check else {
+                warn!("check_c_and_prp received shutdown signal; exiting");
+                return;
+            };
+            match next_check {
+                NextCheck::Prp((id, task_return_permit)) => {
+                    current_work = Some(WORK_RATIO.start(WorkCategory::Prp));
                     info!("{id}: Ready to check a PRP");
                     let mut stopped_early = false;
                     let mut bases_left = U256::MAX - 3;
-                    let Some(bases_text) = check_c_and_prp_http
-                        .retrying_get_and_decode(
+                    let bases_text = match check_c_and_prp_http
+                        .retrying_fetch(
                             &format!("https://factordb.com/frame_prime.php?id={id}"),
                             RETRY_DELAY,
                         )
-                        .await else {
-                        task_return_permit.send(id);
-                        info!("{id}: Requeued PRP");
-                        continue;
+                        .await {
+                        Fetched(bases_text) => bases_text,
+                        Throttled(retry_after) => {
+                            prp_receiver.requeue_after_throttling(id, task_return_permit);
+                            info!("{id}: Requeued PRP because FactorDB is throttling us");
+                            composites_while_waiting(
+                                Instant::now() + retry_after.unwrap_or(THROTTLED_RETRY_DELAY),
+                                check_c_and_prp_http.as_ref(),
+                                &mut c_receiver,
+                                &mut c_filter,
+                            )
+                                .await;
+                            continue;
+                        }
+                        Failed => {
+                            if prp_receiver.requeue_after_failure(id, task_return_permit) {
+                                info!("{id}: Requeued PRP");
+                            }
+                            continue;
+                        }
                     };
                     if bases_text.contains("Proven") {
                         info!("{id}: No longer PRP");
+                        checked_bases.finish(id);
                         continue;
                     }
                     #[derive(Debug)]
@@ -575,39 +1174,49 @@ async fn main() -> anyhow::Result<()> {
                     }
 
                     if let Some(mut infos) = (async {
-                        let mut results = Vec::with_capacity(2);
-                        for (parameter, regex) in [("nm1", &nm1_regex), ("np1", &np1_regex)] {
-                            if let Some(captures) = regex.captures(&bases_text) {
-                                let id_to_check = captures[1].parse::<EntryId>().unwrap();
-                                let ProcessedStatusApiResponse {
-                                    status,
-                                    factors,
-                                    ..
-                                } = check_c_and_prp_http
-                                    .known_factors_as_digits(Id(id_to_check), false, false)
-                                    .await;
-                                if factors.is_empty() && status == Some(FullyFactored) {
-                                    info!("{id}: {parameter} (ID {id_to_check}) is fully factored!");
-                                    report_primality_proof(id, parameter, check_c_and_prp_http.as_ref()).await;
-                                    return None;
+                        let ids_to_check: Vec<_> = [("nm1", &nm1_regex), ("np1", &np1_regex)]
+                            .into_iter()
+                            .filter_map(|(parameter, regex)| match regex.captures(&bases_text) {
+                                Some(captures) => Some((parameter, captures[1].parse::<EntryId>().unwrap())),
+                                None => {
+                                    error!("{id}: {parameter} ID not found: {bases_text}");
+                                    None
                                 }
-                                let divide_2 = factors.first().and_then(|f| f.as_numeric()) == Some(2);
-                                let divide_3 = factors.first().and_then(|f| f.as_numeric()) == Some(3)
-                                    || factors.get(1).and_then(|f| f.as_numeric()) == Some(3);
-                                results.push(NPlusMinus1Info {
-                                    id: id_to_check,
-                                    parameter,
-                                    known_to_divide_2: divide_2,
-                                    known_to_divide_3: divide_3,
-                                    factors: if factors.is_empty() {
-                                        None
-                                    } else {
-                                        Some(factors)
-                                    },
-                                });
-                            } else {
-                                error!("{id}: {parameter} ID not found: {bases_text}");
+                            })
+                            .collect();
+                        let mut responses = known_factors_of_ids(
+                            check_c_and_prp_http.as_ref(),
+                            ids_to_check.iter().map(|&(_, id_to_check)| id_to_check),
+                            false,
+                            false,
+                        )
+                            .await;
+                        let mut results = Vec::with_capacity(2);
+                        for (parameter, id_to_check) in ids_to_check {
+                            let ProcessedStatusApiResponse {
+                                status,
+                                factors,
+                                ..
+                            } = responses.remove(&id_to_check).unwrap_or_default();
+                            if factors.is_empty() && status == Some(FullyFactored) {
+                                info!("{id}: {parameter} (ID {id_to_check}) is fully factored!");
+                                report_primality_proof(id, parameter, check_c_and_prp_http.as_ref()).await;
+                                return None;
                             }
+                            let divide_2 = factors.first().and_then(|f| f.as_numeric()) == Some(2);
+                            let divide_3 = factors.first().and_then(|f| f.as_numeric()) == Some(3)
+                                || factors.get(1).and_then(|f| f.as_numeric()) == Some(3);
+                            results.push(NPlusMinus1Info {
+                                id: id_to_check,
+                                parameter,
+                                known_to_divide_2: divide_2,
+                                known_to_divide_3: divide_3,
+                                factors: if factors.is_empty() {
+                                    None
+                                } else {
+                                    Some(factors)
+                                },
+                            });
                         }
                         Some(results)
                     })
@@ -696,6 +1305,7 @@ async fn main() -> anyhow::Result<()> {
                                         info.id,
                                         factor,
                                         true,
+                                        SubmissionOptions::from_globals(),
                                     )
                                         .await;
                                 }
@@ -704,35 +1314,54 @@ async fn main() -> anyhow::Result<()> {
                     } else {
                         continue;
                     }
-                    let status_text = check_c_and_prp_http
-                        .retrying_get_and_decode(
-                            &format!("https://factordb.com/index.php?open=Prime&ct=Proof&id={id}"),
-                            RETRY_DELAY,
-                        ).await;
-                    if !status_text.as_ref().is_some_and(|status_text| status_text.contains("&lt;")) {
-                        error!("{id}: Failed to decode status for PRP: {status_text:?}");
-                        composites_while_waiting(
-                            Instant::now() + UNPARSEABLE_RESPONSE_RETRY_DELAY,
-                            check_c_and_prp_http.as_ref(),
-                            &mut c_receiver,
-                            &mut c_filter,
-                        )
-                            .await;
-                        task_return_permit.send(id);
-                        info!("{id}: Requeued PRP");
-                        continue;
+                    let still_prp = if let Some(status) = check_c_and_prp_http.try_get_status(id).await {
+                        status == "PRP"
+                    } else {
+                        let status_text = check_c_and_prp_http
+                            .retrying_fetch(
+                                &format!("https://factordb.com/index.php?open=Prime&ct=Proof&id={id}"),
+                                RETRY_DELAY,
+                            ).await;
+                        let retry_delay = if let Throttled(retry_after) = status_text {
+                            info!("{id}: FactorDB is throttling us while checking PRP status");
+                            Some(retry_after.unwrap_or(THROTTLED_RETRY_DELAY))
+                        } else {
+                            None
+                        };
+                        let status_text = status_text.fetched();
+                        if !status_text.as_ref().is_some_and(|status_text| status_text.contains("&lt;")) {
+                            if retry_delay.is_none() {
+                                error!("{id}: Failed to decode status for PRP: {status_text:?}");
+                            }
+                            composites_while_waiting(
+                                Instant::now() + retry_delay.unwrap_or(UNPARSEABLE_RESPONSE_RETRY_DELAY),
+                                check_c_and_prp_http.as_ref(),
+                                &mut c_receiver,
+                                &mut c_filter,
+                            )
+                                .await;
+                            if retry_delay.is_some() {
+                                prp_receiver.requeue_after_throttling(id, task_return_permit);
+                                info!("{id}: Requeued PRP");
+                            } else if prp_receiver.requeue_after_failure(id, task_return_permit) {
+                                info!("{id}: Requeued PRP");
+                            }
+                            continue;
+                        };
+                        let status_text = status_text.unwrap();
+                        !status_text.contains(" is prime") && status_text.contains("PRP")
                     };
-                    let status_text = status_text.unwrap();
-                    if status_text.contains(" is prime") || !status_text.contains("PRP") {
+                    if !still_prp {
                         info!("{id}: No longer PRP");
+                        checked_bases.finish(id);
                         continue;
                     }
-                    if let Some(bases) = bases_regex.captures(&bases_text) {
-                        for base in bases[1].split(", ") {
-                            let Ok(base) = base.parse::<u8>() else {
-                                error!("Invalid PRP-check base: {:?}", base);
-                                continue;
-                            };
+                    let Some(bases) = parse_checked_bases(&bases_text) else {
+                        error!("{id}: Failed to parse the list of checked bases: {bases_text}");
+                        continue;
+                    };
+                    if !bases.is_empty() {
+                        for base in bases {
                             bases_left &= !(U256::from(1) << base);
                         }
                         info!(
@@ -747,22 +1376,48 @@ async fn main() -> anyhow::Result<()> {
                     } else {
                         info!("{id}: no bases checked yet");
                     }
+                    bases_left = checked_bases.remaining(id, bases_left);
                     if bases_left == U256::from(0) {
                         info!("{id}: all bases already checked");
+                        checked_bases.finish(id);
                         continue;
                     }
                     for base in (0..=(u8::MAX as usize)).filter(|i| bases_left.bit(*i)) {
+                        // A PRP can have hundreds of bases left, so each one counts as progress
+                        check_c_and_prp_heartbeat.beat();
+                        if check_c_and_prp_shutdown_receiver.check_for_shutdown() {
+                            warn!("{id}: Stopping PRP check at base {base} because of shutdown");
+                            return;
+                        }
                         let url = format!(
                             "https://factordb.com/index.php?id={id}&open=prime&basetocheck={base}"
                         );
-                        let Some(text) = check_c_and_prp_http.retrying_get_and_decode(&url, RETRY_DELAY).await else {
-                            error!("{id}: PRP check with base {base} failed");
-                            continue;
+                        let text = match check_c_and_prp_http.retrying_fetch(&url, RETRY_DELAY).await {
+                            Fetched(text) => text,
+                            Throttled(retry_after) => {
+                                warn!("{id}: Throttled before PRP check with base {base}");
+                                prp_receiver.requeue_after_throttling(id, task_return_permit);
+                                info!("{id}: Requeued PRP");
+                                composites_while_waiting(
+                                    Instant::now() + retry_after.unwrap_or(THROTTLED_RETRY_DELAY),
+                                    check_c_and_prp_http.as_ref(),
+                                    &mut c_receiver,
+                                    &mut c_filter,
+                                )
+                                    .await;
+                                break;
+                            }
+                            Failed => {
+                                error!("{id}: PRP check with base {base} failed");
+                                continue;
+                            }
                         };
-                        if !text.contains(">number<") {
+                        let outcome = PrpCheckOutcome::parse(&text);
+                        if outcome == PrpCheckOutcome::Unparseable {
                             error!("Failed to decode result from {url}: {text}");
-                            task_return_permit.send(id);
-                            info!("{id}: Requeued PRP");
+                            if prp_receiver.requeue_after_failure(id, task_return_permit) {
+                                info!("{id}: Requeued PRP");
+                            }
                             composites_while_waiting(
                                 Instant::now() + UNPARSEABLE_RESPONSE_RETRY_DELAY,
                                 check_c_and_prp_http.as_ref(),
@@ -772,6 +1427,8 @@ async fn main() -> anyhow::Result<()> {
                                 .await;
                             break;
                         }
+                        RUN_STATS.record_prp_check();
+                        checked_bases.record(id, base as u8);
                         throttle_if_necessary(
                             check_c_and_prp_http.as_ref(),
                             &mut c_receiver,
@@ -780,31 +1437,28 @@ async fn main() -> anyhow::Result<()> {
                             &mut c_filter,
                         )
                             .await;
-                        if cert_regex.is_match(&text) {
-                            info!("{}: No longer PRP (has certificate)", id);
-                            stopped_early = true;
-                            break;
-                        }
-                        if text.contains("set to C") {
-                            info!("{}: No longer PRP (ruled out by PRP check)", id);
-                            stopped_early = true;
-                            break;
-                        }
-                        if !text.contains("PRP") {
-                            info!("{}: No longer PRP (solved by N-1/N+1 or factor)", id);
-                            stopped_early = true;
-                            break;
-                        }
+                        let reason = match outcome {
+                            PrpCheckOutcome::StillPrp | PrpCheckOutcome::Unparseable => continue,
+                            PrpCheckOutcome::HasCertificate => "has certificate",
+                            PrpCheckOutcome::RuledOutComposite => "ruled out by PRP check",
+                            PrpCheckOutcome::SolvedByNpm1 => "solved by N-1/N+1 or factor",
+                        };
+                        info!("{id}: No longer PRP ({reason})");
+                        stopped_early = true;
+                        break;
                     }
-                    if !stopped_early {
+                    if stopped_early {
+                        checked_bases.finish(id);
+                    } else if checked_bases.remaining(id, bases_left) == U256::from(0) {
                         info!("{}: all bases now checked", id);
+                        checked_bases.finish(id);
                     }
                 }
 
-                c_task = c_receiver.recv() => {
-                    let (CompositeCheckTask {id, digits_or_expr}, return_permit) = c_task;
+                NextCheck::C((CompositeCheckTask {id, digits_or_expr}, return_permit)) => {
+                    current_work = Some(WORK_RATIO.start(WorkCategory::C));
                     info!("{id}: Ready to check a C");
-                    check_composite(check_c_and_prp_http.as_ref(), &mut c_filter, id, digits_or_expr, return_permit).await;
+                    check_composite(check_c_and_prp_http.as_ref(), COMPOSITES_OUT.get(), &mut c_receiver, &mut c_filter, id, digits_or_expr, return_permit).await;
                 }
             }
         }
@@ -812,13 +1466,15 @@ async fn main() -> anyhow::Result<()> {
     let check_u = if u_digits != Some(0) {
         let mut check_u_shutdown_receiver = shutdown_receiver.clone();
         let check_u_http = http.clone();
+        let check_u_heartbeat = WATCHDOG.register("check_u");
         task::spawn(async_backtrace::location!().named_const("Check Us").frame(async move {
             info!("check_u task starting");
             let mut next_unknown_attempt = Instant::now();
             let many_digits_regex =
                 Regex::new("&lt;([2-9]|[0-9]+[0-9])[0-9][0-9][0-9][0-9][0-9]&gt;").unwrap();
-            let u_status_regex = Regex::new("(Assigned|already|Please wait|>CF?<|>P<|>PRP<|>FF<)").unwrap();
+            let u_status_regex = Regex::new("(Assigned|already|>CF?<|>P<|>PRP<|>FF<)").unwrap();
             loop {
+                check_u_heartbeat.beat();
                 info!("check_u: Polling for next task");
                 select! {
                     biased;
@@ -826,14 +1482,25 @@ async fn main() -> anyhow::Result<()> {
                         warn!("check_u received shutdown signal; exiting");
                         return;
                     }
-                    (id, task_return_permit) = sleep_until(next_unknown_attempt).then(|_| u_receiver.recv())
+                    (id, task_return_permit) = check_u_heartbeat.idle_while(sleep_until(next_unknown_attempt).then(|_| u_receiver.recv()))
                     => {
                         info!("{id}: Ready to check a U");
                         let url = format!("https://factordb.com/index.php?id={id}&prp=Assign+to+worker");
-                        let Some(result) = check_u_http.retrying_get_and_decode(&url, RETRY_DELAY).await else {
-                            task_return_permit.send(id);
-                            info!("{id}: Requeued U");
-                            continue;
+                        let result = match check_u_http.retrying_fetch(&url, RETRY_DELAY).await {
+                            Fetched(result) => result,
+                            Throttled(retry_after) => {
+                                warn!("{id}: Got 'please wait' for U");
+                                next_unknown_attempt = Instant::now() + retry_after.unwrap_or(unknown_status_check_backoff);
+                                u_receiver.requeue_after_throttling(id, task_return_permit);
+                                info!("{id}: Requeued U");
+                                continue;
+                            }
+                            Failed => {
+                                if u_receiver.requeue_after_failure(id, task_return_permit) {
+                                    info!("{id}: Requeued U");
+                                }
+                                continue;
+                            }
                         };
                         if let Some(status) = u_status_regex.captures_iter(&result).next() {
                             match status.get(1) {
@@ -843,20 +1510,15 @@ async fn main() -> anyhow::Result<()> {
                                     } else {
                                         error!("{id}: Failed to decode status for U: {result}");
                                         next_unknown_attempt = Instant::now() + UNPARSEABLE_RESPONSE_RETRY_DELAY;
-                                        task_return_permit.send(id);
-                                        info!("{id}: Requeued U");
+                                        if u_receiver.requeue_after_failure(id, task_return_permit) {
+                                            info!("{id}: Requeued U");
+                                        }
                                     }
                                 }
                                 Some(matched_status) => match matched_status.as_str() {
                                     "Assigned" => {
                                          info!("Assigned PRP check for unknown-status number with ID {id}");
                                     }
-                                    "Please wait" => {
-                                        warn!("{id}: Got 'please wait' for U");
-                                        next_unknown_attempt = Instant::now() + unknown_status_check_backoff;
-                                        task_return_permit.send(id);
-                                        info!("{id}: Requeued U");
-                                    }
                                     _ => {
                                         warn!("{id}: U is already being checked");
                                     }
@@ -867,8 +1529,9 @@ async fn main() -> anyhow::Result<()> {
                         } else {
                             error!("{id}: Failed to decode status for U from result: {result}");
                             next_unknown_attempt = Instant::now() + UNPARSEABLE_RESPONSE_RETRY_DELAY;
-                            task_return_permit.send(id);
-                            info!("{id}: Requeued U");
+                            if u_receiver.requeue_after_failure(id, task_return_permit) {
+                                info!("{id}: Requeued U");
+                            }
                         }
                     }
                 }
@@ -881,22 +1544,22 @@ async fn main() -> anyhow::Result<()> {
         // Task to queue unknowns
         let mut queue_u_shutdown_receiver = shutdown_receiver.clone();
         let u_http = http.clone();
+        let queue_u_heartbeat = WATCHDOG.register("queue_u");
         let mut u_start = if u_digits.is_some() {
             0
         } else {
-            rng().random_range(0..=MAX_START)
+            u_rng.random_range(0..=MAX_START)
         };
         task::spawn(async_backtrace::location!().named_const("Queue U's").frame(async move {
             let mut u_filter: CuckooFilter<DefaultHasher> = CuckooFilter::with_capacity(4096);
             loop {
+                queue_u_heartbeat.beat();
                 if queue_u_shutdown_receiver.check_for_shutdown() {
                     warn!("Queue U's task received shutdown signal; exiting");
                     return;
                 }
-                let digits = u_digits.unwrap_or_else(|| {
-                    rng().random_range(U_MIN_DIGITS..=U_MAX_DIGITS)
-                });
-                if u_digits.is_none() && digits == U_MIN_DIGITS {
+                let digits = u_digits.unwrap_or_else(|| u_digit_weights.sample(&mut u_rng));
+                if u_digits.is_none() && digits == digit_ranges.u.min {
                     u_start = 0;
                 }
                 let u_search_url =
@@ -918,18 +1581,32 @@ async fn main() -> anyhow::Result<()> {
                         advance_start += 1;
                         continue;
                     }
-                    let digits_or_expr = Factor::from(digits_or_expr);
-                    if graph::find_and_submit_factors(
+                    let digits_or_expr = Factor::parse(digits_or_expr).await;
+                    let too_large = too_large_for_prp_check(
+                        &digits_or_expr,
+                        U_MAX_PRP_CHECK_DIGITS.load(Acquire),
+                    );
+                    let digit_count = estimate_digit_count(&digits_or_expr);
+                    let outcome = graph::find_and_submit_factors(
                         &*u_http,
                         u_id,
                         digits_or_expr,
                         false,
+                        SubmissionOptions::from_globals(),
                     )
-                        .await {
+                        .await;
+                    if let SubmissionOutcome::Accepted(factors) = outcome {
+                        WORK_RATIO.record_untracked_factors(factors);
+                    }
+                    if outcome.made_progress() {
                         info!("{u_id}: Skipping PRP check because this former U is now CF or FF");
+                    } else if too_large {
+                        warn!("{u_id}: Not queueing U, because at about {digit_count} digits it's too large for a PRP check");
+                        advance_start += 1;
                     } else {
-                        if u_sender.send(u_id).await.is_ok() {
+                        if queue_u_heartbeat.idle_while(u_sender.send(u_id)).await.is_ok() {
                             info!("{u_id}: Queued U");
+                            RUN_STATS.record_u_queued();
                         }
                         advance_start += 1;
                     }
@@ -938,7 +1615,7 @@ async fn main() -> anyhow::Result<()> {
                     u_start += advance_start;
                     u_start %= MAX_START + 1;
                 } else if advance_start != 0 {
-                    u_start = rng().random_range(0..=MAX_START);
+                    u_start = u_rng.random_range(0..=MAX_START);
                 }
             }
         }))
@@ -948,7 +1625,7 @@ async fn main() -> anyhow::Result<()> {
     let mut backtraces_paused_task = None;
     // Monitoring task: print stats periodically
     task::spawn(async move {
-        let Ok((mut sigint, mut sigterm)) = signal_installer.await else {
+        let Ok((mut sigint, mut sigterm, mut sigusr1, mut sighup)) = signal_installer.await else {
             error!("Failed to install signal handlers!");
             abort();
         };
@@ -966,12 +1643,23 @@ async fn main() -> anyhow::Result<()> {
                     warn!("Received SIGINT; signaling tasks to exit");
                     break;
                 }
+                _ = sigusr1.next() => {
+                    warn!("Received SIGUSR1; pausing requests to FactorDB until SIGHUP");
+                    PAUSED.store(true, Release);
+                }
+                _ = sighup.next() => {
+                    warn!("Received SIGHUP; resuming requests to FactorDB");
+                    PAUSED.store(false, Release);
+                }
                 _ = sleep_until(next_backtrace) => {
                     log_stats(&mut reg, &mut sys, &mut backtraces_paused_task);
+                    WATCHDOG.check(Duration::from_secs(WATCHDOG_TIMEOUT_SECS.load(Acquire)));
                     next_backtrace = Instant::now() + STATS_INTERVAL;
                 }
             }
         }
+        // Paused requests would otherwise keep tasks from ever exiting
+        PAUSED.store(false, Release);
         if let Err(e) = shutdown_sender.send(()) {
             error!("Error sending shutdown signal: {e}");
         }
@@ -984,9 +1672,11 @@ async fn main() -> anyhow::Result<()> {
     });
     let queue_c: JoinHandle<Result<(), SendError<()>>> = if c_digits != Some(0) {
         let c_http = http.clone();
+        let queue_c_heartbeat = WATCHDOG.register("queue_c");
         task::spawn(async move {
             let mut c_tasks = Vec::with_capacity(C_RESULTS_PER_PAGE);
             loop {
+                queue_c_heartbeat.beat();
                 let select_start = Instant::now();
                 select! {
                     biased;
@@ -994,41 +1684,51 @@ async fn main() -> anyhow::Result<()> {
                         warn!("queue_c received shutdown signal; exiting");
                         return Ok(());
                     }
-                    c_permits = c_sender.reserve_many(C_RESULTS_PER_PAGE) => {
+                    c_permits = queue_c_heartbeat.idle_while(c_sender.reserve_many(C_RESULTS_PER_PAGE)) => {
                         let mut c_permits = c_permits?;
                         info!("Ready to send C's from new search after {:?}", Instant::now() - select_start);
                         while c_tasks.is_empty() {
-                            let start = if c_digits.is_some_and(|digits| digits < C_MIN_DIGITS) {
+                            let start = if c_digits.is_some_and(|digits| digits < digit_ranges.c.min) {
                                 0
+                            } else if let Some(c_cursor) = c_cursor.as_ref() {
+                                c_cursor.start()
                             } else {
-                                rng().random_range(0..=MAX_START)
+                                c_rng.random_range(0..=MAX_START)
                             };
-                            let mut results_per_page = C_RESULTS_PER_PAGE;
-                            let mut composites_page = None;
-                            while composites_page.is_none() && results_per_page > 0 {
-                                if c_shutdown_receiver.check_for_shutdown() {
-                                    return Ok(());
-                                }
-                                let digits = c_digits.unwrap_or_else(|| {
-                                    rng().random_range(C_MIN_DIGITS..=C_MAX_DIGITS)
-                                });
-                                info!("Retrieving {digits}-digit C's starting from {start}");
-                                composites_page = c_http.try_get_and_decode(
-                                    &format!("https://factordb.com/listtype.php?t=3&perpage={results_per_page}&start={start}&mindig={digits}")
-                                ).await;
-                                if composites_page.is_none() {
-                                    results_per_page >>= 1;
-                                    sleep(SEARCH_RETRY_DELAY).await;
-                                }
+                            let digits = c_digits
+                                .unwrap_or_else(|| c_digit_weights.sample(&mut c_rng));
+                            let composites_pages = fetch_composites_batch(
+                                &*c_http,
+                                &mut c_shutdown_receiver,
+                                digits,
+                                digit_ranges.c,
+                                start,
+                                C_PAGE_SIZE.load(Acquire),
+                                |page| c_http.read_ids_and_exprs(page).next().is_some(),
+                            ).await;
+                            if c_shutdown_receiver.check_for_shutdown() {
+                                return Ok(());
+                            }
+                            if composites_pages.is_empty() {
+                                continue;
                             }
-                            info!("{results_per_page} C search results retrieved");
-                            c_tasks.extend(c_http
-                                .read_ids_and_exprs(&composites_page.unwrap())
-                                .map(|(id, expr)| CompositeCheckTask {
-                                    id,
-                                    digits_or_expr: expr.into(),
-                                }));
-                            c_tasks.shuffle(&mut rng());
+                            let results_per_page: usize = composites_pages
+                                .iter()
+                                .map(|page| page.results_per_page)
+                                .sum();
+                            info!("{results_per_page} C search results retrieved in {} pages", composites_pages.len());
+                            if let Some(c_cursor) = c_cursor.as_mut() {
+                                c_cursor.advance(results_per_page);
+                            }
+                            for page in &composites_pages {
+                                c_tasks.extend(c_http
+                                    .read_ids_and_exprs(&page.page)
+                                    .map(|(id, expr)| CompositeCheckTask {
+                                        id,
+                                        digits_or_expr: expr.into(),
+                                    }));
+                            }
+                            c_tasks.shuffle(&mut c_rng);
                         }
                         let c_sent = c_tasks.len();
                         for task in c_tasks.drain(..) {
@@ -1054,6 +1754,7 @@ async fn main() -> anyhow::Result<()> {
                     let _ = check_u.await;
                     let _ = queue_c.await;
                     let _ = check_c_and_prp.await;
+                    RUN_STATS.log_summary();
                     return Ok(());
                 }
                 prp_permits = prp_sender.reserve_many(PRP_RESULTS_PER_PAGE) => {
@@ -1063,20 +1764,39 @@ async fn main() -> anyhow::Result<()> {
                     let mut results_text = None;
                     while results_text.is_none() && results_per_page > 0 {
                         let prp_search_url = format!("https://factordb.com/listtype.php?t=1&mindig={prp_digits}&perpage={results_per_page}&start={prp_start}");
-                        let Some(text) = http.try_get_and_decode(&prp_search_url).await else {
-                            sleep(SEARCH_RETRY_DELAY).await;
-                            results_per_page >>= 1;
-                            continue;
-                        };
-                        results_text = Some(text);
-                        break;
+                        match http.try_fetch(&prp_search_url).await {
+                            Fetched(text) => {
+                                results_text = Some(text);
+                                break;
+                            }
+                            Throttled(retry_after) => {
+                                sleep(retry_after.unwrap_or(THROTTLED_RETRY_DELAY)).await;
+                            }
+                            Failed => {
+                                sleep(SEARCH_RETRY_DELAY).await;
+                                results_per_page >>= 1;
+                            }
+                        }
                     }
                     info!("{results_per_page} PRP search results retrieved");
                     let Some(results_text) = results_text else {
                         continue 'queue_tasks;
                     };
-                    for ((prp_id, _), prp_permit) in http.read_ids_and_exprs(&results_text).zip(prp_permits)
-                    {
+                    let mut prps: Vec<_> = http.read_ids_and_exprs(&results_text).collect();
+                    if prioritize_safe_primes {
+                        let mut prioritized = Vec::with_capacity(prps.len());
+                        let mut others = Vec::with_capacity(prps.len());
+                        for (prp_id, expr) in prps {
+                            if has_prime_sibling(http.as_ref(), prp_id, expr).await {
+                                prioritized.push((prp_id, expr));
+                            } else {
+                                others.push((prp_id, expr));
+                            }
+                        }
+                        prioritized.append(&mut others);
+                        prps = prioritized;
+                    }
+                    for ((prp_id, _), prp_permit) in prps.into_iter().zip(prp_permits) {
                         if !matches!(prp_filter.test_and_add(&prp_id), Ok(true)) {
                             warn!("{prp_id}: Skipping duplicate PRP");
                             continue;
@@ -1090,8 +1810,8 @@ async fn main() -> anyhow::Result<()> {
                         } else {
                             1
                         };
-                        if prp_digits > PRP_MAX_DIGITS {
-                            prp_digits = PRP_MIN_DIGITS;
+                        if prp_digits > digit_ranges.prp.max {
+                            prp_digits = digit_ranges.prp.min;
                         }
                         prp_start = 0;
                     } else {
@@ -1102,6 +1822,7 @@ async fn main() -> anyhow::Result<()> {
                             prp_digits += 1;
                         }
                     }
+                    RUN_STATS.set_prp_start(prp_start as u64);
                 }
             }
         }
@@ -1112,6 +1833,7 @@ async fn main() -> anyhow::Result<()> {
         let _ = check_u.await;
         let _ = queue_c.await;
         let _ = check_c_and_prp.await;
+        RUN_STATS.log_summary();
         Ok(())
     }
 }
@@ -1125,3 +1847,133 @@ pub enum ReportFactorResult {
 }
 
 const MAX_ID_EQUAL_TO_VALUE: EntryId = 999_999_999_999_999_999;
+
+#[cfg(test)]
+mod tests {
+    use crate::channel::PushbackReceiver;
+    use crate::net::NumberStatus::PartlyFactoredComposite;
+    use crate::net::{MockFactorDbClient, ProcessedStatusApiResponse};
+    use crate::{Factor, MAX_KNOWN_FACTORS, check_composite};
+    use cuckoofilter::CuckooFilter;
+    use std::sync::atomic::Ordering::Acquire;
+    use tokio::sync::mpsc::channel;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_crowded_composite_not_requeued_without_yafu() {
+        let max_known_factors = MAX_KNOWN_FACTORS.load(Acquire);
+        let mut http = MockFactorDbClient::new();
+        http.expect_try_get_and_decode().returning(|_| None);
+        http.expect_known_factors_as_digits()
+            .returning(move |_, _, _| {
+                let mut factors = vec![Factor::Numeric(2); max_known_factors];
+                factors.push(Factor::from("2^1277-1"));
+                ProcessedStatusApiResponse {
+                    status: Some(PartlyFactoredComposite),
+                    factors: factors.into_boxed_slice(),
+                    id: Some(1),
+                }
+            });
+        let (sender, receiver) = channel(8);
+        let mut c_receiver = PushbackReceiver::new(receiver, &sender);
+        let (return_sender, mut return_receiver) = channel(1);
+        let return_permit = return_sender.reserve_owned().await.unwrap();
+        assert!(
+            check_composite(
+                &http,
+                None,
+                &mut c_receiver,
+                &mut CuckooFilter::with_capacity(16),
+                1,
+                "1".into(),
+                return_permit
+            )
+            .await
+        );
+        assert!(return_receiver.try_recv().is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_composite_refused_by_yafu_requeued_after_checks() {
+        use crate::yafu::YafuDispatcher;
+        use std::io::{self, Write};
+        use std::sync::mpsc::{Receiver, sync_channel};
+
+        /// A FIFO whose reader never reads.
+        struct StalledFifo(Receiver<()>);
+
+        impl Write for StalledFifo {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                self.0.recv().map_err(io::Error::other)?;
+                Err(io::Error::other("FIFO closed"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let (release, stalled) = sync_channel(0);
+        let yafu = YafuDispatcher::new(StalledFifo(stalled), 1);
+        while yafu.try_dispatch("2^1001-1".into()) {}
+
+        let max_known_factors = MAX_KNOWN_FACTORS.load(Acquire);
+        let mut http = MockFactorDbClient::new();
+        // The check is triggered, but that doesn't make up for yafu having no room
+        http.expect_try_get_and_decode()
+            .returning(|_| Some("".into()));
+        http.expect_known_factors_as_digits()
+            .returning(move |_, _, _| {
+                let mut factors = vec![Factor::Numeric(2); max_known_factors];
+                factors.push(Factor::from("2^1277-1"));
+                ProcessedStatusApiResponse {
+                    status: Some(PartlyFactoredComposite),
+                    factors: factors.into_boxed_slice(),
+                    id: Some(1),
+                }
+            });
+        let (sender, receiver) = channel(8);
+        let mut c_receiver = PushbackReceiver::new(receiver, &sender);
+        let (return_sender, mut return_receiver) = channel(1);
+        let return_permit = return_sender.reserve_owned().await.unwrap();
+        assert!(
+            !check_composite(
+                &http,
+                Some(&yafu),
+                &mut c_receiver,
+                &mut CuckooFilter::with_capacity(16),
+                1,
+                "1".into(),
+                return_permit
+            )
+            .await
+        );
+        assert_eq!(return_receiver.try_recv().unwrap().id, 1);
+        drop(release);
+    }
+
+    #[test]
+    fn test_number_specifier_from_str() {
+        use crate::NumberSpecifier::{self, Expression, Id};
+        use std::borrow::Cow::Owned;
+
+        assert_eq!("12345".parse::<NumberSpecifier>(), Ok(Id(12345)));
+        assert_eq!(
+            " 989999999999999999\n".parse::<NumberSpecifier>(),
+            Ok(Id(989_999_999_999_999_999))
+        );
+        // Close to the limit, FactorDB has to be asked which ID it uses
+        assert_eq!(
+            "999999999999999999".parse::<NumberSpecifier>(),
+            Ok(Expression(Owned(Factor::Numeric(999_999_999_999_999_999))))
+        );
+        // FactorDB gives larger numbers IDs unrelated to their values
+        assert_eq!(
+            "1000000000000000000".parse::<NumberSpecifier>(),
+            Ok(Expression(Owned(Factor::from("1000000000000000000"))))
+        );
+        assert_eq!(
+            "2^127-1".parse::<NumberSpecifier>(),
+            Ok(Expression(Owned(Factor::from("2^127-1"))))
+        );
+    }
+}
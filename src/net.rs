@@ -3,21 +3,30 @@ use crate::ReportFactorResult::{Accepted, AlreadyFullyFactored, DoesNotDivide, O
 use crate::algebraic::Factor::Numeric;
 use crate::algebraic::{NumericFactor, find_factors_of_numeric, get_numeric_value_cache};
 use crate::graph::EntryId;
+use crate::monitor::Monitor;
+use crate::net::FetchOutcome::{Failed, Fetched, Throttled};
 use crate::net::NumberStatus::{
     FullyFactored, PartlyFactoredComposite, Prime, UnfactoredComposite, Unknown,
 };
+use crate::stats::RUN_STATS;
+use crate::{
+    ACCEPTED_FACTORS_OUT, EXIT_TIME, FAILED_U_SUBMISSIONS_OUT, FactorSubmission,
+    MAX_CPU_BUDGET_TENTHS, MAX_ID_EQUAL_TO_VALUE, ReportFactorResult, SUBMIT_FACTOR_MAX_ATTEMPTS,
+    create_cache,
+};
 use crate::{BasicCache, get_from_cache};
 use crate::{
-    EXIT_TIME, FAILED_U_SUBMISSIONS_OUT, FactorSubmission, MAX_CPU_BUDGET_TENTHS,
-    MAX_ID_EQUAL_TO_VALUE, ReportFactorResult, SUBMIT_FACTOR_MAX_ATTEMPTS, create_cache,
+    Factor, NumberLength, NumberSpecifier, NumberStatusApiResponse, RETRY_DELAY,
+    THROTTLED_RETRY_DELAY,
 };
-use crate::{Factor, NumberSpecifier, NumberStatusApiResponse, RETRY_DELAY};
+use anyhow::{anyhow, bail};
 use async_backtrace::framed;
 use atomic_time::AtomicInstant;
 use core::cell::RefCell;
 use core::fmt::{Display, Formatter};
 use curl::easy::{Easy2, Handler, WriteError};
 use futures_util::TryFutureExt;
+use futures_util::stream::{self, StreamExt};
 use governor::middleware::StateInformationMiddleware;
 use governor::{DefaultDirectRateLimiter, Quota, RateLimiter};
 use hipstr::HipStr;
@@ -26,16 +35,21 @@ use log::{debug, error, info, warn};
 use regex::{Regex, RegexBuilder};
 use reqwest::Client;
 use reqwest::Response;
-use serde_json::from_str;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, from_str};
 use std::cmp;
+use std::collections::{BTreeMap, BTreeSet};
 use std::io::Write;
 use std::mem::swap;
 use std::num::NonZeroU32;
 use std::process::exit;
+use std::string::FromUtf8Error;
+use std::sync::OnceLock;
 use std::sync::atomic::Ordering::{Acquire, Release};
-use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
-use std::time::Duration;
-use tokio::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, AtomicUsize, Ordering};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::select;
+use tokio::sync::Semaphore;
 use tokio::task::block_in_place;
 use tokio::time::{Instant, sleep, sleep_until};
 use urlencoding::encode;
@@ -48,6 +62,385 @@ const PARALLEL_REQUEST_THROTTLING_DURATION: Duration = Duration::from_secs(5);
 
 const REQWEST_MAX_URL_LEN: usize = (u16::MAX - 1) as usize;
 
+/// Consecutive failed requests within [CIRCUIT_BREAKER_WINDOW] that open the circuit breaker.
+const CIRCUIT_BREAKER_THRESHOLD: usize = 10;
+const CIRCUIT_BREAKER_WINDOW: Duration = Duration::from_mins(5);
+const CIRCUIT_BREAKER_COOL_DOWN: Duration = Duration::from_mins(10);
+
+/// How long to back off after an HTTP 5xx response, which means FactorDB is overloaded or down, so
+/// retrying right away would only add to its load.
+const SERVER_ERROR_BACKOFF: Duration = Duration::from_mins(2);
+
+/// Result of a GET request that tells FactorDB's throttle pages apart from other failures, so that
+/// callers can back off rather than retrying right away or trying to decode the page.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FetchOutcome {
+    Fetched(HipStr<'static>),
+    /// FactorDB asked us to wait, for the given duration if it said how long.
+    Throttled(Option<Duration>),
+    Failed,
+}
+
+impl FetchOutcome {
+    pub fn fetched(self) -> Option<HipStr<'static>> {
+        match self {
+            Fetched(text) => Some(text),
+            Throttled(_) | Failed => None,
+        }
+    }
+}
+
+impl From<FactorDbError> for FetchOutcome {
+    fn from(error: FactorDbError) -> Self {
+        match error {
+            FactorDbError::Throttled(retry_after) => Throttled(retry_after),
+            FactorDbError::TooManyParallelRequests => {
+                Throttled(Some(PARALLEL_REQUEST_THROTTLING_DURATION))
+            }
+            FactorDbError::HttpStatus(500..=599) => Throttled(Some(SERVER_ERROR_BACKOFF)),
+            _ => Failed,
+        }
+    }
+}
+
+/// Why a request to FactorDB didn't produce a usable page.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum FactorDbError {
+    /// Connecting or receiving the response took longer than the timeout.
+    Timeout,
+    /// The connection couldn't be made or was lost.
+    Connection(String),
+    /// FactorDB or the proxy in front of it returned an HTTP error.
+    HttpStatus(u16),
+    EmptyBody,
+    /// The response wasn't valid text.
+    Decode(String),
+    /// FactorDB asked us to wait, for the given duration if it said how long.
+    Throttled(Option<Duration>),
+    /// FactorDB is already handling as many of our requests at once as it allows.
+    TooManyParallelRequests,
+    /// Shutdown began before the response arrived, so the request was abandoned.
+    Cancelled,
+}
+
+impl Display for FactorDbError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> core::fmt::Result {
+        match self {
+            FactorDbError::Timeout => f.write_str("request timed out"),
+            FactorDbError::Connection(e) => write!(f, "connection failed: {e}"),
+            FactorDbError::HttpStatus(code) => write!(f, "HTTP response code {code}"),
+            FactorDbError::EmptyBody => f.write_str("empty response"),
+            FactorDbError::Decode(e) => write!(f, "couldn't decode response: {e}"),
+            FactorDbError::Throttled(Some(retry_after)) => {
+                write!(f, "asked to wait {retry_after:?}")
+            }
+            FactorDbError::Throttled(None) => f.write_str("asked to wait"),
+            FactorDbError::TooManyParallelRequests => f.write_str("parallel-request limit reached"),
+            FactorDbError::Cancelled => f.write_str("cancelled by shutdown"),
+        }
+    }
+}
+
+impl std::error::Error for FactorDbError {}
+
+impl From<reqwest::Error> for FactorDbError {
+    fn from(e: reqwest::Error) -> Self {
+        if e.is_timeout() {
+            FactorDbError::Timeout
+        } else if e.is_decode() || e.is_body() {
+            FactorDbError::Decode(e.to_string())
+        } else if let Some(status) = e.status() {
+            FactorDbError::HttpStatus(status.as_u16())
+        } else {
+            FactorDbError::Connection(e.to_string())
+        }
+    }
+}
+
+impl From<curl::Error> for FactorDbError {
+    fn from(e: curl::Error) -> Self {
+        if e.is_operation_timedout() {
+            FactorDbError::Timeout
+        } else {
+            FactorDbError::Connection(e.to_string())
+        }
+    }
+}
+
+impl From<FromUtf8Error> for FactorDbError {
+    fn from(e: FromUtf8Error) -> Self {
+        FactorDbError::Decode(e.to_string())
+    }
+}
+
+/// Most requests to send to FactorDB between resets of its resource limits, even if its own limits
+/// would allow more. Overridden by the `MAX_REQUESTS_PER_CYCLE` environment variable.
+pub static MAX_REQUESTS_PER_CYCLE: AtomicU32 = AtomicU32::new(u32::MAX);
+
+/// How long a resource-limit cycle is assumed to last when we haven't seen when the current one
+/// ends.
+const RESOURCE_CYCLE_DURATION: Duration = Duration::from_hours(1);
+
+/// Most requests to FactorDB that may be in flight at once, on every path. FactorDB refuses
+/// requests past its own parallel-request limit, so by default they're sent one at a time.
+/// Overridden by the `MAX_PARALLEL_REQUESTS` environment variable.
+pub static MAX_PARALLEL_REQUESTS: AtomicUsize = AtomicUsize::new(1);
+
+/// Numbers from here up to [MAX_ID_EQUAL_TO_VALUE] are close enough to the limit of FactorDB's
+/// practice of using a small number's value as its ID that we ask FactorDB which ID it uses before
+/// relying on it.
+pub const MIN_BORDERLINE_ID_EQUAL_TO_VALUE: EntryId = 990_000_000_000_000_000;
+
+/// The value of `id` if it's a number small enough that its value may be its ID.
+fn value_as_id(id: &NumberSpecifier) -> Option<EntryId> {
+    let value = match id {
+        Id(entry_id) => *entry_id,
+        Expression(x) => {
+            if let Numeric(n) = **x {
+                n
+            } else if let Some(Some(n)) = get_from_cache(get_numeric_value_cache(), x.as_ref()) {
+                n
+            } else {
+                return None;
+            }
+        }
+    };
+    (value <= MAX_ID_EQUAL_TO_VALUE).then_some(value)
+}
+
+/// The part of a number's `index.php` page that lists its factors: whatever follows the `=` in
+/// the number's row of the result table. Links elsewhere on the page, such as the navigation
+/// and the previous and next numbers, aren't factors. Returns None if the page has no result
+/// table, and an empty string if the number has no factors listed.
+fn listed_factors_section(page: &str) -> Option<&str> {
+    let (_, table) = page.split_once(">number<")?;
+    let (table, _) = table.split_once("</table>")?;
+    Some(table.split_once(" = ").map_or("", |(_, factors)| factors))
+}
+
+/// Seconds for which a fetched page is reused when the same URL is requested again.
+pub static PAGE_CACHE_TTL_SECS: AtomicU64 = AtomicU64::new(30);
+
+/// Minimum seconds between two requests for the same search URL, so that a search that just failed
+/// or came back empty isn't immediately issued again. Overridden by the `MIN_SEARCH_INTERVAL_SECS`
+/// environment variable.
+pub static MIN_SEARCH_INTERVAL_SECS: AtomicU64 = AtomicU64::new(10);
+
+/// Set while the operator has paused scraping (with SIGUSR1), so that FactorDB's capacity goes to
+/// other users. No new request starts until it's cleared again (with SIGHUP).
+pub static PAUSED: AtomicBool = AtomicBool::new(false);
+
+/// How often a paused request checks whether it may go ahead.
+const PAUSE_POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+async fn wait_while_paused(paused: &AtomicBool) {
+    while paused.load(Acquire) {
+        sleep(PAUSE_POLL_INTERVAL).await;
+    }
+}
+
+const SECONDS_PER_DAY: u32 = 24 * 60 * 60;
+
+/// UTC times of day during which no new request starts, such as FactorDB's busiest hours. Set by
+/// the `POLITENESS_WINDOWS` environment variable.
+pub static POLITENESS_WINDOWS: OnceLock<Box<[PolitenessWindow]>> = OnceLock::new();
+
+/// A range of UTC times of day, in seconds since midnight, that wraps past midnight if `end` is
+/// before `start`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct PolitenessWindow {
+    start: u32,
+    end: u32,
+}
+
+impl PolitenessWindow {
+    /// Parses a comma-separated list of `HH:MM-HH:MM` ranges, such as `02:00-04:30,23:00-00:15`.
+    fn parse_list(list: &str) -> anyhow::Result<Box<[Self]>> {
+        fn parse_time(time: &str) -> anyhow::Result<u32> {
+            let (hours, minutes) = time
+                .trim()
+                .split_once(':')
+                .ok_or_else(|| anyhow!("Invalid time {time:?}: expected HH:MM"))?;
+            let hours: u32 = hours
+                .parse()
+                .map_err(|e| anyhow!("Invalid hour in {time:?}: {e}"))?;
+            let minutes: u32 = minutes
+                .parse()
+                .map_err(|e| anyhow!("Invalid minute in {time:?}: {e}"))?;
+            if hours >= 24 || minutes >= 60 {
+                bail!("Invalid time {time:?}: out of range");
+            }
+            Ok((hours * 60 + minutes) * 60)
+        }
+
+        list.split(',')
+            .map(str::trim)
+            .filter(|window| !window.is_empty())
+            .map(|window| {
+                let (start, end) = window
+                    .split_once('-')
+                    .ok_or_else(|| anyhow!("Invalid POLITENESS_WINDOWS entry {window:?}"))?;
+                let window = PolitenessWindow {
+                    start: parse_time(start)?,
+                    end: parse_time(end)?,
+                };
+                if window.start == window.end {
+                    bail!("POLITENESS_WINDOWS entry {start}-{end} is empty");
+                }
+                Ok(window)
+            })
+            .collect()
+    }
+
+    pub fn from_env() -> anyhow::Result<Box<[Self]>> {
+        match std::env::var("POLITENESS_WINDOWS") {
+            Ok(list) => Self::parse_list(&list),
+            Err(_) => Ok(Box::new([])),
+        }
+    }
+
+    /// How long until this window ends, if `seconds_of_day` is inside it.
+    fn remaining(&self, seconds_of_day: u32) -> Option<Duration> {
+        let inside = if self.start < self.end {
+            (self.start..self.end).contains(&seconds_of_day)
+        } else {
+            seconds_of_day >= self.start || seconds_of_day < self.end
+        };
+        inside.then(|| {
+            let remaining = (self.end + SECONDS_PER_DAY - seconds_of_day) % SECONDS_PER_DAY;
+            Duration::from_secs(remaining.into())
+        })
+    }
+}
+
+fn utc_seconds_of_day() -> u32 {
+    let since_epoch = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default();
+    (since_epoch.as_secs() % u64::from(SECONDS_PER_DAY)) as u32
+}
+
+/// Sleeps until none of `windows` is in progress.
+async fn wait_outside_politeness_windows(windows: &[PolitenessWindow]) {
+    while let Some(remaining) = windows
+        .iter()
+        .find_map(|window| window.remaining(utc_seconds_of_day()))
+    {
+        warn!("In a politeness window; pausing requests for {remaining:?}");
+        sleep(remaining).await;
+    }
+}
+
+/// Short-lived cache of page bodies by URL, so that a page requested again soon after it was
+/// fetched (e.g. `frame_prime.php` after a requeue) doesn't cost another request.
+struct PageCache {
+    pages: BasicCache<HipStr<'static>, (Instant, HipStr<'static>)>,
+    ttl: Duration,
+}
+
+impl PageCache {
+    fn new(capacity: usize, ttl: Duration) -> Self {
+        PageCache {
+            pages: create_cache(capacity),
+            ttl,
+        }
+    }
+
+    /// URLs that submit something or make FactorDB do work must never be served from the cache.
+    fn is_cacheable(url: &str) -> bool {
+        !["check=", "prp=", "basetocheck=", "=Proof", "report"]
+            .iter()
+            .any(|action| url.contains(action))
+    }
+
+    fn evict(&self, url: &str) {
+        self.pages.remove(&HipStr::from(url));
+    }
+
+    async fn get_or_fetch(
+        &self,
+        url: &str,
+        fetch: impl Future<Output = FetchOutcome>,
+    ) -> FetchOutcome {
+        let key = HipStr::from(url);
+        if let Some((fetched_at, page)) = get_from_cache(&self.pages, &key)
+            && fetched_at.elapsed() < self.ttl
+        {
+            info!("Page cache hit for {url}");
+            return Fetched(page);
+        }
+        let outcome = fetch.await;
+        if let Fetched(ref page) = outcome
+            && !self.ttl.is_zero()
+        {
+            self.pages.insert(key, (Instant::now(), page.clone()));
+        }
+        outcome
+    }
+}
+
+/// When each search was last requested, so that requests for the same one are spaced at least
+/// `min_interval` apart. Searches are FactorDB's most expensive pages, and [PageCache] only helps
+/// when the last request for the same URL succeeded. Requests that differ only in page size count
+/// as the same search, since retries shrink the page size, but each page of a batch starts
+/// elsewhere, so it doesn't wait for the one before it.
+struct SearchThrottle {
+    last_issued: BasicCache<HipStr<'static>, Instant>,
+    min_interval: Duration,
+}
+
+impl SearchThrottle {
+    fn new(capacity: usize, min_interval: Duration) -> Self {
+        SearchThrottle {
+            last_issued: create_cache(capacity),
+            min_interval,
+        }
+    }
+
+    fn is_search(url: &str) -> bool {
+        url.contains("listtype.php")
+    }
+
+    /// `url` without the page size, which retries change.
+    fn search_key(url: &str) -> HipStr<'static> {
+        let Some((path, query)) = url.split_once('?') else {
+            return HipStr::from(url);
+        };
+        let query = query
+            .split('&')
+            .filter(|param| {
+                let name = param.split_once('=').map_or(*param, |(name, _)| name);
+                name != "perpage"
+            })
+            .join("&");
+        format!("{path}?{query}").into()
+    }
+
+    /// Waits until `url` may be requested again, if it's a search, and records that it's being
+    /// requested.
+    async fn wait_turn(&self, url: &str) {
+        if self.min_interval.is_zero() || !Self::is_search(url) {
+            return;
+        }
+        let key = Self::search_key(url);
+        let now = Instant::now();
+        let issue_at = match get_from_cache(&self.last_issued, &key) {
+            Some(last_issued) => (last_issued + self.min_interval).max(now),
+            None => now,
+        };
+        // Record the slot before waiting, so that a concurrent request for the same URL waits
+        // for the one after it
+        self.last_issued.insert(key, issue_at);
+        if issue_at > now {
+            info!(
+                "Waiting {:?} to repeat search {url}",
+                issue_at.saturating_duration_since(now)
+            );
+            sleep_until(issue_at).await;
+        }
+    }
+}
+
 thread_local! {
     static CURL_CLIENT: RefCell<Easy2<Collector>> = RefCell::new(Easy2::new(Collector(Vec::new())));
 }
@@ -69,6 +462,138 @@ impl Collector {
     }
 }
 
+#[derive(Default)]
+struct CircuitBreakerState {
+    consecutive_failures: usize,
+    first_failure_at: Option<Instant>,
+    open_until: Option<Instant>,
+    /// When the half-open breaker stops waiting for the probe request it let through.
+    probe_until: Option<Instant>,
+}
+
+impl CircuitBreakerState {
+    fn blocked_until(&self, now: Instant) -> Option<Instant> {
+        let open_until = self.open_until?;
+        if open_until > now {
+            return Some(open_until);
+        }
+        self.probe_until.filter(|probe_until| *probe_until > now)
+    }
+}
+
+/// Stops all requests for a cool-down period once FactorDB has failed too many times in a row, so
+/// that an outage doesn't burn through our request quota. After the cool-down, the breaker is
+/// half-open: it lets one probe request through, and if that fails it reopens, and if it succeeds
+/// it closes. A probe that ends without either, e.g. because it was cancelled, lets another one
+/// through; so does one that hasn't finished within [E2E_TIMEOUT].
+#[derive(Default)]
+struct CircuitBreaker {
+    state: std::sync::Mutex<CircuitBreakerState>,
+}
+
+impl CircuitBreaker {
+    /// Returns the time until which requests are blocked, or `None` if a request may be sent now.
+    fn blocked_until(&self, now: Instant) -> Option<Instant> {
+        self.state.lock().unwrap().blocked_until(now)
+    }
+
+    /// Called immediately before sending a request. Returns the time until which it's blocked,
+    /// or else lets it through, claiming the probe if the breaker is half-open. The probe must be
+    /// released with [CircuitBreaker::end_probe] once the request's result has been recorded.
+    fn admit(&self, now: Instant) -> Result<Option<Instant>, Instant> {
+        let mut state = self.state.lock().unwrap();
+        if let Some(blocked_until) = state.blocked_until(now) {
+            return Err(blocked_until);
+        }
+        if state.open_until.is_none() {
+            return Ok(None);
+        }
+        let probe_until = now + E2E_TIMEOUT;
+        state.probe_until = Some(probe_until);
+        Ok(Some(probe_until))
+    }
+
+    /// Lets another probe through, if `probe` hasn't already been superseded or decided.
+    fn end_probe(&self, probe: Instant) {
+        let mut state = self.state.lock().unwrap();
+        if state.probe_until == Some(probe) {
+            state.probe_until = None;
+        }
+    }
+
+    /// Waits until [CircuitBreaker::admit] lets a request through.
+    async fn admission(&self) -> CircuitBreakerAdmission<'_> {
+        loop {
+            match self.admit(Instant::now()) {
+                Ok(probe) => {
+                    return CircuitBreakerAdmission {
+                        breaker: self,
+                        probe,
+                    };
+                }
+                Err(blocked_until) => sleep_until(blocked_until).await,
+            }
+        }
+    }
+
+    fn record_success(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.probe_until = None;
+        if state.open_until.take().is_some() {
+            info!("FactorDB is responding again; closing circuit breaker");
+        }
+        state.consecutive_failures = 0;
+        state.first_failure_at = None;
+    }
+
+    fn record_failure(&self, now: Instant) {
+        let mut state = self.state.lock().unwrap();
+        let half_open = state.open_until.is_some_and(|open_until| open_until <= now);
+        if half_open {
+            warn!(
+                "FactorDB still failing after cool-down; reopening circuit breaker for {CIRCUIT_BREAKER_COOL_DOWN:?}"
+            );
+            state.open_until = Some(now + CIRCUIT_BREAKER_COOL_DOWN);
+            state.probe_until = None;
+            return;
+        }
+        if state.open_until.is_some() {
+            // Request was already in flight when the breaker opened
+            return;
+        }
+        if state
+            .first_failure_at
+            .is_none_or(|first_failure_at| now - first_failure_at > CIRCUIT_BREAKER_WINDOW)
+        {
+            state.first_failure_at = Some(now);
+            state.consecutive_failures = 0;
+        }
+        state.consecutive_failures += 1;
+        if state.consecutive_failures >= CIRCUIT_BREAKER_THRESHOLD {
+            warn!(
+                "{} consecutive failed requests; pausing all requests for {CIRCUIT_BREAKER_COOL_DOWN:?}",
+                state.consecutive_failures
+            );
+            state.open_until = Some(now + CIRCUIT_BREAKER_COOL_DOWN);
+        }
+    }
+}
+
+/// A request that the [CircuitBreaker] let through. If it's the probe, releases it when dropped, so
+/// that a probe that ends without a result, e.g. because it was cancelled, doesn't hold up the next.
+struct CircuitBreakerAdmission<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: Option<Instant>,
+}
+
+impl Drop for CircuitBreakerAdmission<'_> {
+    fn drop(&mut self) {
+        if let Some(probe) = self.probe {
+            self.breaker.end_probe(probe);
+        }
+    }
+}
+
 #[cfg_attr(test, mockall::automock)]
 pub trait FactorDbClient {
     async fn parse_resource_limits(
@@ -84,11 +609,25 @@ pub trait FactorDbClient {
         retry_delay: Duration,
     ) -> Option<HipStr<'static>>;
     async fn try_get_and_decode(&self, url: &str) -> Option<HipStr<'static>>;
+    /// Like [FactorDbClient::retrying_get_and_decode], but returns early with [Throttled] instead
+    /// of retrying when FactorDB asks us to wait.
+    async fn retrying_fetch(&self, url: &str, retry_delay: Duration) -> FetchOutcome;
+    /// Like [FactorDbClient::try_get_and_decode], but reports whether a failure was because
+    /// FactorDB is throttling us.
+    async fn try_fetch(&self, url: &str) -> FetchOutcome;
     async fn try_get_resource_limits(
         &self,
         bases_before_next_cpu_check: &mut usize,
     ) -> Option<ResourceLimits>;
     async fn try_get_expression_form(&self, entry_id: EntryId) -> Option<Factor>;
+    /// Fetches the status code (e.g. "PRP", "C", "FF") of a number from the JSON API. Returns
+    /// None if the API response couldn't be fetched or decoded, so that the caller can fall back
+    /// to scraping HTML.
+    async fn try_get_status(&self, id: EntryId) -> Option<HipStr<'static>>;
+    /// Fetches only the small prime factors FactorDB already knows for a number, which is much
+    /// lighter than [FactorDbClient::known_factors_as_digits]. Returns None if they aren't
+    /// available this way, so that the caller can fall back to the full fetch.
+    async fn small_factors<'a>(&self, id: NumberSpecifier<'a>) -> Option<Box<[NumericFactor]>>;
     async fn known_factors_as_digits<'a>(
         &self,
         id: NumberSpecifier<'a>,
@@ -103,6 +642,14 @@ pub trait FactorDbClient {
         u_id: NumberSpecifier<'a>,
         factor: &Factor,
     ) -> ReportFactorResult;
+    /// Submits `factor^exponent`, so that FactorDB learns the full power of a repeated factor in
+    /// one request.
+    async fn report_factor_with_exponent<'a>(
+        &self,
+        u_id: NumberSpecifier<'a>,
+        factor: &Factor,
+        exponent: NumberLength,
+    ) -> ReportFactorResult;
     async fn report_numeric_factor(
         &self,
         u_id: EntryId,
@@ -122,14 +669,29 @@ pub struct RealFactorDbClient {
     rate_limiter: DefaultDirectRateLimiter<StateInformationMiddleware>,
     requests_left_last_check: AtomicU32,
     requests_per_hour: u32,
-    request_mutex: Mutex<()>,
+    request_slots: Semaphore,
     all_threads_blocked_until: AtomicInstant,
     id_and_expr_regex: Regex,
     digits_fallback_regex: Regex,
     expression_form_regex: Regex,
+    throttle_regex: Regex,
     by_id_cache: BasicCache<EntryId, ProcessedStatusApiResponse>,
     by_expr_cache: BasicCache<Factor, ProcessedStatusApiResponse>,
     expression_form_cache: BasicCache<EntryId, Factor>,
+    /// Whether each borderline number's ID turned out to be its value.
+    id_equal_to_value_cache: BasicCache<EntryId, bool>,
+    circuit_breaker: CircuitBreaker,
+    page_cache: PageCache,
+    search_throttle: SearchThrottle,
+    /// Once this receives the shutdown signal, requests in flight are cancelled.
+    shutdown: Option<std::sync::Mutex<Monitor>>,
+    max_requests_per_cycle: u32,
+    requests_this_cycle: AtomicU32,
+    cycle_resets_at: AtomicInstant,
+    /// [PAUSED], except in tests.
+    paused: &'static AtomicBool,
+    /// [POLITENESS_WINDOWS], except in tests.
+    politeness_windows: Box<[PolitenessWindow]>,
 }
 
 pub struct ResourceLimits {
@@ -137,6 +699,69 @@ pub struct ResourceLimits {
     pub resets_at: Instant,
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ReserveCurve {
+    Linear,
+    Quadratic,
+}
+
+/// How much of FactorDB's CPU budget to leave unspent, as a function of the time until it resets,
+/// so that other users still have some left late in the cycle. Set by the `CPU_RESERVE_DIVISOR`
+/// and `CPU_RESERVE_CURVE` (`linear` or `quadratic`) environment variables.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CpuReserve {
+    pub divisor: f64,
+    pub curve: ReserveCurve,
+}
+
+impl Default for CpuReserve {
+    fn default() -> Self {
+        CpuReserve {
+            divisor: 18000.0,
+            curve: ReserveCurve::Quadratic,
+        }
+    }
+}
+
+impl CpuReserve {
+    fn with_overrides(divisor: Option<String>, curve: Option<String>) -> anyhow::Result<Self> {
+        let mut reserve = CpuReserve::default();
+        if let Some(divisor) = divisor {
+            reserve.divisor = divisor
+                .trim()
+                .parse()
+                .map_err(|e| anyhow!("Invalid CPU_RESERVE_DIVISOR {divisor:?}: {e}"))?;
+            if !reserve.divisor.is_finite() || reserve.divisor <= 0.0 {
+                bail!("CPU_RESERVE_DIVISOR must be positive and finite");
+            }
+        }
+        if let Some(curve) = curve {
+            reserve.curve = match curve.trim().to_ascii_lowercase().as_str() {
+                "linear" => ReserveCurve::Linear,
+                "quadratic" => ReserveCurve::Quadratic,
+                _ => bail!("Invalid CPU_RESERVE_CURVE {curve:?}: expected linear or quadratic"),
+            };
+        }
+        Ok(reserve)
+    }
+
+    pub fn from_env() -> anyhow::Result<Self> {
+        Self::with_overrides(
+            std::env::var("CPU_RESERVE_DIVISOR").ok(),
+            std::env::var("CPU_RESERVE_CURVE").ok(),
+        )
+    }
+
+    /// Tenths of a CPU second to hold back when the budget resets in `seconds_to_reset`.
+    pub fn reserved_tenths(&self, seconds_to_reset: f64) -> usize {
+        let numerator = match self.curve {
+            ReserveCurve::Linear => seconds_to_reset,
+            ReserveCurve::Quadratic => seconds_to_reset * seconds_to_reset,
+        };
+        (numerator / self.divisor) as usize
+    }
+}
+
 impl RealFactorDbClient {
     pub fn new(requests_per_hour: NonZeroU32) -> Self {
         let rate_limiter =
@@ -162,6 +787,10 @@ impl RealFactorDbClient {
                 .build()
                 .unwrap();
         let expression_form_regex = Regex::new("name=\"query\" value=\"([^\"]+)\"").unwrap();
+        // Only where the phrase is the page's first text, such as its title, so that a page that
+        // merely quotes it isn't mistaken for one
+        let throttle_regex =
+            Regex::new("^\\s*(?:<[^>]*>\\s*)*Please wait(?:[^<0-9]*([0-9]+) seconds?)?").unwrap();
         // Governor rate-limiters start out with their full burst capacity and recharge starting
         // immediately, but this would lead to twice the allowed number of requests in our first hour,
         // so we make it start nearly empty instead.
@@ -176,21 +805,110 @@ impl RealFactorDbClient {
             rate_limiter,
             requests_per_hour: requests_per_hour.get(),
             requests_left_last_check,
-            request_mutex: Mutex::const_new(()),
+            request_slots: Semaphore::new(MAX_PARALLEL_REQUESTS.load(Acquire).max(1)),
             all_threads_blocked_until: AtomicInstant::now(),
             id_and_expr_regex,
             digits_fallback_regex,
             expression_form_regex,
+            throttle_regex,
             by_id_cache: create_cache(1 << 16),
             by_expr_cache: create_cache(1 << 12),
             expression_form_cache: create_cache(1 << 16),
+            id_equal_to_value_cache: create_cache(1 << 10),
+            circuit_breaker: CircuitBreaker::default(),
+            page_cache: PageCache::new(
+                1 << 10,
+                Duration::from_secs(PAGE_CACHE_TTL_SECS.load(Acquire)),
+            ),
+            search_throttle: SearchThrottle::new(
+                1 << 8,
+                Duration::from_secs(MIN_SEARCH_INTERVAL_SECS.load(Acquire)),
+            ),
+            shutdown: None,
+            max_requests_per_cycle: MAX_REQUESTS_PER_CYCLE.load(Acquire),
+            requests_this_cycle: AtomicU32::new(0),
+            cycle_resets_at: AtomicInstant::new((Instant::now() + RESOURCE_CYCLE_DURATION).into()),
+            paused: &PAUSED,
+            politeness_windows: POLITENESS_WINDOWS.get().cloned().unwrap_or_default(),
+        }
+    }
+
+    async fn wait_until_requests_allowed(&self) {
+        wait_while_paused(self.paused).await;
+        wait_outside_politeness_windows(&self.politeness_windows).await;
+    }
+
+    /// Makes requests in flight when `shutdown` receives the shutdown signal give up instead of
+    /// delaying shutdown until they complete. Factor submissions are the exception, since a found
+    /// factor is too valuable to drop.
+    pub fn with_shutdown(mut self, shutdown: Monitor) -> Self {
+        self.shutdown = Some(std::sync::Mutex::new(shutdown));
+        self
+    }
+
+    fn is_shutting_down(&self) -> bool {
+        self.shutdown
+            .as_ref()
+            .is_some_and(|shutdown| shutdown.lock().unwrap().check_for_shutdown())
+    }
+
+    /// Awaits `request`, unless shutdown begins first, in which case it's dropped.
+    async fn unless_shutdown<T>(
+        &self,
+        request: impl Future<Output = T>,
+    ) -> Result<T, FactorDbError> {
+        let Some(ref shutdown) = self.shutdown else {
+            return Ok(request.await);
+        };
+        let mut shutdown = {
+            let mut shutdown = shutdown.lock().unwrap();
+            // A clone only hears about shutdowns that this one has seen, or that begin later
+            shutdown.check_for_shutdown();
+            shutdown.clone()
+        };
+        select! {
+            biased;
+            _ = shutdown.recv() => Err(FactorDbError::Cancelled),
+            result = request => Ok(result),
+        }
+    }
+
+    /// Counts a request against [MAX_REQUESTS_PER_CYCLE], or returns how long until the cycle
+    /// resets if there's none left.
+    fn take_request_budget(&self) -> Result<(), Duration> {
+        let now = Instant::now();
+        let mut resets_at: Instant = self.cycle_resets_at.load(Acquire).into();
+        if resets_at <= now {
+            resets_at = now + RESOURCE_CYCLE_DURATION;
+            self.cycle_resets_at.store(resets_at.into(), Release);
+            self.requests_this_cycle.store(0, Release);
         }
+        let requests = self.requests_this_cycle.fetch_add(1, Ordering::AcqRel);
+        if requests >= self.max_requests_per_cycle {
+            self.requests_this_cycle.fetch_sub(1, Ordering::AcqRel);
+            return Err(resets_at - now);
+        }
+        Ok(())
     }
 
     #[framed]
-    async fn try_get_and_decode_core(&self, url: &str) -> Option<HipStr<'static>> {
+    async fn try_get_and_decode_core(&self, url: &str) -> Result<HipStr<'static>, FactorDbError> {
+        while let Some(open_until) = self.circuit_breaker.blocked_until(Instant::now()) {
+            sleep_until(open_until).await;
+        }
+        if let Err(until_reset) = self.take_request_budget() {
+            warn!("Request budget for this cycle used up; throttling for {until_reset:?}");
+            return Err(FactorDbError::Throttled(Some(until_reset)));
+        }
         self.rate_limiter.until_ready().await;
-        let permit = self.request_mutex.lock().await;
+        let Ok(permit) = self.request_slots.acquire().await else {
+            unreachable!("request semaphore is never closed");
+        };
+        // Checked only once we're next in line, so that no request starts while paused or in a
+        // politeness window
+        self.wait_until_requests_allowed().await;
+        // Admitted last, so that a half-open breaker's probe is the request that's actually sent
+        let _admission = self.circuit_breaker.admission().await;
         info!("Start of request to {url}");
         let result = if url.len() > REQWEST_MAX_URL_LEN {
             let result = block_in_place(|| {
@@ -200,52 +918,128 @@ impl RealFactorDbClient {
                         .and_then(|_| curl.timeout(E2E_TIMEOUT))
                         .and_then(|_| curl.url(url))
                         .and_then(|_| curl.perform())
-                        .map_err(anyhow::Error::from)
                         .and_then(|_| {
                             let response_code = curl.response_code()?;
-                            if response_code != 200 {
+                            if response_code != 200 && response_code < 500 {
                                 error!("Error reading {url}: HTTP response code {response_code}");
                             }
                             let response_body = curl.get_mut().take_all();
                             curl.reset();
-                            Ok(response_body)
+                            Ok((response_code, response_body))
                         })
                 })
             });
             drop(permit);
-            result.and_then(|response_body| Ok(String::from_utf8(response_body)?))
+            result
+                .map_err(FactorDbError::from)
+                .and_then(|(response_code, response_body)| match response_code {
+                    500..=599 => Err(FactorDbError::HttpStatus(response_code as u16)),
+                    _ => Ok(String::from_utf8(response_body)?),
+                })
         } else {
-            let result = self
+            let request = self
                 .http
                 .get(url)
                 .header("Referer", "https://factordb.com")
                 .send()
-                .and_then(Response::text)
-                .await;
+                .and_then(|response| async move {
+                    // Server errors get their own backoff, so don't mistake their pages for results
+                    if response.status().is_server_error() {
+                        response.error_for_status()
+                    } else {
+                        Ok(response)
+                    }
+                })
+                .and_then(Response::text);
+            let result = self.unless_shutdown(request).await;
             drop(permit);
-            result.map_err(|e| anyhow::Error::from(e.without_url()))
+            result.and_then(|result| result.map_err(|e| FactorDbError::from(e.without_url())))
         };
         info!("End of request to {url}");
+        let result = result.and_then(|text| self.check_response_text(text));
         match result {
-            Err(e) => {
-                error!("Error reading {url}: {e}");
-                None
+            Ok(_) => self.circuit_breaker.record_success(),
+            Err(
+                FactorDbError::Throttled(_)
+                | FactorDbError::TooManyParallelRequests
+                | FactorDbError::Cancelled,
+            ) => {}
+            Err(_) => self.circuit_breaker.record_failure(Instant::now()),
+        }
+        match result {
+            Err(FactorDbError::Throttled(retry_after)) => {
+                warn!("Got 'please wait' from {url}; retry after {retry_after:?}");
             }
-            Ok(text) => {
-                if text.contains("502 Proxy Error") {
-                    error!("502 error from {url}");
-                    None
-                } else if text.contains("parallel processing requests") {
-                    warn!("Parallel-request limit reached; throttling");
-                    let end_of_throttling = Instant::now() + PARALLEL_REQUEST_THROTTLING_DURATION;
-                    self.all_threads_blocked_until
-                        .store(end_of_throttling.into(), Release);
-                    None
-                } else {
-                    Some(text.into())
-                }
+            Err(FactorDbError::TooManyParallelRequests) => {
+                warn!("Parallel-request limit reached; throttling");
+                let end_of_throttling = Instant::now() + PARALLEL_REQUEST_THROTTLING_DURATION;
+                self.all_threads_blocked_until
+                    .store(end_of_throttling.into(), Release);
             }
+            Err(FactorDbError::Cancelled) => warn!("Cancelled request to {url} for shutdown"),
+            Err(ref e) => error!("Error reading {url}: {e}"),
+            Ok(_) => {}
         }
+        result
+    }
+
+    /// Recognizes the pages that FactorDB and its proxy return instead of the one requested.
+    fn check_response_text(&self, text: String) -> Result<HipStr<'static>, FactorDbError> {
+        if text.contains("502 Proxy Error") {
+            Err(FactorDbError::HttpStatus(502))
+        } else if text.is_empty() {
+            Err(FactorDbError::EmptyBody)
+        } else if text.contains("parallel processing requests") {
+            Err(FactorDbError::TooManyParallelRequests)
+        } else {
+            self.check_for_throttle_page(text.into())
+        }
+    }
+
+    /// Recognizes FactorDB's "please wait" page, which can be returned by any endpoint when we're
+    /// making requests too quickly, and extracts how long it asked us to wait.
+    fn check_for_throttle_page(
+        &self,
+        text: HipStr<'static>,
+    ) -> Result<HipStr<'static>, FactorDbError> {
+        match self.throttle_regex.captures(&text) {
+            Some(captures) => Err(FactorDbError::Throttled(
+                captures
+                    .get(1)
+                    .and_then(|seconds| seconds.as_str().parse::<u64>().ok())
+                    .map(Duration::from_secs),
+            )),
+            None => Ok(text),
+        }
+    }
+
+    #[framed]
+    async fn try_fetch_uncached(&self, url: &str) -> FetchOutcome {
+        sleep_until(self.all_threads_blocked_until.load(Acquire).into()).await;
+        self.search_throttle.wait_turn(url).await;
+        let response = match self.try_get_and_decode_core(url).await {
+            Ok(response) => response,
+            Err(e) => return e.into(),
+        };
+        let mut temp_bases = usize::MAX;
+        if let Some(ResourceLimits { resets_at, .. }) =
+            self.parse_resource_limits(&mut temp_bases, &response).await
+        {
+            self.all_threads_blocked_until
+                .store(resets_at.into(), Release);
+            if EXIT_TIME
+                .get()
+                .is_some_and(|exit_time| exit_time <= &resets_at)
+            {
+                error!("Resource limits reached and won't reset during this process's lifespan");
+                RUN_STATS.log_summary();
+                exit(0);
+            }
+            let throttling_duration = resets_at.saturating_duration_since(Instant::now());
+            warn!("Resource limits reached; throttling for {throttling_duration:?}");
+            return Throttled(Some(throttling_duration));
+        }
+        Fetched(response)
     }
 
     async fn retrying_get_and_decode_internal(
@@ -253,14 +1047,67 @@ impl RealFactorDbClient {
         url: &str,
         retry_delay: Duration,
         max_retries: usize,
-    ) -> Option<HipStr<'static>> {
+        retry_when_throttled: bool,
+    ) -> FetchOutcome {
         for _ in 0..max_retries {
-            if let Some(value) = self.try_get_and_decode(url).await {
-                return Some(value);
+            match self.try_fetch(url).await {
+                Failed if !self.is_shutting_down() => sleep(retry_delay).await,
+                Throttled(_) if retry_when_throttled && !self.is_shutting_down() => {
+                    sleep(retry_delay).await
+                }
+                outcome => return outcome,
             }
-            sleep(retry_delay).await;
         }
-        None
+        Failed
+    }
+
+    /// Whether `value` is already known to be the ID of the number `value`.
+    fn is_known_id_equal_to_value(&self, value: EntryId) -> bool {
+        value < MIN_BORDERLINE_ID_EQUAL_TO_VALUE
+            || (value <= MAX_ID_EQUAL_TO_VALUE
+                && self.id_equal_to_value_cache.get(&value) == Some(true))
+    }
+
+    /// Whether `value` is the ID of the number `value`. For borderline numbers, this is checked
+    /// with a status fetch, so that a mismatch can't make us read from or submit to the wrong
+    /// entry; if the fetch fails, the ID isn't presumed.
+    #[framed]
+    async fn id_equals_value(&self, value: EntryId) -> bool {
+        if value > MAX_ID_EQUAL_TO_VALUE {
+            return false;
+        }
+        if self.is_known_id_equal_to_value(value) {
+            return true;
+        }
+        if let Some(verified) = self.id_equal_to_value_cache.get(&value) {
+            return verified;
+        }
+        let Some(response) = self
+            .try_get_and_decode(&format!("https://factordb.com/api?query={value}"))
+            .await
+        else {
+            warn!("{value}: Couldn't check whether this number's ID is its value");
+            return false;
+        };
+        let verified = match from_str::<NumberStatusApiResponse>(&response) {
+            Ok(NumberStatusApiResponse { id, .. }) => {
+                let id = match id {
+                    Value::Number(id) => id.as_u64().map(EntryId::from),
+                    Value::String(id) => id.parse::<EntryId>().ok(),
+                    _ => None,
+                };
+                if id != Some(value) {
+                    warn!("{value}: FactorDB gave this number the ID {id:?} instead of its value");
+                }
+                id == Some(value)
+            }
+            Err(e) => {
+                error!("{value}: Failed to decode API response: {e}: {response}");
+                return false;
+            }
+        };
+        self.id_equal_to_value_cache.insert(value, verified);
+        verified
     }
 }
 
@@ -307,9 +1154,16 @@ impl FactorDbClient for RealFactorDbClient {
         let cpu_tenths_spent = cpu_seconds.parse::<usize>().unwrap() * 10
             + cpu_tenths_within_second.parse::<usize>().unwrap();
         CPU_TENTHS_SPENT_LAST_CHECK.store(cpu_tenths_spent, Release);
+        RUN_STATS.record_cpu_tenths_spent(cpu_tenths_spent);
         let seconds_to_reset = minutes_to_reset.parse::<u64>().unwrap() * 60
             + seconds_within_minute_to_reset.parse::<u64>().unwrap();
         let resets_at = now + Duration::from_secs(seconds_to_reset);
+        let prev_resets_at = self
+            .cycle_resets_at
+            .swap(resets_at.into(), Ordering::AcqRel);
+        if Instant::from(prev_resets_at) <= now {
+            self.requests_this_cycle.store(0, Release);
+        }
         Some(ResourceLimits {
             cpu_tenths_spent,
             resets_at,
@@ -324,34 +1178,30 @@ impl FactorDbClient for RealFactorDbClient {
         url: &str,
         retry_delay: Duration,
     ) -> Option<HipStr<'static>> {
-        self.retrying_get_and_decode_internal(url, retry_delay, MAX_RETRIES)
+        self.retrying_get_and_decode_internal(url, retry_delay, MAX_RETRIES, true)
             .await
+            .fetched()
     }
 
     #[framed]
     async fn try_get_and_decode(&self, url: &str) -> Option<HipStr<'static>> {
-        sleep_until(self.all_threads_blocked_until.load(Acquire).into()).await;
-        let response = self.try_get_and_decode_core(url).await?;
-        let mut temp_bases = usize::MAX;
-        if let Some(ResourceLimits { resets_at, .. }) =
-            self.parse_resource_limits(&mut temp_bases, &response).await
-        {
-            self.all_threads_blocked_until
-                .store(resets_at.into(), Release);
-            if EXIT_TIME
-                .get()
-                .is_some_and(|exit_time| exit_time <= &resets_at)
-            {
-                error!("Resource limits reached and won't reset during this process's lifespan");
-                exit(0);
-            } else if let Some(throttling_duration) =
-                resets_at.checked_duration_since(Instant::now())
-            {
-                warn!("Resource limits reached; throttling for {throttling_duration:?}");
-            }
-            return None;
+        self.try_fetch(url).await.fetched()
+    }
+
+    #[framed]
+    async fn retrying_fetch(&self, url: &str, retry_delay: Duration) -> FetchOutcome {
+        self.retrying_get_and_decode_internal(url, retry_delay, MAX_RETRIES, false)
+            .await
+    }
+
+    #[framed]
+    async fn try_fetch(&self, url: &str) -> FetchOutcome {
+        if !PageCache::is_cacheable(url) {
+            return self.try_fetch_uncached(url).await;
         }
-        Some(response)
+        self.page_cache
+            .get_or_fetch(url, self.try_fetch_uncached(url))
+            .await
     }
 
     #[framed]
@@ -361,14 +1211,15 @@ impl FactorDbClient for RealFactorDbClient {
     ) -> Option<ResourceLimits> {
         let response = self
             .try_get_and_decode_core("https://factordb.com/res.php")
-            .await?;
+            .await
+            .ok()?;
         self.parse_resource_limits(bases_before_next_cpu_check, &response)
             .await
     }
     #[inline]
     #[framed]
     async fn try_get_expression_form(&self, entry_id: EntryId) -> Option<Factor> {
-        if entry_id <= MAX_ID_EQUAL_TO_VALUE {
+        if self.id_equals_value(entry_id).await {
             return Some(Factor::from(entry_id));
         }
         if let Some(response) = self.expression_form_cache.get(&entry_id) {
@@ -378,17 +1229,73 @@ impl FactorDbClient for RealFactorDbClient {
         let response = self
             .try_get_and_decode(&format!("https://factordb.com/index.php?id={entry_id}"))
             .await?;
-        let expression_form = Factor::from(
+        let expression_form = Factor::parse(
             self.expression_form_regex
                 .captures(&response)?
                 .get(1)?
                 .as_str(),
-        );
+        )
+        .await;
         self.expression_form_cache
             .insert(entry_id, expression_form.clone());
         Some(expression_form)
     }
 
+    #[framed]
+    async fn try_get_status(&self, id: EntryId) -> Option<HipStr<'static>> {
+        let response = self
+            .try_get_and_decode(&format!("https://factordb.com/api?id={id}"))
+            .await?;
+        match from_str::<NumberStatusApiResponse>(&response) {
+            Ok(NumberStatusApiResponse { status, .. }) => {
+                debug!("{id}: Fetched status of {status} from API");
+                Some(status)
+            }
+            Err(e) => {
+                error!("{id}: Failed to decode API response: {e}: {response}");
+                None
+            }
+        }
+    }
+
+    #[framed]
+    async fn small_factors<'a>(&self, id: NumberSpecifier<'a>) -> Option<Box<[NumericFactor]>> {
+        if let Some(cached) = self.cached_factors(&id) {
+            return Some(
+                cached
+                    .factors
+                    .iter()
+                    .filter_map(|factor| match factor {
+                        Numeric(n) => Some(*n),
+                        _ => None,
+                    })
+                    .collect(),
+            );
+        }
+        // Only numbers with IDs have a page to read them from
+        let Id(entry_id) = id else {
+            return None;
+        };
+        // Shares the page cache with try_get_expression_form, which fetches the same page
+        let page = self
+            .try_get_and_decode(&format!("https://factordb.com/index.php?id={entry_id}"))
+            .await?;
+        let Some(listed_factors) = listed_factors_section(&page) else {
+            warn!("{entry_id}: No factor table found on number page");
+            return None;
+        };
+        // Small primes' IDs are their values, and the table links every factor to its ID
+        let small_factors: BTreeSet<_> = self
+            .read_ids_and_exprs(listed_factors)
+            .filter(|&(factor_id, expr)| {
+                factor_id != entry_id && expr.parse::<NumberSpecifier>() == Ok(Id(factor_id))
+            })
+            .map(|(factor_id, _)| factor_id)
+            .collect();
+        debug!("{entry_id}: Fetched small factors {small_factors:?}");
+        Some(small_factors.into_iter().collect())
+    }
+
     #[inline]
     #[framed]
     async fn known_factors_as_digits<'a>(
@@ -398,6 +1305,10 @@ impl FactorDbClient for RealFactorDbClient {
         get_digits_as_fallback: bool,
     ) -> ProcessedStatusApiResponse {
         debug!("known_factors_as_digits: id={id:?}");
+        if let Some(value) = value_as_id(&id) {
+            // Lets cached_factors answer for a borderline number once its ID is verified
+            self.id_equals_value(value).await;
+        }
         if let Some(cached) = self.cached_factors(&id) {
             return cached;
         }
@@ -457,10 +1368,11 @@ impl FactorDbClient for RealFactorDbClient {
                         }
                     };
                     let factors = {
-                        let mut factors: Vec<_> = factors
-                            .into_iter()
-                            .map(|(factor, _exponent)| Factor::from(factor.as_str()))
-                            .collect();
+                        let mut parsed = Vec::with_capacity(factors.len());
+                        for (factor, _exponent) in factors {
+                            parsed.push(Factor::parse(factor.as_str()).await);
+                        }
+                        let mut factors = parsed;
                         factors.sort_unstable();
                         factors.dedup();
                         factors
@@ -478,21 +1390,21 @@ impl FactorDbClient for RealFactorDbClient {
                 factors: Box::new([]),
             },
             Err(Some(fallback_response)) => {
-                let factors = self
+                let digits = self
                     .digits_fallback_regex
                     .captures(&fallback_response)
                     .and_then(|c| c.get(1))
                     .map(|digits_cell| {
-                        vec![Factor::from(
-                            digits_cell
-                                .as_str()
-                                .chars()
-                                .filter(char::is_ascii_digit)
-                                .collect::<String>()
-                                .as_str(),
-                        )]
-                    })
-                    .unwrap_or_default();
+                        digits_cell
+                            .as_str()
+                            .chars()
+                            .filter(char::is_ascii_digit)
+                            .collect::<String>()
+                    });
+                let factors = match digits {
+                    Some(digits) => vec![Factor::parse(&digits).await],
+                    None => vec![],
+                };
                 ProcessedStatusApiResponse {
                     status: None,
                     factors: factors.into_boxed_slice(),
@@ -522,21 +1434,8 @@ impl FactorDbClient for RealFactorDbClient {
 
     #[inline]
     fn cached_factors(&self, id: &NumberSpecifier) -> Option<ProcessedStatusApiResponse> {
-        let numeric_or_id = match id {
-            Id(entry_id) => Some(*entry_id),
-            Expression(x) => {
-                if let Numeric(n) = **x {
-                    Some(n)
-                } else if let Some(Some(n)) = get_from_cache(get_numeric_value_cache(), x.as_ref())
-                {
-                    Some(n)
-                } else {
-                    None
-                }
-            }
-        };
-        if let Some(entry_id) = numeric_or_id
-            && entry_id <= MAX_ID_EQUAL_TO_VALUE
+        if let Some(entry_id) = value_as_id(id)
+            && self.is_known_id_equal_to_value(entry_id)
         {
             debug!("Specially handling numeric expression {entry_id}");
             let factors: Box<[_]> = find_factors_of_numeric(entry_id).into_keys().collect();
@@ -568,8 +1467,14 @@ impl FactorDbClient for RealFactorDbClient {
     fn invalidate_cached_factors(&self, id: Option<EntryId>, expression: &Factor) {
         if let Some(id) = id {
             self.by_id_cache.remove(&id);
+            self.page_cache
+                .evict(&format!("https://factordb.com/api?id={id}"));
         }
         self.by_expr_cache.remove(expression);
+        self.page_cache.evict(&format!(
+            "https://factordb.com/api?query={}",
+            encode(&expression.to_unelided_string())
+        ));
     }
 
     #[framed]
@@ -598,8 +1503,23 @@ impl FactorDbClient for RealFactorDbClient {
                 (Some(id), None)
             }
         };
+        let factor_digits = factor
+            .digits()
+            .unwrap_or_else(|| factor.to_unelided_string());
+        while let Some(open_until) = self.circuit_breaker.blocked_until(Instant::now()) {
+            sleep_until(open_until).await;
+        }
+        // A found factor is too valuable to drop, so wait for the next cycle instead
+        while let Err(until_reset) = self.take_request_budget() {
+            warn!("{u_id}: Request budget used up; waiting {until_reset:?} to report {factor}");
+            sleep(until_reset).await;
+        }
         self.rate_limiter.until_ready().await;
-        let permit = self.request_mutex.lock().await;
+        let Ok(permit) = self.request_slots.acquire().await else {
+            unreachable!("request semaphore is never closed");
+        };
+        self.wait_until_requests_allowed().await;
+        let _admission = self.circuit_breaker.admission().await;
         info!("Start of request to https://factordb.com/reportfactor.php");
         let response = self
             .http
@@ -607,19 +1527,29 @@ impl FactorDbClient for RealFactorDbClient {
             .form(&FactorSubmission {
                 id,
                 number,
-                factor: &factor.to_unelided_string(),
+                factor: &factor_digits,
             })
             .send()
             .and_then(Response::text)
             .await;
         drop(permit);
         info!("End of request to https://factordb.com/reportfactor.php");
+        let response = response
+            .map_err(|e| FactorDbError::from(e.without_url()))
+            .and_then(|text| self.check_for_throttle_page(text.into()));
+        match response {
+            Ok(_) => self.circuit_breaker.record_success(),
+            Err(FactorDbError::Throttled(_)) => {}
+            Err(_) => self.circuit_breaker.record_failure(Instant::now()),
+        }
         match response {
             Ok(text) => {
                 info!("{u_id}: reported a factor of {factor}; response: {text}",);
                 if text.contains("Error") {
                     OtherError
                 } else if text.contains("submitted") {
+                    RUN_STATS.record_factor_accepted();
+                    record_accepted_factor(&u_id, &factor_digits).await;
                     Accepted
                 } else if text.contains("fully factored") || text.contains("Number too small") {
                     AlreadyFullyFactored
@@ -629,6 +1559,11 @@ impl FactorDbClient for RealFactorDbClient {
                     OtherError
                 }
             }
+            Err(FactorDbError::Throttled(retry_after)) => {
+                warn!("{u_id}: Got 'please wait' when submitting {factor}");
+                sleep(retry_after.unwrap_or(THROTTLED_RETRY_DELAY)).await;
+                OtherError
+            }
             Err(e) => {
                 error!("{u_id}: Failed to get response when submitting {factor}: {e}");
                 sleep(RETRY_DELAY).await;
@@ -637,6 +1572,20 @@ impl FactorDbClient for RealFactorDbClient {
         }
     }
 
+    #[framed]
+    async fn report_factor_with_exponent(
+        &self,
+        u_id: NumberSpecifier<'_>,
+        factor: &Factor,
+        exponent: NumberLength,
+    ) -> ReportFactorResult {
+        if exponent <= 1 {
+            return self.try_report_factor(u_id, factor).await;
+        }
+        let power = Factor::multiply([(factor.clone(), exponent)].into());
+        self.try_report_factor(u_id, &power).await
+    }
+
     #[framed]
     async fn report_numeric_factor(
         &self,
@@ -663,6 +1612,34 @@ impl FactorDbClient for RealFactorDbClient {
     }
 }
 
+/// Appends `factor` to the accepted-factors file, if [ACCEPTED_FACTORS_OUT] is open.
+async fn record_accepted_factor(u_id: &NumberSpecifier<'_>, factor: &str) {
+    let Some(out) = ACCEPTED_FACTORS_OUT.get() else {
+        return;
+    };
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_secs();
+    if let Err(e) = write_accepted_factor_row(&mut *out.lock().await, u_id, factor, timestamp) {
+        error!("{u_id}: failed to write {factor} to accepted factors file: {e}");
+    }
+}
+
+/// Writes an `id,factor,timestamp` row, with the number's full expression in place of the ID if
+/// that's how it was specified.
+fn write_accepted_factor_row(
+    out: &mut impl Write,
+    u_id: &NumberSpecifier<'_>,
+    factor: &str,
+    timestamp: u64,
+) -> std::io::Result<()> {
+    match u_id {
+        Id(id) => writeln!(out, "{id},{factor},{timestamp}"),
+        Expression(x) => writeln!(out, "{},{factor},{timestamp}", x.to_unelided_string()),
+    }
+}
+
 impl FactorDbClientReadIdsAndExprs for RealFactorDbClient {
     fn read_ids_and_exprs<'a>(
         &self,
@@ -737,6 +1714,54 @@ pub struct ProcessedStatusApiResponse {
     pub id: Option<EntryId>,
 }
 
+impl ProcessedStatusApiResponse {
+    /// If FactorDB knows more than `max_factors` factors of the number and all but one of them
+    /// are small enough to be numeric, returns the remaining one. Searching such a number for
+    /// factors mostly finds ones FactorDB already has, so the remaining cofactor is better left to
+    /// yafu.
+    pub fn crowded_cofactor(&self, max_factors: usize) -> Option<&Factor> {
+        if self.factors.len() <= max_factors {
+            return None;
+        }
+        self.factors
+            .iter()
+            .filter(|factor| !matches!(factor, Numeric(_)))
+            .exactly_one()
+            .ok()
+    }
+}
+
+/// Fetches the known factors and statuses of several numbers, up to [MAX_PARALLEL_REQUESTS] at
+/// once. That's only concurrent if the operator has raised [MAX_PARALLEL_REQUESTS] above its
+/// default of 1, in which case e.g. the N-1 and N+1 of a PRP don't wait for each other. Numbers
+/// whose fetch fails map to the default (empty) response.
+pub async fn known_factors_of_ids(
+    http: &impl FactorDbClient,
+    ids: impl IntoIterator<Item = EntryId>,
+    include_ff: bool,
+    get_digits_as_fallback: bool,
+) -> BTreeMap<EntryId, ProcessedStatusApiResponse> {
+    fetch_concurrently(ids, MAX_PARALLEL_REQUESTS.load(Acquire).max(1), |id| {
+        http.known_factors_as_digits(Id(id), include_ff, get_digits_as_fallback)
+    })
+    .await
+}
+
+async fn fetch_concurrently<T, F: Future<Output = T>>(
+    ids: impl IntoIterator<Item = EntryId>,
+    max_in_flight: usize,
+    fetch: impl Fn(EntryId) -> F,
+) -> BTreeMap<EntryId, T> {
+    stream::iter(ids)
+        .map(|id| {
+            let response = fetch(id);
+            async move { (id, response.await) }
+        })
+        .buffer_unordered(max_in_flight)
+        .collect()
+        .await
+}
+
 pub trait NumberStatusExt {
     fn is_known_fully_factored(&self) -> bool;
 }
@@ -748,7 +1773,7 @@ impl NumberStatusExt for Option<NumberStatus> {
     }
 }
 
-#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug)]
+#[derive(Eq, PartialEq, Ord, PartialOrd, Copy, Clone, Debug, Serialize, Deserialize)]
 pub enum NumberStatus {
     Unknown,
     UnfactoredComposite,
@@ -756,3 +1781,685 @@ pub enum NumberStatus {
     Prime, // includes PRP
     FullyFactored,
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::NumberSpecifier::{Expression, Id};
+    use crate::monitor::Monitor;
+    use crate::net::{
+        CIRCUIT_BREAKER_COOL_DOWN, CIRCUIT_BREAKER_THRESHOLD, CIRCUIT_BREAKER_WINDOW,
+        CircuitBreaker, CpuReserve, E2E_TIMEOUT, FactorDbClientReadIdsAndExprs, FactorDbError,
+        FetchOutcome, PARALLEL_REQUEST_THROTTLING_DURATION, PageCache, PolitenessWindow,
+        RESOURCE_CYCLE_DURATION, RealFactorDbClient, ReserveCurve, SERVER_ERROR_BACKOFF,
+        SearchThrottle, listed_factors_section, utc_seconds_of_day, write_accepted_factor_row,
+    };
+    use crate::{Factor, NumberStatusApiResponse};
+    use serde_json::from_str;
+    use std::borrow::Cow;
+    use std::num::NonZeroU32;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use tokio::time::{Duration, Instant, sleep, timeout};
+
+    #[tokio::test]
+    async fn test_page_cache() {
+        let cache = PageCache::new(16, Duration::from_millis(200));
+        let fetches = AtomicUsize::new(0);
+        let fetch = || async {
+            fetches.fetch_add(1, SeqCst);
+            FetchOutcome::Fetched("page".into())
+        };
+        let url = "https://factordb.com/frame_prime.php?id=1";
+        assert!(PageCache::is_cacheable(url));
+        for _ in 0..2 {
+            assert_eq!(
+                cache.get_or_fetch(url, fetch()).await,
+                FetchOutcome::Fetched("page".into())
+            );
+        }
+        assert_eq!(fetches.load(SeqCst), 1);
+        sleep(Duration::from_millis(300)).await;
+        cache.get_or_fetch(url, fetch()).await;
+        assert_eq!(fetches.load(SeqCst), 2);
+
+        assert!(!PageCache::is_cacheable(
+            "https://factordb.com/index.php?id=1&prp=Assign+to+worker"
+        ));
+        assert!(!PageCache::is_cacheable(
+            "https://factordb.com/index.php?id=1&open=prime&basetocheck=3"
+        ));
+        assert!(!PageCache::is_cacheable(
+            "https://factordb.com/sequences.php?check=1"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shutdown_cancels_pending_request() {
+        let (shutdown_sender, shutdown) = Monitor::new();
+        let client =
+            RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap()).with_shutdown(shutdown);
+        assert_eq!(client.unless_shutdown(async { 1 }).await, Ok(1));
+
+        let pending_request = client.unless_shutdown(std::future::pending::<()>());
+        let shutdown_after_delay = async {
+            sleep(Duration::from_millis(100)).await;
+            shutdown_sender.send(()).unwrap();
+        };
+        let (result, _) = timeout(
+            Duration::from_secs(5),
+            futures_util::future::join(pending_request, shutdown_after_delay),
+        )
+        .await
+        .expect("pending request wasn't cancelled");
+        assert_eq!(result, Err(FactorDbError::Cancelled));
+
+        // Requests that start after shutdown don't wait either
+        assert_eq!(
+            client.unless_shutdown(std::future::pending::<()>()).await,
+            Err(FactorDbError::Cancelled)
+        );
+        assert!(client.is_shutting_down());
+    }
+
+    #[tokio::test]
+    async fn test_politeness_window() {
+        let windows = PolitenessWindow::parse_list("02:00-04:30, 23:00-00:15").unwrap();
+        assert_eq!(
+            *windows,
+            [
+                PolitenessWindow {
+                    start: 2 * 60 * 60,
+                    end: 4 * 60 * 60 + 30 * 60
+                },
+                PolitenessWindow {
+                    start: 23 * 60 * 60,
+                    end: 15 * 60
+                },
+            ]
+        );
+        assert_eq!(
+            windows[0].remaining(4 * 60 * 60),
+            Some(Duration::from_secs(30 * 60))
+        );
+        assert_eq!(windows[0].remaining(5 * 60 * 60), None);
+        // Windows can wrap past midnight
+        assert_eq!(
+            windows[1].remaining(23 * 60 * 60 + 59 * 60),
+            Some(Duration::from_secs(16 * 60))
+        );
+        assert_eq!(
+            windows[1].remaining(60),
+            Some(Duration::from_secs(14 * 60 - 1))
+        );
+        assert_eq!(windows[1].remaining(12 * 60 * 60), None);
+        assert!(PolitenessWindow::parse_list("02:00").is_err());
+        assert!(PolitenessWindow::parse_list("02:00-24:00").is_err());
+        assert!(PolitenessWindow::parse_list("02:00-02:00").is_err());
+    }
+
+    /// A window that started a minute ago and ends `seconds_left` seconds from now.
+    fn politeness_window_in_progress(seconds_left: u32) -> PolitenessWindow {
+        let now = utc_seconds_of_day();
+        PolitenessWindow {
+            start: (now + super::SECONDS_PER_DAY - 60) % super::SECONDS_PER_DAY,
+            end: (now + seconds_left) % super::SECONDS_PER_DAY,
+        }
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_request_waits_for_politeness_window() {
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        client.politeness_windows = Box::new([politeness_window_in_progress(2)]);
+        let start = Instant::now();
+        // Nothing listens on the discard port, so the request fails fast once it's sent
+        timeout(
+            Duration::from_secs(10),
+            client.try_get_and_decode_core("http://127.0.0.1:9/"),
+        )
+        .await
+        .expect("request still waiting after the window ended")
+        .unwrap_err();
+        assert!(start.elapsed() >= Duration::from_secs(1));
+
+        // Outside any window, it doesn't wait
+        client.politeness_windows = Box::new([]);
+        let start = Instant::now();
+        client
+            .try_get_and_decode_core("http://127.0.0.1:9/")
+            .await
+            .unwrap_err();
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_factor_report_waits_for_politeness_window() {
+        use crate::net::FactorDbClient;
+
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        client.politeness_windows = Box::new([politeness_window_in_progress(60 * 60)]);
+        // Still pending when dropped, since the window is still in progress, so it never reaches
+        // FactorDB. The ID has to be too large to be the number's value, or the report would be
+        // refused without waiting.
+        let report = client.try_report_factor(Id(1_100_000_000_000_000_001), &Factor::from("3"));
+        assert!(timeout(Duration::from_secs(3), report).await.is_err());
+    }
+
+    #[test]
+    fn test_accepted_factor_row() {
+        let mut out = Vec::new();
+        write_accepted_factor_row(
+            &mut out,
+            &Id(1_100_000_000_123_456_789),
+            "1000003",
+            1_700_000_000,
+        )
+        .unwrap();
+        write_accepted_factor_row(
+            &mut out,
+            &Expression(Cow::Owned(Factor::from(
+                "1234567890123456789012345678901234567890123",
+            ))),
+            "7",
+            1_700_000_001,
+        )
+        .unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "1100000000123456789,1000003,1700000000\n\
+            1234567890123456789012345678901234567890123,7,1700000001\n"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_search_throttle() {
+        const MIN_INTERVAL: Duration = Duration::from_millis(200);
+        let throttle = SearchThrottle::new(16, MIN_INTERVAL);
+        let url = "https://factordb.com/listtype.php?t=3&perpage=64&start=0&mindig=90";
+        let start = Instant::now();
+        throttle.wait_turn(url).await;
+        assert!(start.elapsed() < MIN_INTERVAL);
+        throttle.wait_turn(url).await;
+        assert!(start.elapsed() >= MIN_INTERVAL);
+
+        // A retry with a smaller page is delayed too
+        throttle
+            .wait_turn("https://factordb.com/listtype.php?t=3&perpage=32&start=0&mindig=90")
+            .await;
+        assert!(start.elapsed() >= 2 * MIN_INTERVAL);
+
+        // Other searches, the next pages of a batch, and pages that aren't searches, aren't delayed
+        let start = Instant::now();
+        throttle
+            .wait_turn("https://factordb.com/listtype.php?t=3&perpage=64&start=0&mindig=91")
+            .await;
+        for batch_start in [64, 128, 192] {
+            throttle
+                .wait_turn(&format!(
+                    "https://factordb.com/listtype.php?t=3&perpage=64&start={batch_start}&mindig=90"
+                ))
+                .await;
+        }
+        throttle
+            .wait_turn("https://factordb.com/frame_prime.php?id=1")
+            .await;
+        throttle
+            .wait_turn("https://factordb.com/frame_prime.php?id=1")
+            .await;
+        assert!(start.elapsed() < MIN_INTERVAL);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_pause_blocks_requests() {
+        use std::sync::atomic::AtomicBool;
+        use std::sync::atomic::Ordering::Release;
+        use tokio::time::timeout;
+
+        let paused: &'static AtomicBool = Box::leak(Box::new(AtomicBool::new(true)));
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        client.paused = paused;
+        // Nothing listens on the discard port, so the request fails fast once it's sent
+        let request = client.try_get_and_decode_core("http://127.0.0.1:9/");
+        tokio::pin!(request);
+        assert!(timeout(Duration::from_secs(3), &mut request).await.is_err());
+        paused.store(false, Release);
+        assert!(timeout(Duration::from_secs(10), request).await.is_ok());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_pause_blocks_factor_reports() {
+        use crate::net::FactorDbClient;
+        use std::sync::atomic::AtomicBool;
+
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        client.paused = Box::leak(Box::new(AtomicBool::new(true)));
+        // Dropped while still paused, so it never reaches FactorDB
+        let report = client.try_report_factor(Id(1 << 40), &Factor::from("3"));
+        assert!(timeout(Duration::from_secs(3), report).await.is_err());
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_request_budget_per_cycle() {
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        client.max_requests_per_cycle = 2;
+        // Nothing listens on the discard port, so these fail but still count against the budget
+        for _ in 0..2 {
+            assert_eq!(
+                client.try_fetch_uncached("http://127.0.0.1:9/").await,
+                FetchOutcome::Failed
+            );
+        }
+        match client.try_fetch_uncached("http://127.0.0.1:9/").await {
+            FetchOutcome::Throttled(Some(until_reset)) => {
+                assert!(until_reset <= RESOURCE_CYCLE_DURATION)
+            }
+            outcome => panic!("Expected throttling, got {outcome:?}"),
+        }
+        // Once the cycle resets, requests are allowed again
+        client.cycle_resets_at.store(Instant::now().into(), SeqCst);
+        assert_eq!(
+            client.try_fetch_uncached("http://127.0.0.1:9/").await,
+            FetchOutcome::Failed
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_borderline_id_equal_to_value_is_verified() {
+        use crate::algebraic::Factor;
+        use crate::net::FactorDbClient;
+        use crate::{MAX_ID_EQUAL_TO_VALUE, NumberSpecifier::Id};
+
+        const BORDERLINE: u128 = MAX_ID_EQUAL_TO_VALUE - 1;
+
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        // Every request is refused, so nothing borderline can be verified
+        client.max_requests_per_cycle = 0;
+        assert_eq!(
+            client.try_get_expression_form(12345).await,
+            Some(Factor::Numeric(12345))
+        );
+        assert!(client.cached_factors(&Id(12345)).is_some());
+        assert_eq!(client.requests_this_cycle.load(SeqCst), 0);
+
+        // Unverified and unable to ask, so a borderline ID isn't presumed
+        assert!(client.cached_factors(&Id(BORDERLINE)).is_none());
+        assert_eq!(client.try_get_expression_form(BORDERLINE).await, None);
+        assert_eq!(client.id_equal_to_value_cache.get(&BORDERLINE), None);
+
+        // Stand in for FactorDB with cached API responses, so that the only way to verify is to
+        // make the request
+        client.page_cache = PageCache::new(16, Duration::from_secs(3600));
+        for (value, id) in [(BORDERLINE, BORDERLINE), (BORDERLINE - 1, 1)] {
+            client.page_cache.pages.insert(
+                format!("https://factordb.com/api?query={value}").into(),
+                (
+                    Instant::now(),
+                    format!(r#"{{"id":"{id}","status":"C","factors":[]}}"#).into(),
+                ),
+            );
+        }
+        assert_eq!(
+            client.try_get_expression_form(BORDERLINE).await,
+            Some(Factor::Numeric(BORDERLINE))
+        );
+        assert_eq!(client.id_equal_to_value_cache.get(&BORDERLINE), Some(true));
+        assert!(client.cached_factors(&Id(BORDERLINE)).is_some());
+        assert!(!client.id_equals_value(BORDERLINE - 1).await);
+        assert_eq!(
+            client.id_equal_to_value_cache.get(&(BORDERLINE - 1)),
+            Some(false)
+        );
+
+        // Once verified, a borderline ID is used without asking again
+        client.page_cache = PageCache::new(16, Duration::from_secs(3600));
+        assert_eq!(
+            client.try_get_expression_form(BORDERLINE).await,
+            Some(Factor::Numeric(BORDERLINE))
+        );
+        assert!(!client.id_equals_value(BORDERLINE - 1).await);
+        assert_eq!(client.requests_this_cycle.load(SeqCst), 0);
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_invalidated_factors_are_fetched_again() {
+        use crate::NumberSpecifier::Id;
+        use crate::algebraic::Factor;
+        use crate::graph::EntryId;
+        use crate::net::FactorDbClient;
+        use crate::net::NumberStatus::UnfactoredComposite;
+
+        const ID: EntryId = 1_100_000_000_000_000_001;
+
+        let mut client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        // Every request that gets past the cache is refused
+        client.max_requests_per_cycle = 0;
+        client.page_cache = PageCache::new(16, Duration::from_secs(3600));
+        client.page_cache.pages.insert(
+            format!("https://factordb.com/api?id={ID}").into(),
+            (
+                Instant::now(),
+                format!(r#"{{"id":"{ID}","status":"C","factors":[]}}"#).into(),
+            ),
+        );
+        assert_eq!(
+            client
+                .known_factors_as_digits(Id(ID), false, false)
+                .await
+                .status,
+            Some(UnfactoredComposite)
+        );
+
+        // After a factor is reported, the page from before the report isn't reused
+        client.invalidate_cached_factors(Some(ID), &Factor::from("2^4423-1"));
+        assert_eq!(
+            client
+                .known_factors_as_digits(Id(ID), false, false)
+                .await
+                .status,
+            None
+        );
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_known_factors_of_ids() {
+        use crate::NumberSpecifier::Id;
+        use crate::algebraic::Factor;
+        use crate::net::NumberStatus::{FullyFactored, UnfactoredComposite};
+        use crate::net::{
+            MockFactorDbClient, ProcessedStatusApiResponse, fetch_concurrently,
+            known_factors_of_ids,
+        };
+        use std::collections::BTreeMap;
+        use tokio::sync::Barrier;
+        use tokio::time::timeout;
+
+        let mut http = MockFactorDbClient::new();
+        http.expect_known_factors_as_digits()
+            .times(2)
+            .returning(|id, _, _| match id {
+                Id(1) => ProcessedStatusApiResponse {
+                    status: Some(FullyFactored),
+                    factors: Box::new([]),
+                    id: Some(1),
+                },
+                _ => ProcessedStatusApiResponse {
+                    status: Some(UnfactoredComposite),
+                    factors: Box::new([Factor::Numeric(3)]),
+                    id: Some(2),
+                },
+            });
+        let responses = known_factors_of_ids(&http, [1, 2], false, false).await;
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[&1].status, Some(FullyFactored));
+        assert_eq!(responses[&2].status, Some(UnfactoredComposite));
+        assert_eq!(*responses[&2].factors, [Factor::Numeric(3)]);
+
+        // Neither fetch can finish until both have started
+        let barrier = Barrier::new(2);
+        let results = timeout(
+            Duration::from_secs(10),
+            fetch_concurrently([1, 2], 2, |id| {
+                let barrier = &barrier;
+                async move {
+                    barrier.wait().await;
+                    id * 10
+                }
+            }),
+        )
+        .await
+        .expect("Fetches should run concurrently");
+        assert_eq!(results, BTreeMap::from([(1, 10), (2, 20)]));
+    }
+
+    #[test]
+    fn test_check_for_throttle_page() {
+        let client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        assert_eq!(
+            client.check_for_throttle_page(
+                "<html><body><b>Please wait 30 seconds before assigning another number.</b></body></html>"
+                    .into()
+            ),
+            Err(FactorDbError::Throttled(Some(Duration::from_secs(30))))
+        );
+        assert_eq!(
+            client.check_for_throttle_page("<td>Please wait</td>".into()),
+            Err(FactorDbError::Throttled(None))
+        );
+        assert_eq!(
+            client.check_for_throttle_page("<title>Please wait</title>".into()),
+            Err(FactorDbError::Throttled(None))
+        );
+        let page = "<td>PRP</td><td>Assigned</td>";
+        assert_eq!(client.check_for_throttle_page(page.into()), Ok(page.into()));
+        // A normal page that quotes the phrase isn't a throttle page
+        let page = "<html><head><title>FactorDB</title></head><body><table><tr><td>C</td>\
+            <td>Please wait 30 seconds between assignments</td></tr></table></body></html>";
+        assert_eq!(client.check_for_throttle_page(page.into()), Ok(page.into()));
+    }
+
+    #[test]
+    fn test_factordb_error_variants() {
+        let client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        assert_eq!(
+            client.check_response_text("<h1>502 Proxy Error</h1>".into()),
+            Err(FactorDbError::HttpStatus(502))
+        );
+        assert_eq!(
+            client.check_response_text(String::new()),
+            Err(FactorDbError::EmptyBody)
+        );
+        assert_eq!(
+            client.check_response_text("Too many parallel processing requests".into()),
+            Err(FactorDbError::TooManyParallelRequests)
+        );
+        assert_eq!(
+            client.check_response_text("Please wait 5 seconds".into()),
+            Err(FactorDbError::Throttled(Some(Duration::from_secs(5))))
+        );
+        assert_eq!(
+            client.check_response_text("<td>C</td>".into()),
+            Ok("<td>C</td>".into())
+        );
+
+        // CURLE_OPERATION_TIMEDOUT and CURLE_COULDNT_CONNECT
+        assert_eq!(
+            FactorDbError::from(curl::Error::new(28)),
+            FactorDbError::Timeout
+        );
+        assert!(matches!(
+            FactorDbError::from(curl::Error::new(7)),
+            FactorDbError::Connection(_)
+        ));
+        assert!(matches!(
+            FactorDbError::from(String::from_utf8(vec![0xff]).unwrap_err()),
+            FactorDbError::Decode(_)
+        ));
+
+        // Only throttling is worth telling apart from other failures when fetching a page
+        assert_eq!(
+            FetchOutcome::from(FactorDbError::TooManyParallelRequests),
+            FetchOutcome::Throttled(Some(PARALLEL_REQUEST_THROTTLING_DURATION))
+        );
+        assert_eq!(
+            FetchOutcome::from(FactorDbError::Throttled(None)),
+            FetchOutcome::Throttled(None)
+        );
+        // An overloaded server gets a long rest, while other failures are retried after the usual
+        // short delay
+        for status in [500, 502, 503] {
+            assert_eq!(
+                FetchOutcome::from(FactorDbError::HttpStatus(status)),
+                FetchOutcome::Throttled(Some(SERVER_ERROR_BACKOFF))
+            );
+        }
+        for error in [
+            FactorDbError::Timeout,
+            FactorDbError::Connection("connection reset by peer".into()),
+            FactorDbError::HttpStatus(404),
+            FactorDbError::EmptyBody,
+        ] {
+            assert_eq!(FetchOutcome::from(error), FetchOutcome::Failed);
+        }
+    }
+
+    #[test]
+    fn test_decode_api_response() {
+        let response = from_str::<NumberStatusApiResponse>(
+            r#"{"id":"1100000000003145467","status":"PRP","factors":[["3*10^999+1",1]]}"#,
+        )
+        .unwrap();
+        assert_eq!(response.id.to_string(), "\"1100000000003145467\"");
+        assert_eq!(response.status, "PRP");
+        assert_eq!(response.factors.len(), 1);
+        assert_eq!(response.factors[0].0, "3*10^999+1");
+        assert_eq!(response.factors[0].1, 1);
+
+        let response = from_str::<NumberStatusApiResponse>(
+            r#"{"id":360,"status":"FF","factors":[["2",3],["3",2],["5",1]]}"#,
+        )
+        .unwrap();
+        assert_eq!(response.id.to_string(), "360");
+        assert_eq!(response.status, "FF");
+        assert_eq!(
+            response
+                .factors
+                .iter()
+                .map(|(factor, exponent)| (factor.as_str(), *exponent))
+                .collect::<Vec<_>>(),
+            vec![("2", 3), ("3", 2), ("5", 1)]
+        );
+    }
+
+    #[test]
+    fn test_circuit_breaker() {
+        let breaker = CircuitBreaker::default();
+        let mut now = Instant::now();
+        for _ in 1..CIRCUIT_BREAKER_THRESHOLD {
+            breaker.record_failure(now);
+            assert_eq!(breaker.blocked_until(now), None);
+            now += Duration::from_secs(1);
+        }
+        breaker.record_failure(now);
+        let open_until = now + CIRCUIT_BREAKER_COOL_DOWN;
+        assert_eq!(breaker.blocked_until(now), Some(open_until));
+        assert_eq!(
+            breaker.blocked_until(open_until - Duration::from_secs(1)),
+            Some(open_until)
+        );
+
+        assert_eq!(breaker.admit(now), Err(open_until));
+
+        // Half-open: one probe is allowed, and a single failure reopens the breaker
+        now = open_until;
+        assert_eq!(breaker.blocked_until(now), None);
+        let probe = now + E2E_TIMEOUT;
+        assert_eq!(breaker.admit(now), Ok(Some(probe)));
+        assert_eq!(breaker.blocked_until(now), Some(probe));
+        assert_eq!(breaker.admit(now), Err(probe));
+        breaker.record_failure(now);
+        breaker.end_probe(probe);
+        let open_until = now + CIRCUIT_BREAKER_COOL_DOWN;
+        assert_eq!(breaker.admit(now), Err(open_until));
+
+        // A probe that ends without a result, or never reports back, is replaced
+        now = open_until;
+        let probe = now + E2E_TIMEOUT;
+        assert_eq!(breaker.admit(now), Ok(Some(probe)));
+        breaker.end_probe(probe);
+        assert_eq!(breaker.admit(now), Ok(Some(probe)));
+        now += E2E_TIMEOUT;
+        let probe = now + E2E_TIMEOUT;
+        assert_eq!(breaker.admit(now), Ok(Some(probe)));
+        assert_eq!(breaker.admit(now), Err(probe));
+
+        // A successful probe closes it again
+        breaker.record_success();
+        breaker.end_probe(probe);
+        assert_eq!(breaker.admit(now), Ok(None));
+        breaker.record_failure(now);
+        assert_eq!(breaker.admit(now), Ok(None));
+    }
+
+    #[test]
+    fn test_circuit_breaker_window() {
+        let breaker = CircuitBreaker::default();
+        let mut now = Instant::now();
+        for _ in 0..CIRCUIT_BREAKER_THRESHOLD {
+            breaker.record_failure(now);
+            now += CIRCUIT_BREAKER_WINDOW / CIRCUIT_BREAKER_THRESHOLD as u32 * 2;
+        }
+        assert_eq!(breaker.blocked_until(now), None);
+    }
+
+    #[test]
+    fn test_cpu_reserve_parameters() {
+        let default = CpuReserve::with_overrides(None, None).unwrap();
+        assert_eq!(default, CpuReserve::default());
+        // An hour before reset, the default reserves 3600^2/18000 tenths
+        assert_eq!(default.reserved_tenths(3600.0), 720);
+
+        let halved = CpuReserve::with_overrides(Some("36000".into()), None).unwrap();
+        assert_eq!(halved.reserved_tenths(3600.0), 360);
+
+        let linear = CpuReserve::with_overrides(Some("10".into()), Some("Linear".into())).unwrap();
+        assert_eq!(linear.curve, ReserveCurve::Linear);
+        assert_eq!(linear.reserved_tenths(3600.0), 360);
+        assert_eq!(linear.reserved_tenths(7200.0), 720);
+
+        assert!(CpuReserve::with_overrides(Some("0".into()), None).is_err());
+        assert!(CpuReserve::with_overrides(Some("abc".into()), None).is_err());
+        assert!(CpuReserve::with_overrides(None, Some("cubic".into())).is_err());
+    }
+
+    #[test]
+    fn test_listed_factors_section() {
+        const HEADER: &str = "<a href=\"index.php?id=3\"><font color=\"#000000\">3</font></a>\
+            <table><tr><td>Status</td><td>digits</td><td>number</td></tr>";
+        let page = format!(
+            "{HEADER}<tr><td>CF</td><td>4</td><td><a href=\"index.php?id=1155\">\
+            <font color=\"#002099\">1155</font></a> = <a href=\"index.php?id=5\">\
+            <font color=\"#000000\">5</font></a> &middot; <a href=\"index.php?id=231\">\
+            <font color=\"#000000\">231</font></a></td></tr></table>\
+            <a href=\"index.php?id=7\"><font color=\"#000000\">7</font></a>"
+        );
+        let client = RealFactorDbClient::new(NonZeroU32::new(10_000).unwrap());
+        let listed: Vec<_> = client
+            .read_ids_and_exprs(listed_factors_section(&page).unwrap())
+            .collect();
+        assert_eq!(listed, [(5, "5"), (231, "231")]);
+
+        let unfactored = format!(
+            "{HEADER}<tr><td>C</td><td>4</td><td><a href=\"index.php?id=1157\">\
+            <font color=\"#002099\">1157</font></a></td></tr></table>"
+        );
+        assert_eq!(listed_factors_section(&unfactored), Some(""));
+        assert_eq!(
+            listed_factors_section("<html>Too many requests</html>"),
+            None
+        );
+    }
+
+    #[test]
+    fn test_crowded_cofactor() {
+        use crate::algebraic::Factor;
+        use crate::net::NumberStatus::PartlyFactoredComposite;
+        use crate::net::ProcessedStatusApiResponse;
+
+        let response = |factors: Vec<Factor>| ProcessedStatusApiResponse {
+            status: Some(PartlyFactoredComposite),
+            factors: factors.into_boxed_slice(),
+            id: Some(1),
+        };
+        let cofactor = Factor::from("2^1277-1");
+        let mut factors: Vec<Factor> = (2..=300).map(Factor::Numeric).collect();
+        factors.push(cofactor.clone());
+        assert_eq!(
+            response(factors.clone()).crowded_cofactor(100),
+            Some(&cofactor)
+        );
+        // Not enough known factors to skip the search
+        assert_eq!(response(factors.clone()).crowded_cofactor(1000), None);
+        // Not mostly factored: more than one large factor is left
+        factors.push(Factor::from("2^1279-1"));
+        assert_eq!(response(factors).crowded_cofactor(100), None);
+    }
+}
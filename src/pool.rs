@@ -0,0 +1,201 @@
+use crate::monitor::Monitor;
+use async_backtrace::framed;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::Acquire;
+use std::sync::{Arc, Mutex, OnceLock};
+use tokio::select;
+use tokio::sync::Semaphore;
+use tokio::task::{self, AbortHandle, JoinError};
+use tokio::time::{Duration, timeout};
+
+/// How many factor-finder jobs may run on the blocking pool at once. Callers beyond this wait for
+/// a slot, so a burst of huge expressions can't starve the runtime of blocking threads or the
+/// network tasks of CPU time. Overridden by the `MAX_CONCURRENT_FACTORING_JOBS` environment
+/// variable.
+pub static MAX_CONCURRENT_FACTORING_JOBS: AtomicUsize = AtomicUsize::new(2);
+
+pub static FACTORING_POOL: OnceLock<FactoringPool> = OnceLock::new();
+
+/// Returns the pool that `main` installed, or an uncancellable one if none was (e.g. in tests and
+/// one-off subcommands).
+pub fn factoring_pool() -> &'static FactoringPool {
+    FACTORING_POOL
+        .get_or_init(|| FactoringPool::new(MAX_CONCURRENT_FACTORING_JOBS.load(Acquire), None))
+}
+
+#[derive(Debug)]
+pub enum JobOutcome<T> {
+    Done(T),
+    TimedOut,
+    Failed(JoinError),
+    ShutDown,
+}
+
+/// Runs CPU-heavy factoring on tokio's blocking pool, with a bounded number of jobs at once.
+/// Waiting for a job can be abandoned on shutdown or timeout, unlike `block_in_place`. A job that
+/// hasn't started yet is then cancelled, releasing its slot; one that has keeps running until it
+/// finishes, and keeps its slot until then, since it's still using the CPU.
+#[derive(Debug)]
+pub struct FactoringPool {
+    slots: Arc<Semaphore>,
+    shutdown: Option<Mutex<Monitor>>,
+}
+
+impl FactoringPool {
+    pub fn new(max_concurrent_jobs: usize, shutdown: Option<Monitor>) -> Self {
+        FactoringPool {
+            slots: Arc::new(Semaphore::new(max_concurrent_jobs)),
+            shutdown: shutdown.map(Mutex::new),
+        }
+    }
+
+    fn shutdown_monitor(&self) -> Option<Monitor> {
+        let shutdown = self.shutdown.as_ref()?;
+        let mut shutdown = shutdown.lock().unwrap();
+        // Checking the original receiver first catches a signal sent before we subscribed
+        shutdown.check_for_shutdown();
+        Some(shutdown.clone())
+    }
+
+    /// Runs `job`, giving up if it hasn't finished within `time_limit` (including time spent
+    /// waiting for a slot) or shutdown begins first.
+    #[framed]
+    pub async fn run<T: Send + 'static>(
+        &self,
+        job: impl FnOnce() -> T + Send + 'static,
+        time_limit: Duration,
+    ) -> JobOutcome<T> {
+        let mut shutdown = self.shutdown_monitor();
+        if shutdown
+            .as_mut()
+            .is_some_and(|shutdown| shutdown.check_for_shutdown())
+        {
+            return JobOutcome::ShutDown;
+        }
+        let slots = self.slots.clone();
+        let run_job = async move {
+            let Ok(slot) = slots.acquire_owned().await else {
+                unreachable!("factoring pool semaphore is never closed");
+            };
+            // Held by the job, so that it's released only when the job finishes or is cancelled
+            let job = task::spawn_blocking(move || {
+                let _slot = slot;
+                job()
+            });
+            let _abort_if_abandoned = AbortOnDrop(job.abort_handle());
+            job.await
+        };
+        let shutdown_signal = async {
+            match shutdown.as_mut() {
+                Some(shutdown) => shutdown.recv().await,
+                None => std::future::pending().await,
+            }
+        };
+        select! {
+            biased;
+            _ = shutdown_signal => JobOutcome::ShutDown,
+            result = timeout(time_limit, run_job) => match result {
+                Ok(Ok(result)) => JobOutcome::Done(result),
+                Ok(Err(e)) => JobOutcome::Failed(e),
+                Err(_) => JobOutcome::TimedOut,
+            },
+        }
+    }
+}
+
+/// Aborts a task when dropped, which cancels a blocking job that hasn't started running yet.
+struct AbortOnDrop(AbortHandle);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        self.0.abort();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::monitor::Monitor;
+    use crate::pool::{FactoringPool, JobOutcome};
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::atomic::Ordering::SeqCst;
+    use std::time::Instant;
+    use tokio::time::Duration;
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_shutdown_cancels_pending_job() {
+        let (shutdown_sender, shutdown_receiver) = Monitor::new();
+        let pool = FactoringPool::new(1, Some(shutdown_receiver));
+        let start = Instant::now();
+        let pending = pool.run(
+            || std::thread::sleep(Duration::from_secs(2)),
+            Duration::from_secs(60),
+        );
+        let send_shutdown = async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            shutdown_sender.send(()).unwrap();
+        };
+        let (outcome, _) = tokio::join!(pending, send_shutdown);
+        assert!(matches!(outcome, JobOutcome::ShutDown));
+        assert!(start.elapsed() < Duration::from_secs(2));
+
+        // Jobs started after shutdown don't run at all
+        assert!(matches!(
+            pool.run(|| (), Duration::from_secs(60)).await,
+            JobOutcome::ShutDown
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_job_completes_without_shutdown() {
+        let pool = FactoringPool::new(1, None);
+        assert!(matches!(
+            pool.run(|| 6 * 7, Duration::from_secs(60)).await,
+            JobOutcome::Done(42)
+        ));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_timed_out_job_keeps_slot_until_finished() {
+        let pool = FactoringPool::new(1, None);
+        let start = Instant::now();
+        assert!(matches!(
+            pool.run(
+                || std::thread::sleep(Duration::from_secs(1)),
+                Duration::from_millis(100)
+            )
+            .await,
+            JobOutcome::TimedOut
+        ));
+        // The first job is still running, so the next one has to wait for it to finish
+        assert!(matches!(
+            pool.run(|| 6 * 7, Duration::from_secs(5)).await,
+            JobOutcome::Done(42)
+        ));
+        assert!(start.elapsed() >= Duration::from_secs(1));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_concurrent_jobs_limited() {
+        let pool = FactoringPool::new(2, None);
+        let running = Arc::new(AtomicUsize::new(0));
+        let max_running = Arc::new(AtomicUsize::new(0));
+        let jobs = (0..8).map(|_| {
+            let running = running.clone();
+            let max_running = max_running.clone();
+            pool.run(
+                move || {
+                    let now_running = running.fetch_add(1, SeqCst) + 1;
+                    max_running.fetch_max(now_running, SeqCst);
+                    std::thread::sleep(Duration::from_millis(50));
+                    running.fetch_sub(1, SeqCst);
+                },
+                Duration::from_secs(60),
+            )
+        });
+        for outcome in futures_util::future::join_all(jobs).await {
+            assert!(matches!(outcome, JobOutcome::Done(())));
+        }
+        assert_eq!(max_running.load(SeqCst), 2);
+    }
+}
@@ -0,0 +1,194 @@
+use crate::net::CPU_TENTHS_SPENT_LAST_CHECK;
+use crate::stats::RUN_STATS;
+use log::info;
+use rand::RngExt;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Acquire};
+
+/// Kinds of work that compete for FactorDB's CPU budget in the check loop. They take turns, so
+/// no two units of work are ever measured at once.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WorkCategory {
+    Prp = 0,
+    C = 1,
+}
+
+const CATEGORY_COUNT: usize = 2;
+
+/// Smallest share of the selection weight that any category gets, so that none is starved and
+/// its yield keeps being measured.
+const MIN_WEIGHT: f64 = 0.05;
+
+#[derive(Clone, Copy, Debug, Default)]
+struct CategoryStats {
+    cpu_tenths: usize,
+    factors: usize,
+}
+
+/// CPU time and accepted-factor counts when a unit of work started.
+#[derive(Clone, Copy, Debug)]
+pub struct WorkSnapshot {
+    category: WorkCategory,
+    cpu_tenths_spent: usize,
+    factors_accepted: usize,
+    untracked_factors: usize,
+}
+
+/// Weights each category of work by the factors it has yielded per tenth of a CPU second that
+/// FactorDB has spent on it, so that whichever is currently most productive is chosen most often.
+#[derive(Debug)]
+pub struct WorkRatioController {
+    stats: Mutex<[CategoryStats; CATEGORY_COUNT]>,
+    /// Factors accepted by work running alongside the check loop, such as U submissions, which
+    /// mustn't be credited to whichever check happened to be in progress.
+    untracked_factors: AtomicUsize,
+}
+
+pub static WORK_RATIO: WorkRatioController = WorkRatioController::new();
+
+impl Default for WorkRatioController {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WorkRatioController {
+    pub const fn new() -> Self {
+        WorkRatioController {
+            stats: Mutex::new(
+                [CategoryStats {
+                    cpu_tenths: 0,
+                    factors: 0,
+                }; CATEGORY_COUNT],
+            ),
+            untracked_factors: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn record(&self, category: WorkCategory, cpu_tenths: usize, factors: usize) {
+        let mut stats = self.stats.lock().unwrap();
+        let stats = &mut stats[category as usize];
+        stats.cpu_tenths += cpu_tenths;
+        stats.factors += factors;
+    }
+
+    /// Call when starting a unit of work, then pass the result to [WorkRatioController::finish]
+    /// once it's done. CPU time is only as precise as the most recent resource-limit check.
+    pub fn start(&self, category: WorkCategory) -> WorkSnapshot {
+        WorkSnapshot {
+            category,
+            cpu_tenths_spent: CPU_TENTHS_SPENT_LAST_CHECK.load(Acquire),
+            factors_accepted: RUN_STATS.factors_accepted(),
+            untracked_factors: self.untracked_factors.load(Acquire),
+        }
+    }
+
+    /// Call when work outside the check loop gets factors accepted, so that they aren't credited
+    /// to the check in progress.
+    pub fn record_untracked_factors(&self, factors: usize) {
+        self.untracked_factors.fetch_add(factors, AcqRel);
+    }
+
+    pub fn finish(&self, snapshot: WorkSnapshot) {
+        // When the CPU budget has reset since the snapshot, we can't tell how much was spent
+        // before the reset, so only count what's been spent since.
+        let cpu_tenths_spent = CPU_TENTHS_SPENT_LAST_CHECK.load(Acquire);
+        let cpu_tenths = cpu_tenths_spent
+            .checked_sub(snapshot.cpu_tenths_spent)
+            .unwrap_or(cpu_tenths_spent);
+        let untracked_factors = self
+            .untracked_factors
+            .load(Acquire)
+            .saturating_sub(snapshot.untracked_factors);
+        let factors = RUN_STATS
+            .factors_accepted()
+            .saturating_sub(snapshot.factors_accepted)
+            .saturating_sub(untracked_factors);
+        self.record(snapshot.category, cpu_tenths, factors);
+    }
+
+    /// Selection weight of each category, indexed by [WorkCategory]. The weights sum to 1. A
+    /// category that hasn't been measured yet counts as having yielded a factor for a tenth of a
+    /// second, so that it gets tried.
+    pub fn weights(&self) -> [f64; CATEGORY_COUNT] {
+        let stats = *self.stats.lock().unwrap();
+        let yields = stats.map(|stats| (stats.factors + 1) as f64 / (stats.cpu_tenths + 1) as f64);
+        let total_yield: f64 = yields.iter().sum();
+        let scale = 1.0 - MIN_WEIGHT * CATEGORY_COUNT as f64;
+        yields.map(|category_yield| MIN_WEIGHT + scale * category_yield / total_yield)
+    }
+
+    /// Randomly decides whether the next check should prefer a C over a PRP, in proportion to
+    /// their weights.
+    pub fn prefer_c_over_prp(&self, rng: &mut impl RngExt) -> bool {
+        rng.random_bool(self.c_share_vs_prp())
+    }
+
+    fn c_share_vs_prp(&self) -> f64 {
+        let weights = self.weights();
+        let c_weight = weights[WorkCategory::C as usize];
+        c_weight / (c_weight + weights[WorkCategory::Prp as usize])
+    }
+
+    pub fn log_weights(&self) {
+        let [prp, c] = self.weights();
+        info!("Work weights: PRP {prp:.3}, C {c:.3}");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::ratio::WorkCategory::{C, Prp};
+    use crate::ratio::{MIN_WEIGHT, WorkRatioController};
+    use rand::SeedableRng;
+    use rand::rngs::StdRng;
+
+    #[test]
+    fn test_work_ratio_controller() {
+        let controller = WorkRatioController::new();
+        // Nothing measured yet, so all categories are equal
+        assert!(controller.weights().iter().all(|w| (w - 0.5).abs() < 1e-9));
+        assert!((controller.c_share_vs_prp() - 0.5).abs() < 1e-9);
+
+        controller.record(Prp, 999, 0);
+        controller.record(C, 99, 9);
+        let weights = controller.weights();
+        assert!((weights.iter().sum::<f64>() - 1.0).abs() < 1e-9);
+        assert!(weights[C as usize] > weights[Prp as usize]);
+        assert!(weights.iter().all(|&w| w >= MIN_WEIGHT));
+        assert!(controller.c_share_vs_prp() > 0.9);
+
+        // PRP's become the most productive
+        controller.record(Prp, 0, 1000);
+        let weights = controller.weights();
+        assert!(weights[Prp as usize] > weights[C as usize]);
+        assert!(controller.c_share_vs_prp() < 0.5);
+        controller.log_weights();
+
+        // The choice only depends on the generator it's given
+        let choices = |seed| {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..64)
+                .map(|_| controller.prefer_c_over_prp(&mut rng))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(choices(42), choices(42));
+    }
+
+    #[test]
+    fn test_untracked_factors_not_credited() {
+        use crate::stats::RUN_STATS;
+
+        let controller = WorkRatioController::new();
+        let work = controller.start(C);
+        RUN_STATS.record_factor_accepted();
+        RUN_STATS.record_factor_accepted();
+        // One of them came from a U submission running at the same time
+        controller.record_untracked_factors(1);
+        controller.finish(work);
+        let stats = controller.stats.lock().unwrap();
+        assert_eq!(stats[C as usize].factors, 1);
+        assert_eq!(stats[Prp as usize].factors, 0);
+    }
+}
@@ -0,0 +1,87 @@
+use crate::NumberSpecifier::Expression;
+use crate::algebraic::Factor;
+use crate::graph::EntryId;
+use crate::net::FactorDbClient;
+use crate::net::NumberStatus::Prime;
+use async_backtrace::framed;
+use log::info;
+use std::borrow::Cow::Owned;
+
+/// Returns `2p+1` and `(p-1)/2`. If either is prime, then `p` is a Sophie Germain prime or a safe
+/// prime respectively.
+fn siblings(p: &str) -> [Factor; 2] {
+    [
+        Factor::from(format!("2*({p})+1").as_str()),
+        Factor::from(format!("(({p})-1)/2").as_str()),
+    ]
+}
+
+/// Whether the PRP `p` is a Sophie Germain or safe prime candidate, i.e. whether FactorDB already
+/// knows `2p+1` or `(p-1)/2` to be prime or PRP. Each sibling costs one status lookup, so this is
+/// only used when the operator has asked for such PRPs to be prioritized.
+#[framed]
+pub async fn has_prime_sibling(http: &impl FactorDbClient, id: EntryId, p: &str) -> bool {
+    let p_factor = Factor::from(p);
+    if p_factor.is_elided() {
+        // Can't build the siblings' expressions without all the digits
+        return false;
+    }
+    for sibling in siblings(p) {
+        let status = http
+            .known_factors_as_digits(Expression(Owned(sibling.clone())), false, false)
+            .await
+            .status;
+        if status == Some(Prime) {
+            info!("{id}: {sibling} is also prime, so prioritizing this PRP");
+            return true;
+        }
+    }
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::NumberSpecifier::Expression;
+    use crate::algebraic::evaluate_as_numeric;
+    use crate::net::MockFactorDbClient;
+    use crate::net::NumberStatus::{Prime, UnfactoredComposite};
+    use crate::net::ProcessedStatusApiResponse;
+    use crate::safe_primes::has_prime_sibling;
+
+    /// Mocks FactorDB's status for the numbers whose values are listed as prime.
+    fn mock_statuses(primes: &'static [u128]) -> MockFactorDbClient {
+        let mut http = MockFactorDbClient::new();
+        http.expect_known_factors_as_digits()
+            .returning(move |id, _, _| {
+                let Expression(expr) = id else {
+                    panic!("Looked up an ID instead of a sibling expression");
+                };
+                let value = evaluate_as_numeric(&expr).unwrap();
+                ProcessedStatusApiResponse {
+                    status: Some(if primes.contains(&value) {
+                        Prime
+                    } else {
+                        UnfactoredComposite
+                    }),
+                    factors: Box::new([]),
+                    id: None,
+                }
+            });
+        http
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 1)]
+    async fn test_has_prime_sibling() {
+        // 11 is a Sophie Germain prime, since 23 is prime
+        assert!(has_prime_sibling(&mock_statuses(&[23]), 1, "11").await);
+        // 23 is a safe prime, since 11 is prime
+        assert!(has_prime_sibling(&mock_statuses(&[11]), 1, "23").await);
+        // 13 is neither: 27 and 6 are composite
+        assert!(!has_prime_sibling(&mock_statuses(&[]), 1, "13").await);
+
+        // Elided numbers are never looked up
+        let mut http = MockFactorDbClient::new();
+        http.expect_known_factors_as_digits().never();
+        assert!(!has_prime_sibling(&http, 1, "12345678901234567890...12345").await);
+    }
+}
@@ -0,0 +1,65 @@
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng, rng};
+use std::sync::{Mutex, OnceLock};
+
+/// Where [shuffle_rng] gets its randomness. `main` installs a seeded one when the `SHUFFLE_SEED`
+/// environment variable is set.
+pub static SHUFFLE_RNG: OnceLock<ShuffleRng> = OnceLock::new();
+
+/// Source of the randomness used to shuffle search and submission order. Seeded, it hands out the
+/// same sequence of generators on every run, so that a run can be reproduced for debugging. That
+/// only covers the order in which generators are requested: a generator owned by one task is
+/// reproducible, but the shuffles in graph submission fork from here whenever a task reaches
+/// them, so they only repeat when the tasks interleave the same way.
+#[derive(Debug)]
+pub struct ShuffleRng {
+    seeded: Option<Mutex<StdRng>>,
+}
+
+impl ShuffleRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        ShuffleRng {
+            seeded: seed.map(|seed| Mutex::new(StdRng::seed_from_u64(seed))),
+        }
+    }
+
+    /// Returns a generator for one shuffle, seeded from entropy unless this was given a seed.
+    pub fn fork(&self) -> StdRng {
+        let seed = match self.seeded {
+            Some(ref seeded) => seeded.lock().unwrap().next_u64(),
+            None => rng().next_u64(),
+        };
+        StdRng::seed_from_u64(seed)
+    }
+}
+
+/// Returns a generator for one shuffle from [SHUFFLE_RNG].
+pub fn shuffle_rng() -> StdRng {
+    SHUFFLE_RNG.get_or_init(|| ShuffleRng::new(None)).fork()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::shuffle::ShuffleRng;
+    use rand::seq::SliceRandom;
+
+    fn shuffle_orders(shuffle_rng: &ShuffleRng) -> Vec<Vec<u32>> {
+        (0..3)
+            .map(|_| {
+                let mut items: Vec<u32> = (0..100).collect();
+                items.shuffle(&mut shuffle_rng.fork());
+                items
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_seeded_shuffles_repeat() {
+        let orders = shuffle_orders(&ShuffleRng::new(Some(42)));
+        assert_eq!(orders, shuffle_orders(&ShuffleRng::new(Some(42))));
+        // Successive shuffles in a run still differ from each other
+        assert_ne!(orders[0], orders[1]);
+        assert_ne!(orders[1], orders[2]);
+        assert_ne!(orders, shuffle_orders(&ShuffleRng::new(Some(43))));
+    }
+}
@@ -0,0 +1,188 @@
+use crate::algebraic::Factor;
+use crate::graph::{Divisibility, EntryId};
+use crate::net::NumberStatus;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use log::{error, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fs;
+use std::io::{self, ErrorKind, Read, Write};
+use std::path::{Path, PathBuf};
+
+/// Where divisibility graphs are saved while [crate::GRAPH_SNAPSHOT_INTERVAL] is set.
+pub const GRAPH_SNAPSHOT_DIR: &str = "graph-snapshots";
+
+/// A divisibility graph as saved to disk partway through submitting factors, so that a restart
+/// resumes with the same graph for the same root instead of rediscovering it. A root is identified
+/// by its ID together with [root_hash], since one ID's factors can be submitted through several
+/// roots. Vertices are referred to by their position in `vertices`, since vertex IDs don't survive
+/// a reload.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GraphSnapshot {
+    pub root_id: EntryId,
+    pub root_hash: u64,
+    pub vertices: Vec<VertexSnapshot>,
+    pub edges: Vec<(usize, usize, Divisibility)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VertexSnapshot {
+    pub expr: String,
+    pub entry_id: Option<EntryId>,
+    pub last_known_status: Option<NumberStatus>,
+    pub factors_known_to_factordb: Vec<usize>,
+    pub factors_up_to_date: bool,
+    pub checked_for_listed_algebraic: bool,
+    pub checked_in_factor_finder: bool,
+    pub expression_form_checked_in_factor_finder: bool,
+    pub checked_for_known_common_factors: bool,
+}
+
+/// Hash of a root's expression that stays the same across restarts, unlike [std::hash::Hash].
+pub fn root_hash(root: &Factor) -> u64 {
+    // 64-bit FNV-1a
+    root.to_unelided_string()
+        .bytes()
+        .fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(0x100_0000_01b3)
+        })
+}
+
+fn snapshot_path(dir: &Path, root_id: EntryId, root_hash: u64, compressed: bool) -> PathBuf {
+    if compressed {
+        dir.join(format!("{root_id}-{root_hash:016x}.json.gz"))
+    } else {
+        dir.join(format!("{root_id}-{root_hash:016x}.json"))
+    }
+}
+
+fn read_json(path: &Path, compressed: bool) -> io::Result<String> {
+    if compressed {
+        let mut json = String::new();
+        GzDecoder::new(fs::File::open(path)?).read_to_string(&mut json)?;
+        Ok(json)
+    } else {
+        fs::read_to_string(path)
+    }
+}
+
+fn remove_if_present(path: &Path) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+        _ => Ok(()),
+    }
+}
+
+impl GraphSnapshot {
+    /// Saves the snapshot in `dir`, gzipped if `compress` is true, since the graphs for big
+    /// composites can take up a lot of space.
+    pub fn save(&self, dir: &Path, compress: bool) {
+        let root_id = self.root_id;
+        let root_hash = self.root_hash;
+        let path = snapshot_path(dir, root_id, root_hash, compress);
+        let json = match serde_json::to_string(self) {
+            Ok(json) => json,
+            Err(e) => {
+                error!("{root_id}: Failed to serialize divisibility graph: {e}");
+                return;
+            }
+        };
+        let contents = if compress {
+            let mut encoder = GzEncoder::new(Vec::new(), Compression::default());
+            encoder
+                .write_all(json.as_bytes())
+                .and_then(|_| encoder.finish())
+        } else {
+            Ok(json.into_bytes())
+        };
+        // Write to a temporary file first, so that a crash mid-write can't lose the old snapshot
+        let temp_path = path.with_extension("tmp");
+        if let Err(e) = contents
+            .and_then(|contents| {
+                fs::create_dir_all(dir).and_then(|_| fs::write(&temp_path, contents))
+            })
+            .and_then(|_| fs::rename(&temp_path, &path))
+            // Otherwise a stale snapshot in the other format could be loaded instead
+            .and_then(|_| remove_if_present(&snapshot_path(dir, root_id, root_hash, !compress)))
+        {
+            error!(
+                "{root_id}: Failed to save divisibility graph to {}: {e}",
+                path.display()
+            );
+        }
+    }
+
+    /// Reads the snapshot saved for the root with ID `root_id` and [root_hash] `root_hash`, if any,
+    /// whether or not it was compressed.
+    pub fn load(dir: &Path, root_id: EntryId, root_hash: u64) -> Option<Self> {
+        let compressed_path = snapshot_path(dir, root_id, root_hash, true);
+        let (path, json) = match read_json(&compressed_path, true) {
+            Err(e) if e.kind() == ErrorKind::NotFound => {
+                let path = snapshot_path(dir, root_id, root_hash, false);
+                let json = read_json(&path, false);
+                (path, json)
+            }
+            json => (compressed_path, json),
+        };
+        match json {
+            Ok(json) => match serde_json::from_str::<Self>(&json) {
+                Ok(snapshot) if snapshot.root_id == root_id && snapshot.root_hash == root_hash => {
+                    Some(snapshot)
+                }
+                Ok(snapshot) => {
+                    warn!(
+                        "{root_id}: Ignoring {}, which holds the graph for {}",
+                        path.display(),
+                        snapshot.root_id
+                    );
+                    None
+                }
+                Err(e) => {
+                    warn!(
+                        "{root_id}: Ignoring corrupt snapshot {}: {e}",
+                        path.display()
+                    );
+                    None
+                }
+            },
+            Err(e) if e.kind() == ErrorKind::NotFound => None,
+            Err(e) => {
+                error!("{root_id}: Failed to read {}: {e}", path.display());
+                None
+            }
+        }
+    }
+
+    /// Removes the snapshot for a root once its submission has finished.
+    pub fn delete(dir: &Path, root_id: EntryId, root_hash: u64) {
+        for compressed in [false, true] {
+            if let Err(e) = remove_if_present(&snapshot_path(dir, root_id, root_hash, compressed)) {
+                error!("{root_id}: Failed to delete divisibility graph snapshot: {e}");
+            }
+        }
+    }
+
+    /// IDs that have at least one snapshot saved in `dir`, so that they can be queued again after a
+    /// restart rather than waiting to come up in a search.
+    pub fn saved_root_ids(dir: &Path) -> BTreeSet<EntryId> {
+        let entries = match fs::read_dir(dir) {
+            Ok(entries) => entries,
+            Err(e) if e.kind() == ErrorKind::NotFound => return BTreeSet::new(),
+            Err(e) => {
+                error!("Failed to list divisibility graph snapshots: {e}");
+                return BTreeSet::new();
+            }
+        };
+        entries
+            .filter_map(|entry| {
+                let name = entry.ok()?.file_name().into_string().ok()?;
+                let stem = name
+                    .strip_suffix(".json.gz")
+                    .or_else(|| name.strip_suffix(".json"))?;
+                stem.split_once('-')?.0.parse().ok()
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,124 @@
+use log::warn;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+
+/// Counters for the work done during this run, shared by all tasks and logged on shutdown.
+#[derive(Debug)]
+pub struct RunStats {
+    prp_checks: AtomicUsize,
+    us_queued: AtomicUsize,
+    cs_dispatched_to_yafu: AtomicUsize,
+    factors_accepted: AtomicUsize,
+    cpu_tenths_consumed: AtomicUsize,
+    /// CPU tenths reported by the most recent resource check, so that only the difference is
+    /// added to `cpu_tenths_consumed`. `usize::MAX` until the first check, whose total includes
+    /// time spent before this run.
+    last_cpu_tenths_spent: AtomicUsize,
+    prp_start: AtomicU64,
+}
+
+pub static RUN_STATS: RunStats = RunStats::new();
+
+impl Default for RunStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl RunStats {
+    pub const fn new() -> Self {
+        RunStats {
+            prp_checks: AtomicUsize::new(0),
+            us_queued: AtomicUsize::new(0),
+            cs_dispatched_to_yafu: AtomicUsize::new(0),
+            factors_accepted: AtomicUsize::new(0),
+            cpu_tenths_consumed: AtomicUsize::new(0),
+            last_cpu_tenths_spent: AtomicUsize::new(usize::MAX),
+            prp_start: AtomicU64::new(0),
+        }
+    }
+
+    pub fn record_prp_check(&self) {
+        self.prp_checks.fetch_add(1, Relaxed);
+    }
+
+    pub fn record_u_queued(&self) {
+        self.us_queued.fetch_add(1, Relaxed);
+    }
+
+    pub fn record_c_dispatched_to_yafu(&self) {
+        self.cs_dispatched_to_yafu.fetch_add(1, Relaxed);
+    }
+
+    pub fn record_factor_accepted(&self) {
+        self.factors_accepted.fetch_add(1, Relaxed);
+    }
+
+    pub fn factors_accepted(&self) -> usize {
+        self.factors_accepted.load(Relaxed)
+    }
+
+    /// Records the CPU time FactorDB reports as spent in the current cycle. When the count has
+    /// gone down, the cycle has reset, and everything spent since then is new.
+    pub fn record_cpu_tenths_spent(&self, cpu_tenths_spent: usize) {
+        let last = self.last_cpu_tenths_spent.swap(cpu_tenths_spent, AcqRel);
+        let delta = if last == usize::MAX {
+            0
+        } else {
+            cpu_tenths_spent
+                .checked_sub(last)
+                .unwrap_or(cpu_tenths_spent)
+        };
+        self.cpu_tenths_consumed.fetch_add(delta, Relaxed);
+    }
+
+    pub fn set_prp_start(&self, prp_start: u64) {
+        self.prp_start.store(prp_start, Release);
+    }
+
+    pub fn log_summary(&self) {
+        warn!(
+            "Run summary: {} PRP checks, {} U's queued, {} C's dispatched to yafu, \
+            {} factors accepted, {:.1} CPU seconds consumed, final PRP start {}",
+            self.prp_checks.load(Relaxed),
+            self.us_queued.load(Relaxed),
+            self.cs_dispatched_to_yafu.load(Relaxed),
+            self.factors_accepted.load(Relaxed),
+            self.cpu_tenths_consumed.load(Relaxed) as f64 * 0.1,
+            self.prp_start.load(Acquire)
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::stats::RunStats;
+    use std::sync::atomic::Ordering::Relaxed;
+
+    #[test]
+    fn test_run_stats() {
+        let stats = RunStats::new();
+        for _ in 0..3 {
+            stats.record_prp_check();
+        }
+        stats.record_u_queued();
+        stats.record_u_queued();
+        stats.record_c_dispatched_to_yafu();
+        stats.record_factor_accepted();
+        stats.record_cpu_tenths_spent(20);
+        stats.record_cpu_tenths_spent(50);
+        stats.record_cpu_tenths_spent(140);
+        // CPU budget reset
+        stats.record_cpu_tenths_spent(30);
+        stats.record_cpu_tenths_spent(30);
+        stats.set_prp_start(3200);
+        assert_eq!(stats.prp_checks.load(Relaxed), 3);
+        assert_eq!(stats.us_queued.load(Relaxed), 2);
+        assert_eq!(stats.cs_dispatched_to_yafu.load(Relaxed), 1);
+        assert_eq!(stats.factors_accepted.load(Relaxed), 1);
+        assert_eq!(stats.cpu_tenths_consumed.load(Relaxed), 150);
+        assert_eq!(stats.prp_start.load(Relaxed), 3200);
+        stats.log_summary();
+    }
+}
@@ -0,0 +1,156 @@
+use atomic_time::AtomicInstant;
+use log::error;
+use std::sync::atomic::Ordering::{Acquire, Release};
+use std::sync::atomic::{AtomicBool, AtomicU64};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Seconds a task can go without a heartbeat before [WATCHDOG] reports it as stuck. FactorDB can
+/// throttle us for up to an hour, so this has to be longer than that. Overridden by the
+/// `WATCHDOG_TIMEOUT_SECS` environment variable.
+pub static WATCHDOG_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(2 * 60 * 60);
+
+/// Heartbeats of the long-running tasks, checked by the monitoring task.
+pub static WATCHDOG: Watchdog = Watchdog::new();
+
+/// When a long-running task last showed that it was still making progress.
+#[derive(Debug)]
+pub struct Heartbeat {
+    name: &'static str,
+    last_beat: AtomicInstant,
+    /// Whether the task is waiting for work, which can take any length of time without it being
+    /// stuck.
+    idle: AtomicBool,
+}
+
+/// Marks a [Heartbeat] as busy again when dropped, even if the wait it covered was cancelled.
+struct IdleGuard<'a>(&'a Heartbeat);
+
+impl Drop for IdleGuard<'_> {
+    fn drop(&mut self) {
+        self.0.idle.store(false, Release);
+        self.0.beat();
+    }
+}
+
+impl Heartbeat {
+    pub fn beat(&self) {
+        self.last_beat.store(Instant::now(), Release);
+    }
+
+    /// Awaits `future`, such as a receive from an empty queue or a send to a full one, without the
+    /// task counting as stuck however long it takes.
+    pub async fn idle_while<F: IntoFuture>(&self, future: F) -> F::Output {
+        self.idle.store(true, Release);
+        let _guard = IdleGuard(self);
+        future.await
+    }
+}
+
+/// Tracks the heartbeats of tasks that are meant to loop forever, so that one that's deadlocked or
+/// waiting on a request that never completes doesn't go unnoticed.
+#[derive(Debug, Default)]
+pub struct Watchdog {
+    heartbeats: Mutex<Vec<Arc<Heartbeat>>>,
+}
+
+impl Watchdog {
+    pub const fn new() -> Self {
+        Watchdog {
+            heartbeats: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Starts watching the task called `name`, which should then beat the returned heartbeat on
+    /// every iteration of its main loop.
+    pub fn register(&self, name: &'static str) -> Arc<Heartbeat> {
+        let heartbeat = Arc::new(Heartbeat {
+            name,
+            last_beat: AtomicInstant::now(),
+            idle: AtomicBool::new(false),
+        });
+        self.heartbeats.lock().unwrap().push(heartbeat.clone());
+        heartbeat
+    }
+
+    /// Logs an error for each task whose last heartbeat is older than `timeout` and that isn't
+    /// waiting for work, and returns their names.
+    pub fn check(&self, timeout: Duration) -> Vec<&'static str> {
+        let now = Instant::now();
+        self.heartbeats
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|heartbeat| !heartbeat.idle.load(Acquire))
+            .filter_map(|heartbeat| {
+                let since_last_beat =
+                    now.saturating_duration_since(heartbeat.last_beat.load(Acquire));
+                if since_last_beat > timeout {
+                    error!(
+                        "{}: No heartbeat for {since_last_beat:?}; task may be stuck",
+                        heartbeat.name
+                    );
+                    Some(heartbeat.name)
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::watchdog::Watchdog;
+    use futures_util::poll;
+    use std::pin::pin;
+    use std::sync::atomic::Ordering::Release;
+    use std::time::{Duration, Instant};
+    use tokio::sync::mpsc::channel;
+
+    #[test]
+    fn test_frozen_heartbeat_triggers_watchdog() {
+        const TIMEOUT: Duration = Duration::from_secs(60);
+
+        let watchdog = Watchdog::new();
+        let frozen = watchdog.register("frozen");
+        let alive = watchdog.register("alive");
+        assert!(watchdog.check(TIMEOUT).is_empty());
+
+        frozen
+            .last_beat
+            .store(Instant::now() - 2 * TIMEOUT, Release);
+        alive.beat();
+        assert_eq!(watchdog.check(TIMEOUT), ["frozen"]);
+
+        // Once the task beats again, it's no longer reported
+        frozen.beat();
+        assert!(watchdog.check(TIMEOUT).is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_idle_task_not_reported() {
+        const TIMEOUT: Duration = Duration::from_secs(60);
+
+        let watchdog = Watchdog::new();
+        let waiting = watchdog.register("waiting");
+        let (sender, mut receiver) = channel(1);
+        let mut wait = pin!(waiting.idle_while(receiver.recv()));
+        // Stuck in the receive since long before the timeout, but only because nothing's queued
+        assert!(poll!(wait.as_mut()).is_pending());
+        waiting
+            .last_beat
+            .store(Instant::now() - 2 * TIMEOUT, Release);
+        assert!(watchdog.check(TIMEOUT).is_empty());
+
+        sender.send(()).await.unwrap();
+        assert_eq!(wait.await, Some(()));
+        assert!(watchdog.check(TIMEOUT).is_empty());
+
+        // Once the task is busy again, a missing heartbeat is reported as usual
+        waiting
+            .last_beat
+            .store(Instant::now() - 2 * TIMEOUT, Release);
+        assert_eq!(watchdog.check(TIMEOUT), ["waiting"]);
+    }
+}
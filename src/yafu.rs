@@ -0,0 +1,97 @@
+use hipstr::HipStr;
+use log::error;
+use std::io::Write;
+use std::thread;
+use tokio::sync::mpsc::{Sender, channel};
+
+/// How many composites may wait to be written to yafu's FIFO before more are requeued instead.
+pub const YAFU_STAGING_CAPACITY: usize = 64;
+
+/// Feeds composites to yafu without ever blocking the caller. Writes happen on a dedicated thread,
+/// so a FIFO whose reader has stalled only fills the staging buffer, and once that's full,
+/// [YafuDispatcher::try_dispatch] refuses further composites so the caller can requeue them.
+pub struct YafuDispatcher {
+    staged: Sender<HipStr<'static>>,
+}
+
+impl YafuDispatcher {
+    pub fn new(mut out: impl Write + Send + 'static, capacity: usize) -> Self {
+        let (staged, mut receiver) = channel::<HipStr<'static>>(capacity);
+        thread::Builder::new()
+            .name("yafu-dispatch".into())
+            .spawn(move || {
+                while let Some(composite) = receiver.blocking_recv() {
+                    let line = format!("{composite}\n");
+                    if let Err(e) = out.write_all(line.as_bytes()).and_then(|_| out.flush()) {
+                        error!("Failed to write {composite} to yafu's FIFO: {e}");
+                    }
+                }
+            })
+            .expect("Failed to start the yafu dispatch thread");
+        YafuDispatcher { staged }
+    }
+
+    /// Stages `composite` to be written, unless the staging buffer is full, in which case it
+    /// returns false.
+    pub fn try_dispatch(&self, composite: HipStr<'static>) -> bool {
+        self.staged.try_send(composite).is_ok()
+    }
+
+    /// Number of composites staged but not yet taken by the writer thread.
+    pub fn staging_depth(&self) -> usize {
+        self.staged.max_capacity() - self.staged.capacity()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::yafu::YafuDispatcher;
+    use std::io::{self, Write};
+    use std::sync::mpsc::{Receiver, SyncSender, sync_channel};
+
+    /// A FIFO whose reader has stalled: every write blocks until the test releases it.
+    struct StalledFifo {
+        entered_write: SyncSender<()>,
+        release: Receiver<()>,
+    }
+
+    impl Write for StalledFifo {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let _ = self.entered_write.send(());
+            self.release.recv().map_err(io::Error::other)?;
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_full_staging_buffer_refuses_composites() {
+        let (entered_write, writes_entered) = sync_channel(16);
+        let (release_write, release) = sync_channel(16);
+        let yafu = YafuDispatcher::new(
+            StalledFifo {
+                entered_write,
+                release,
+            },
+            2,
+        );
+        assert!(yafu.try_dispatch("2^1001-1".into()));
+        // Wait until the writer thread is stuck writing the first composite
+        writes_entered.recv().unwrap();
+        assert_eq!(yafu.staging_depth(), 0);
+        assert!(yafu.try_dispatch("2^1003-1".into()));
+        assert!(yafu.try_dispatch("2^1005-1".into()));
+        assert_eq!(yafu.staging_depth(), 2);
+        // Would have blocked on the FIFO, so the caller gets it back to requeue
+        assert!(!yafu.try_dispatch("2^1007-1".into()));
+
+        // Once the reader catches up, there's room again
+        release_write.send(()).unwrap();
+        writes_entered.recv().unwrap();
+        assert!(yafu.try_dispatch("2^1007-1".into()));
+        drop(release_write);
+    }
+}
@@ -0,0 +1,35 @@
+use std::process::Command;
+
+fn run(args: &[&str]) -> (bool, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_ScrapeFactordbPrpsRust"))
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.success(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn test_factor_subcommand() {
+    let (success, stdout) = run(&["factor", "2^16-1"]);
+    assert!(success);
+    let factors: Vec<_> = stdout.lines().collect();
+    for factor in ["3", "5", "17", "257"] {
+        assert!(factors.contains(&factor), "{factor} missing from {stdout}");
+    }
+}
+
+#[test]
+fn test_eval_subcommand() {
+    let (success, stdout) = run(&["eval", "2^10+1", "5!"]);
+    assert!(success);
+    assert_eq!(stdout, "1025\n120\n");
+}
+
+#[test]
+fn test_unknown_subcommand() {
+    let (success, _) = run(&["frobnicate"]);
+    assert!(!success);
+}